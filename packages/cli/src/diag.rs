@@ -0,0 +1,281 @@
+use std::sync::Mutex;
+
+/// Format of the diagnostics from the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Human-readable messages, with the source code for errors in TypeScript inputs
+    #[default]
+    Human,
+    /// One JSON object per line in stdout for each diagnostic, then a summary object
+    Json,
+}
+
+/// Severity of a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Stable code for each kind of diagnostic, for tools that consume the diagnostics.
+///
+/// Codes are never reused for a different kind of diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    /// An error without a more specific code, such as failing to write the output
+    Other,
+    /// Syntax error in an input file
+    Syntax,
+    /// An interface member that is not a method, such as a property or an index signature
+    UnsupportedMember,
+    /// Type parameters on interfaces or methods
+    UnsupportedGenerics,
+    /// Interface inheritance or supertraits
+    UnsupportedInheritance,
+    /// Duplicate interface or function names
+    Duplicate,
+    /// Interface without functions
+    EmptyInterface,
+    /// Interface or method name that cannot be used
+    InvalidName,
+    /// Argument that cannot be sent to the other side, or is not declared in a supported way
+    InvalidArgument,
+    /// Return type that is not supported
+    InvalidReturnType,
+    /// Invalid doc tag on a function, such as `@timeout` or `@transfer`
+    InvalidDocTag,
+    /// Rust type that cannot be declared in TypeScript
+    UnsupportedType,
+    /// Import statement that cannot be processed
+    InvalidImport,
+    /// Input file that cannot be read or used
+    InvalidInput,
+    /// Invalid CLI option, including links to interfaces that don't exist
+    InvalidOption,
+    /// Name that clashes with the names in the generated code
+    NameClash,
+    /// `@param` tag that does not match any argument
+    ParamMismatch,
+    /// Function marked with `@deprecated`
+    Deprecated,
+    /// Feature that is ignored in the Rust bindings, such as cancellation
+    UnsupportedInRust,
+}
+
+impl Code {
+    /// The code in diagnostics, such as `E0001`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Other => "E0000",
+            Self::Syntax => "E0001",
+            Self::UnsupportedMember => "E0002",
+            Self::UnsupportedGenerics => "E0003",
+            Self::UnsupportedInheritance => "E0004",
+            Self::Duplicate => "E0005",
+            Self::EmptyInterface => "E0006",
+            Self::InvalidName => "E0007",
+            Self::InvalidArgument => "E0008",
+            Self::InvalidReturnType => "E0009",
+            Self::InvalidDocTag => "E0010",
+            Self::UnsupportedType => "E0011",
+            Self::InvalidImport => "E0012",
+            Self::InvalidInput => "E0013",
+            Self::InvalidOption => "E0100",
+            Self::NameClash => "E0101",
+            Self::ParamMismatch => "W0001",
+            Self::Deprecated => "W0002",
+            Self::UnsupportedInRust => "W0003",
+        }
+    }
+
+    pub fn severity(self) -> Severity {
+        match self {
+            Self::ParamMismatch | Self::Deprecated | Self::UnsupportedInRust => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// Span in an input file. Lines and columns are 1-based, and the end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+}
+
+/// A suggested edit that fixes a diagnostic
+#[derive(Debug, Clone)]
+pub struct Fix {
+    /// Short description of the edit
+    pub title: String,
+    /// Span in the same file as the diagnostic to replace
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A diagnostic, optionally with the location in an input file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: Code,
+    pub message: String,
+    /// Path of the input file, as given to the CLI
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    /// Suggested edit, which is offered as a quick fix by the language server
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            file: None,
+            span: None,
+            fix: None,
+        }
+    }
+
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    /// The location as `file:line:column`, or just the file if there's no span
+    fn location(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(match self.span {
+            Some(span) => format!("{file}:{}:{}", span.line_start, span.column_start),
+            None => file.clone(),
+        })
+    }
+
+    fn to_json(&self) -> String {
+        let severity = match self.code.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let file = match &self.file {
+            Some(file) => json_string(file),
+            None => "null".to_string(),
+        };
+        let span = match self.span {
+            Some(span) => format!(
+                "{{\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{}}}",
+                span.line_start, span.column_start, span.line_end, span.column_end
+            ),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"reason\":\"diagnostic\",\"severity\":\"{severity}\",\"code\":\"{}\",\"message\":{},\"file\":{file},\"span\":{span}}}",
+            self.code.as_str(),
+            json_string(&self.message)
+        )
+    }
+}
+
+struct State {
+    format: MessageFormat,
+    errors: usize,
+    warnings: usize,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    format: MessageFormat::Human,
+    errors: 0,
+    warnings: 0,
+});
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    // the state is always valid, even if another thread panicked
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Set the format of the diagnostics, before any diagnostic is reported
+pub fn set_format(format: MessageFormat) {
+    state().format = format;
+}
+
+pub fn is_json() -> bool {
+    state().format == MessageFormat::Json
+}
+
+/// Report a diagnostic. It's printed to stdout as JSON, or logged in the human format
+pub fn report(diagnostic: Diagnostic) {
+    count(diagnostic.code.severity());
+    if is_json() {
+        println!("{}", diagnostic.to_json());
+        return;
+    }
+    let message = match diagnostic.location() {
+        Some(location) => format!("{location}: {}", diagnostic.message),
+        None => diagnostic.message,
+    };
+    match diagnostic.code.severity() {
+        Severity::Error => cu::error!("{message}"),
+        Severity::Warning => cu::warn!("{message}"),
+    }
+}
+
+/// Count a diagnostic that is shown in the human format by other means
+pub fn count(severity: Severity) {
+    let mut state = state();
+    match severity {
+        Severity::Error => state.errors += 1,
+        Severity::Warning => state.warnings += 1,
+    }
+}
+
+/// Report the error that stops the CLI as a diagnostic with the code, if no errors
+/// are reported before it (which would be the cause). The error is returned as is,
+/// since it's also shown in the human format when the CLI exits
+pub fn fatal(code: Code, error: cu::Error) -> cu::Error {
+    if is_json() && state().errors == 0 {
+        report(Diagnostic::new(code, format!("{error:#}")));
+    }
+    error
+}
+
+/// Print the summary object after all diagnostics in JSON format,
+/// similar to the `build-finished` message from cargo
+pub fn print_summary(success: bool) {
+    let state = state();
+    if state.format != MessageFormat::Json {
+        return;
+    }
+    println!(
+        "{{\"reason\":\"summary\",\"success\":{success},\"errors\":{},\"warnings\":{}}}",
+        state.errors, state.warnings
+    );
+}
+
+/// Quote and escape a string for JSON
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
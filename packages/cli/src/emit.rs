@@ -1,200 +1,585 @@
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use codize::{Concat, cblock, cconcat};
+use codize::{Code, Concat, cblock, cconcat};
 
 use crate::ir;
 
-fn header() -> Concat {
-    cconcat![
-        "/*",
-        " * This file is generated by the workex CLI Tool",
-        " *",
-        " * Please visit https://workex.pistonite.dev for more information",
-        " */",
-        "",
-    ]
+mod decl;
+mod dts;
+mod header;
+mod index;
+mod loopback;
+mod mock;
+mod rust;
+mod single;
+mod style;
+
+/// Generated files in memory, by path
+#[derive(Debug, Default)]
+pub struct Output {
+    /// The generated files, by path
+    pub files: BTreeMap<PathBuf, String>,
+    /// Directory to empty before writing the files, since the output directory
+    /// is only for the generated files
+    pub clean_dir: Option<PathBuf>,
 }
 
-/// Emit the output
+impl Output {
+    /// Add a generated file
+    pub fn add(&mut self, path: &Path, content: String) {
+        self.files.insert(path.to_path_buf(), content);
+    }
+
+    /// Write the generated files
+    pub fn write(&self) -> cu::Result<()> {
+        if let Some(dir) = &self.clean_dir {
+            cu::fs::make_dir_empty(dir)?;
+        }
+        for (path, content) in &self.files {
+            cu::fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Render and write the output
 pub fn emit(pkg: &ir::Package) -> cu::Result<()> {
-    let func_map = make_func_id_map(pkg);
+    render(pkg)?.write()
+}
+
+/// Render the output into memory
+pub fn render(pkg: &ir::Package) -> cu::Result<Output> {
+    let mut output = Output::default();
+    let out = &mut output;
+    let func_map = ir::make_func_id_map(&pkg.interfaces);
     let out_dir = &pkg.out_dir;
-    cu::fs::make_dir_empty(out_dir)?;
+
+    // declarations for Rust inputs are generated next to the input files
+    if let Some(in_dir) = out_dir.parent() {
+        for decl_file in &pkg.decl_files {
+            decl::emit_decl_file(out, pkg, decl_file, in_dir)?;
+        }
+    }
+
+    if let Some(path) = &pkg.single_file {
+        single::emit_single_file(out, pkg, &func_map, path)?;
+    } else {
+        emit_interfaces_dir(out, pkg, &func_map, out_dir)?;
+    }
+
+    if let Some(rust_out) = &pkg.rust_out {
+        rust::emit_rust(out, pkg, &func_map, rust_out)?;
+    }
+
+    Ok(output)
+}
+
+/// Emit the files in the output directory
+fn emit_interfaces_dir(
+    out: &mut Output,
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    out.clean_dir = Some(out_dir.to_path_buf());
 
     for interface in pkg.interfaces.values() {
-        emit_interface_impl(interface, &func_map, out_dir)?;
-        let linked_interface = pkg
-            .linkage
-            .get(&interface.name)
-            .and_then(|name| pkg.interfaces.get(name));
-        emit_interface_bus(
-            &pkg.protocol,
-            &pkg.prefix,
-            interface,
-            linked_interface,
-            out_dir,
-        )?;
+        emit_interface_impl(out, pkg, func_map, interface, out_dir)?;
+        emit_interface_bus(out, pkg, func_map, interface, out_dir)?;
+        if pkg.mock {
+            mock::emit_interface_mock(out, pkg, interface, out_dir)?;
+        }
+    }
+
+    if pkg.index {
+        index::emit_index(out, pkg, func_map, out_dir)?;
+    }
+
+    if pkg.loopback {
+        loopback::emit_loopback(out, pkg, out_dir)?;
     }
 
     if !pkg.no_gitignore {
-        emit_gitignore(out_dir)?;
+        emit_gitignore(out, out_dir)?;
     }
 
     Ok(())
 }
 
-/// Make map from function name to function id
-fn make_func_id_map(pkg: &ir::Package) -> BTreeMap<String, u32> {
-    let mut map = BTreeMap::new();
-    // 0-15 are reserved function id for internal use in the workex library
-    let mut i = 16;
-    for interface in pkg.interfaces.values() {
-        for function in &interface.functions {
-            map.insert(format!("{}_{}", interface.name, function.name), i);
-            i += 1;
+/// Names of the sender and receiver implementations of an interface in the generated code
+struct ImplNames {
+    sender: String,
+    recver: String,
+    /// Export the implementations so they can be imported by the bind functions
+    export: bool,
+}
+
+impl ImplNames {
+    /// Names in INTERFACE.ts, which are imported by the bind functions in INTERFACE.bus.ts
+    fn exported() -> Self {
+        Self {
+            sender: "_wxSenderImpl".to_string(),
+            recver: "_wxRecverImpl".to_string(),
+            export: true,
+        }
+    }
+
+    /// Names in a single file module, where the bind functions are in the same file
+    fn internal(interface: &ir::Interface) -> Self {
+        Self {
+            sender: format!("_wx{}SenderImpl", interface.name),
+            recver: format!("_wx{}RecverImpl", interface.name),
+            export: false,
         }
     }
-    map
 }
 
-/// Emit /interfaces/INTERFACE.ts
+/// Generated implementations of an interface
+struct InterfaceImpl {
+    /// Imports used by the implementations. The relative paths are not mapped for the target
+    imports: ir::ImplImports,
+    sender_decl: Code,
+    recver_decl: Code,
+}
+
+/// Emit /interfaces/INTERFACE.ts, or INTERFACE.js and INTERFACE.d.ts for JavaScript
 fn emit_interface_impl(
-    interface: &ir::Interface,
+    out: &mut Output,
+    pkg: &ir::Package,
     func_map: &BTreeMap<String, u32>,
+    interface: &ir::Interface,
     out_dir: &Path,
 ) -> cu::Result<()> {
-    let imports = &interface.impl_imports;
+    let target = pkg.target;
+    let InterfaceImpl {
+        mut imports,
+        sender_decl,
+        recver_decl,
+    } = make_interface_impl(pkg, func_map, interface, &ImplNames::exported());
+    imports.map_relative_paths(|path| target.import_path(path));
+
+    let mut code = Concat::empty();
+    match target {
+        ir::Target::Ts => {
+            // import from parent directory since we are inside interfaces/
+            code.push(
+                format!(
+                    "import type {{ {} }} from \"../{}\";",
+                    interface.name, interface.filename
+                )
+                .into(),
+            );
+            code.push("".into());
+            code.push(imports.to_code());
+            code.push("".into());
+        }
+        ir::Target::Js => {
+            // only the values are imported, since the types are in the .d.ts file
+            if let Some(value_imports) = imports.to_value_code() {
+                code.push(value_imports);
+                code.push("".into());
+            }
+        }
+    }
+    code.push(
+        cconcat![
+            "/*",
+            " * These generated implementations are used internally by other generated code.",
+            " * They should not be used directly!",
+            " */",
+            "",
+        ]
+        .into(),
+    );
 
+    let comment = interface.comment.to_code();
+    if let Some(comment) = comment.clone() {
+        code.push(comment);
+    }
+    code.push(sender_decl);
+    code.push("".into());
+    if let Some(comment) = comment {
+        code.push(comment);
+    }
+    code.push(recver_decl);
+
+    let path = out_dir.join(format!("{}.{}", interface.name, target.extension()));
+    header::write_ts(out, pkg, &path, &interface.filename, &code)?;
+
+    if target == ir::Target::Js {
+        dts::emit_interface_impl_dts(out, pkg, interface, out_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Generate the sender class and the receiver function of an interface
+fn make_interface_impl(
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    interface: &ir::Interface,
+    names: &ImplNames,
+) -> InterfaceImpl {
+    let target = pkg.target;
+    let mut imports = interface.impl_imports.clone();
+    let ident_warn_deprecated = if pkg.log_deprecated
+        && interface
+            .functions
+            .iter()
+            .any(|f| f.comment.deprecated().is_some())
+    {
+        Some(imports.add_workex_value_import("wxWarnDeprecatedCall"))
+    } else {
+        None
+    };
+
+    let export = if names.export { "export " } else { "" };
+    let sender_name = &names.sender;
+    let recver_name = &names.recver;
     let ident_wxsender = &imports.ident_wxsender;
     let ident_wxhandler = &imports.ident_wxhandler;
+    let func_id = |f: &ir::Function| {
+        let func_ident = format!("{}_{}", interface.name, f.name);
+        *func_map.get(&func_ident).unwrap()
+    };
+    let funcid_expr =
+        |f: &ir::Function| format!("{} /* {}.{} */", func_id(f), interface.name, f.name);
 
-    let sender_decl = cblock! {
-        format!("export class _wxSenderImpl implements {} {{", interface.name),
-        [
-            format!("private sender: {}", ident_wxsender),
-            "",
+    let semi = pkg.style.optional_semi();
+    let sender_decl = match target {
+        ir::Target::Ts => cblock! {
+            format!("{export}class {sender_name} implements {} {{", interface.name),
+            [
+                format!("private sender: {ident_wxsender}{semi}"),
+                "",
+                cblock! {
+                    format!("constructor(sender: {}) {{", ident_wxsender),
+                    [ format!("this.sender = sender{semi}") ],
+                    "}"
+                },
+                cconcat!(interface.functions.iter().map(|f| {
+                    f.to_send_function(&funcid_expr(f), &imports.ident_wxpromise, target)
+                })),
+            ],
+            "}"
+        },
+        ir::Target::Js => cblock! {
+            format!("{export}class {sender_name} {{"),
+            [
+                cblock! {
+                    "constructor(sender) {",
+                    [ format!("this.sender = sender{semi}") ],
+                    "}"
+                },
+                cconcat!(interface.functions.iter().map(|f| {
+                    f.to_send_function(&funcid_expr(f), &imports.ident_wxpromise, target)
+                })),
+            ],
+            "}"
+        },
+    };
+
+    let has_context = interface
+        .functions
+        .iter()
+        .any(|f| f.args.iter().any(|arg| arg.callback || arg.signal));
+    let recv_params = match (target, has_context) {
+        (ir::Target::Ts, true) => "fId, args: any[], context",
+        (ir::Target::Ts, false) => "fId, args: any[]",
+        (ir::Target::Js, true) => "fId, args, context",
+        (ir::Target::Js, false) => "fId, args",
+    };
+    let prelude = |f: &ir::Function| {
+        ident_warn_deprecated
+            .as_ref()
+            .filter(|_| f.comment.deprecated().is_some())
+            .map(|ident| format!("{ident}(\"{}.{}\");", interface.name, f.name))
+    };
+    let recver_fn = if pkg.use_dispatch_table(interface) {
+        // the function IDs of an interface are consecutive, so the table
+        // is indexed by the offset from the first one
+        let first_id = interface.functions.first().map(func_id).unwrap_or(16);
+        let table_decl = match (target, has_context) {
+            (ir::Target::Ts, true) => format!(
+                "const table: ((args: any[], context: Parameters<{ident_wxhandler}>[2]) => unknown)[] = ["
+            ),
+            (ir::Target::Ts, false) => "const table: ((args: any[]) => unknown)[] = [".to_string(),
+            (ir::Target::Js, _) => "const table = [".to_string(),
+        };
+        let table_call = if has_context {
+            "f(args, context)"
+        } else {
+            "f(args)"
+        };
+        cconcat![
+            cblock! {
+                table_decl, [
+                cconcat!(interface.functions.iter().map(|f| {
+                    let comment = format!("{} {}.{}", func_id(f), interface.name, f.name);
+                    f.to_recv_table_entry(&comment, prelude(f))
+                })) ],
+                "];"
+            },
             cblock! {
-                format!("constructor(sender: {}) {{", ident_wxsender),
-                [ "this.sender = sender" ],
-                "}"
+                format!("return (({recv_params}) => {{"), [
+                    format!("const f = table[fId - {first_id}];"),
+                    format!("return f ? {table_call} : Promise.resolve({{ err: {{ code: \"UnknownFunction\" }} }});"),
+                ],
+                match target {
+                    ir::Target::Ts => format!("}}) as {ident_wxhandler};"),
+                    ir::Target::Js => "});".to_string(),
+                }
             },
+        ]
+    } else {
+        let semi = pkg.style.inline_semi();
+        cconcat![[cblock! {
+            format!("return (({recv_params}) => {{ switch (fId) {{"), [
             cconcat!(interface.functions.iter().map(|f| {
-                let func_ident = format!("{}_{}", interface.name, f.name);
-                let funcid_expr = format!("{} /* {}.{} */", func_map.get(&func_ident).unwrap(), interface.name, f.name);
-                f.to_send_function(&funcid_expr, &imports.ident_wxpromise)
-            })),
-        ],
-        "}"
+                f.to_recv_switch_case(&funcid_expr(f), prelude(f))
+            })) ],
+            match target {
+                // adding the cast to avoid TypeScript shenanigans
+                ir::Target::Ts => format!("}} return Promise.resolve({{ err: {{ code: \"UnknownFunction\" }} }}){semi} }}) as {ident_wxhandler};"),
+                ir::Target::Js => format!("}} return Promise.resolve({{ err: {{ code: \"UnknownFunction\" }} }}){semi} }});"),
+            }
+        }]]
     };
-
     let recver_decl = cblock! {
-        format!("export const _wxRecverImpl = (handler: {}): {} => {{", interface.name, ident_wxhandler), [
-        cblock! {
-            "return ((fId, args: any[]) => { switch (fId) {", [
-            cconcat!(interface.functions.iter().map(|f| {
-                let func_ident = format!("{}_{}", interface.name, f.name);
-                let funcid_expr = format!("{} /* {}.{} */", func_map.get(&func_ident).unwrap(), interface.name, f.name);
-                f.to_recv_switch_case(&funcid_expr)
-            })) ],
-            // adding the cast to avoid TypeScript shenanigans
-            format!("}} return Promise.resolve({{ err: {{ code: \"UnknownFunction\" }} }}); }}) as {};", ident_wxhandler)
-        }, ],
+        match target {
+            ir::Target::Ts => format!("{export}const {recver_name} = (handler: {}): {} => {{", interface.name, ident_wxhandler),
+            ir::Target::Js => format!("{export}const {recver_name} = (handler) => {{"),
+        },
+        [recver_fn],
         "};"
     };
 
-    let mut code = cconcat![
-        header(),
-        // import from parent directory since we are inside interfaces/
-        format!(
-            "import type {{ {} }} from \"../{}\";",
-            interface.name, interface.filename
-        ),
-        "",
-        imports.to_code(),
-        "",
-        "/*",
-        " * These generated implementations are used internally by other generated code.",
-        " * They should not be used directly!",
-        " */",
-        "",
-    ];
+    InterfaceImpl {
+        sender_decl: sender_decl.into(),
+        recver_decl: recver_decl.into(),
+        imports,
+    }
+}
 
-    let comment = interface.comment.to_code();
-    if let Some(comment) = comment.clone() {
-        code.push(comment);
+/// Emit /interfaces/INTERFACE.bus.ts, or INTERFACE.bus.js and INTERFACE.bus.d.ts for JavaScript
+fn emit_interface_bus(
+    out: &mut Output,
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    interface: &ir::Interface,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    let target = pkg.target;
+    let links = pkg.links(&interface.name);
+    // the same interface can be linked under multiple protocols
+    let mut linked_interfaces: Vec<&ir::Interface> = vec![];
+    for link in links {
+        if let Some(linked) = pkg.interfaces.get(&link.interface)
+            && !linked_interfaces.iter().any(|x| x.name == linked.name)
+        {
+            linked_interfaces.push(linked);
+        }
     }
-    code.push(sender_decl.into());
-    code.push("".into());
-    if let Some(comment) = comment {
-        code.push(comment);
+
+    let ext = target.extension();
+    // the receivers of the linked interfaces have the same name,
+    // so they are renamed if there are multiple
+    let recver_impl = |linked_name: &str| match linked_interfaces.len() {
+        1 => "_wxRecverImpl".to_string(),
+        _ => format!("_wxRecverImpl{linked_name}"),
+    };
+    let impl_imports = match linked_interfaces.as_slice() {
+        [] => cconcat![[format!(
+            "import {{ _wxSenderImpl, _wxRecverImpl }} from \"./{}.{ext}\";",
+            interface.name
+        )]],
+        [linked] => cconcat![
+            format!(
+                "import {{ _wxRecverImpl }} from \"./{}.{ext}\";",
+                linked.name
+            ),
+            format!(
+                "import {{ _wxSenderImpl }} from \"./{}.{ext}\";",
+                interface.name
+            ),
+        ],
+        linked_interfaces => cconcat![
+            cconcat!(linked_interfaces.iter().map(|linked| format!(
+                "import {{ _wxRecverImpl as {} }} from \"./{}.{ext}\";",
+                recver_impl(&linked.name),
+                linked.name
+            ))),
+            format!(
+                "import {{ _wxSenderImpl }} from \"./{}.{ext}\";",
+                interface.name
+            ),
+        ],
+    };
+
+    let mut bind_config_funcs: Vec<Code> = vec![];
+    if links.is_empty() {
+        bind_config_funcs.push(
+            make_bind_config(
+                pkg,
+                func_map,
+                interface,
+                None,
+                "_wxSenderImpl",
+                "_wxRecverImpl",
+            )
+            .into(),
+        );
     }
-    code.push(recver_decl.into());
+    for link in links {
+        if !bind_config_funcs.is_empty() {
+            bind_config_funcs.push("".into());
+        }
+        bind_config_funcs.push(
+            make_bind_config(
+                pkg,
+                func_map,
+                interface,
+                Some(link),
+                "_wxSenderImpl",
+                &recver_impl(&link.interface),
+            )
+            .into(),
+        );
+    }
+    let bind_config_funcs = cconcat!(bind_config_funcs);
 
-    let path = out_dir.join(format!("{}.ts", interface.name));
-    cu::fs::write(&path, code.to_string())?;
+    let trace_imports = if pkg.trace {
+        cconcat![["import { wxTraceRecver, wxTraceSender } from \"@pistonite/workex\";"]]
+    } else {
+        cconcat![]
+    };
+    let output = match target {
+        ir::Target::Ts => cconcat![
+            "import type { WxProtocolBindConfig } from \"@pistonite/workex\";",
+            trace_imports,
+            format!(
+                "import type {{ {} }} from \"../{}\";",
+                interface.name, interface.filename
+            ),
+            cconcat!(linked_interfaces.iter().map(|linked| format!(
+                "import type {{ {} }} from \"../{}\";",
+                linked.name, linked.filename
+            ))),
+            impl_imports,
+            "",
+            bind_config_funcs
+        ],
+        ir::Target::Js => cconcat![trace_imports, impl_imports, "", bind_config_funcs],
+    };
+
+    let path = out_dir.join(format!("{}.bus.{ext}", interface.name));
+    header::write_ts(out, pkg, &path, &interface.filename, &output)?;
+
+    if target == ir::Target::Js {
+        dts::emit_interface_bus_dts(out, pkg, interface, out_dir)?;
+    }
 
     Ok(())
 }
 
-/// Emit /interfaces/INTERFACE.bus.ts
-fn emit_interface_bus(
-    protocol: &str,
-    prefix: &str,
+/// Generate the bind config function of an interface, with the documentation.
+///
+/// The bind config is for the `link` of the interface, or for both sides if not linked.
+/// `sender_impl` is the sender class of the interface, and `recver_impl` is
+/// the receiver function of the linked interface, or of the interface itself if not linked
+fn make_bind_config(
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
     interface: &ir::Interface,
-    linked_interface: Option<&ir::Interface>,
-    out_dir: &Path,
-) -> cu::Result<()> {
+    link: Option<&ir::Link>,
+    sender_impl: &str,
+    recver_impl: &str,
+) -> Concat {
+    let linked_interface = link.and_then(|link| pkg.interfaces.get(&link.interface));
+    let protocol = match link {
+        Some(link) => &link.protocol,
+        None => &pkg.protocol,
+    };
+    let target = pkg.target;
     let name = &interface.name;
 
-    let function_name = format!("{prefix}{name}");
+    let function_name = match link {
+        Some(link) => link.bind_name.clone(),
+        None => format!("{}{name}", pkg.prefix),
+    };
+
+    // with tracing, the sender and receiver are wrapped with the names and IDs of the functions
+    let function_ids = |interface: &ir::Interface| {
+        let ids = interface.functions.iter().map(|f| {
+            let func_ident = format!("{}_{}", interface.name, f.name);
+            format!("{}: {}", f.name, func_map.get(&func_ident).unwrap())
+        });
+        format!("{{ {} }}", ids.collect::<Vec<_>>().join(", "))
+    };
+    let new_sender = if pkg.trace {
+        format!(
+            "const impl = wxTraceSender(new {sender_impl}(sender), {}, {}, {});",
+            quoted(protocol),
+            quoted(name),
+            function_ids(interface)
+        )
+    } else {
+        format!("const impl = new {sender_impl}(sender);")
+    };
+    let recv_handler = |handler: &str, recv_interface: &ir::Interface| {
+        if pkg.trace {
+            format!(
+                "recvHandler: wxTraceRecver({recver_impl}({handler}), {}, {}, {}),",
+                quoted(protocol),
+                quoted(&recv_interface.name),
+                function_ids(recv_interface)
+            )
+        } else {
+            format!("recvHandler: {recver_impl}({handler}),")
+        }
+    };
 
     #[rustfmt::skip]
-    let bind_config_func = match linked_interface.map(|i| &i.name) {
-        Some(linked_name) => cconcat![
-                    "/**",
-            format!(" * Create a bind config for the {name} interface, under the `{protocol}` protocol"),
-                    " *",
-            format!(" * When used with a creator function in Workex, an implementation of {name} will be returned"),
-            format!(" * to send remote calls to the other side. This side needs to provide an implementation for {linked_name}"),
-                    " * to be used when the other side calls this side",
-                    " *",
-                    " * This function is generated by the workex CLI tool",
-                    " */",
+    let bind_config_func = match linked_interface {
+        Some(linked) => {
+            let linked_name = &linked.name;
+            cconcat![
+            bind_config_doc(protocol, interface, linked_interface),
             cblock! {
-                format!("export const {function_name} = (handler: {linked_name}, resolve?: (_: {name}) => (void | Promise<void>)): WxProtocolBindConfig<{name}> => {{ return {{"), [
+                match target {
+                    ir::Target::Ts => format!("export const {function_name} = (handler: {linked_name}, resolve?: (_: {name}) => (void | Promise<void>)): WxProtocolBindConfig<{name}> => {{ return {{"),
+                    ir::Target::Js => format!("export const {function_name} = (handler, resolve) => {{ return {{"),
+                }, [
                     format!("protocol: {},", quoted(protocol)),
                     format!("interfaces: [{}, {}],", quoted(name), quoted(linked_name)),
-                            "recvHandler: _wxRecverImpl(handler),",
+                            recv_handler("handler", linked),
                     cblock! {
                             "bindSend: (sender) => {", [
-                                "const impl = new _wxSenderImpl(sender);",
-                                "resolve?.(impl);",
-                                "return impl;", ],
+                                new_sender.clone(),
+                                "resolve?.(impl);".to_string(),
+                                "return impl;".to_string(), ],
                             "}," } ],
                         "}};"
             }
-        ],
+        ]},
         // for unlinked interfaces, generate one function that can be used both as a sender and receiver
         None => cconcat![
-                    "/**",
-            format!(" * Create a bind config for the {name} interface, under the `{protocol}` protocol"),
-                    " *",
-                    " * When used with a creator function in Workex, if no arguments, or a `resolve` function is provided,",
-            format!(" * the config will be for the sender side (i.e. the side that calls {name})."),
-            format!(" * Otherwise, the config will be for the receiver side, and an implementation of {name} needs to be provided."),
-                    " *",
-                    " * This interface is not linked to another interface in the protocol. One side should provide an implementation, and the other",
-                    " * side should call with no arguments or a `resolve` function to receive a caller.",
-                    " *",
-                    " * This function is generated by the workex CLI tool",
-                    " */",
-            // receiver signature
-            format!("export function {function_name}(handler: {name}): WxProtocolBindConfig<Record<string, never>>;"),
-            // sender signature
-            format!("export function {function_name}(resolve?: (_: {name}) => (void | Promise<void>)): WxProtocolBindConfig<{name}>;"),
+            bind_config_doc(protocol, interface, linked_interface),
+            match target {
+                ir::Target::Ts => cconcat![
+                    // receiver signature
+                    format!("export function {function_name}(handler: {name}): WxProtocolBindConfig<Record<string, never>>;"),
+                    // sender signature
+                    format!("export function {function_name}(resolve?: (_: {name}) => (void | Promise<void>)): WxProtocolBindConfig<{name}>;"),
+                ],
+                ir::Target::Js => cconcat![],
+            },
             cblock! {
-                format!("export function {function_name}(handlerOrResolve?: {name} | ((_: {name}) => (void | Promise<void>))): WxProtocolBindConfig<Record<string, never>> | WxProtocolBindConfig<{name}> {{"), [
+                match target {
+                    ir::Target::Ts => format!("export function {function_name}(handlerOrResolve?: {name} | ((_: {name}) => (void | Promise<void>))): WxProtocolBindConfig<Record<string, never>> | WxProtocolBindConfig<{name}> {{"),
+                    ir::Target::Js => format!("export function {function_name}(handlerOrResolve) {{"),
+                }, [
                     cblock!{
                         "if (!handlerOrResolve || typeof handlerOrResolve === \"function\") { return {", [
                             format!("protocol: {},", quoted(protocol)),
@@ -202,9 +587,9 @@ fn emit_interface_bus(
                                     "recvHandler: () => Promise.resolve({ err: { code: \"UnexpectedStubCall\" } }),",
                             cblock! {
                                     "bindSend: (sender) => {", [
-                                        "const impl = new _wxSenderImpl(sender);",
-                                        "handlerOrResolve?.(impl);",
-                                        "return impl;", ],
+                                        new_sender.clone(),
+                                        "handlerOrResolve?.(impl);".to_string(),
+                                        "return impl;".to_string(), ],
                                     "},"
                             } ],
                         "};}"
@@ -213,58 +598,64 @@ fn emit_interface_bus(
                         "return {", [
                         format!("protocol: {},", quoted(protocol)),
                         format!("interfaces: [\"_wxStub\", {}],", quoted(name)),
-                                "recvHandler: _wxRecverImpl(handlerOrResolve),",
-                                "bindSend: () => ({})", ],
+                                recv_handler("handlerOrResolve", interface),
+                                format!("bindSend: () => ({{}}){}", pkg.style.optional_trailing_comma()), ],
                         "};"
                     }, ],
                     "};"
             }
         ],
     };
+    bind_config_func
+}
 
-    let output = cconcat![
-        header(),
-        "import type { WxProtocolBindConfig } from \"@pistonite/workex\";",
-        format!(
-            "import type {{ {} }} from \"../{}\";",
-            interface.name, interface.filename
-        ),
-        match linked_interface {
-            Some(linked) => cconcat![
-                format!(
-                    "import type {{ {0} }} from \"../{1}\";\nimport {{ _wxRecverImpl }} from \"./{0}.ts\";",
-                    linked.name, linked.filename
-                ),
-                format!(
-                    "import {{ _wxSenderImpl }} from \"./{}.ts\";",
-                    interface.name
-                ),
-            ],
-            None => cconcat![[format!(
-                "import {{ _wxSenderImpl, _wxRecverImpl }} from \"./{}.ts\";",
-                interface.name
-            )]],
-        },
-        "",
-        bind_config_func
-    ];
-
-    let path = out_dir.join(format!("{}.bus.ts", interface.name));
-    cu::fs::write(&path, output.to_string())?;
-
-    Ok(())
+/// Documentation comment for the bind config function of an interface
+#[rustfmt::skip]
+fn bind_config_doc(
+    protocol: &str,
+    interface: &ir::Interface,
+    linked_interface: Option<&ir::Interface>,
+) -> Concat {
+    let name = &interface.name;
+    match linked_interface.map(|i| &i.name) {
+        Some(linked_name) => cconcat![
+                    "/**",
+            format!(" * Create a bind config for the {name} interface, under the `{protocol}` protocol"),
+                    " *",
+            format!(" * When used with a creator function in Workex, an implementation of {name} will be returned"),
+            format!(" * to send remote calls to the other side. This side needs to provide an implementation for {linked_name}"),
+                    " * to be used when the other side calls this side",
+                    " *",
+                    " * This function is generated by the workex CLI tool",
+                    " */",
+        ],
+        None => cconcat![
+                    "/**",
+            format!(" * Create a bind config for the {name} interface, under the `{protocol}` protocol"),
+                    " *",
+                    " * When used with a creator function in Workex, if no arguments, or a `resolve` function is provided,",
+            format!(" * the config will be for the sender side (i.e. the side that calls {name})."),
+            format!(" * Otherwise, the config will be for the receiver side, and an implementation of {name} needs to be provided."),
+                    " *",
+                    " * This interface is not linked to another interface in the protocol. One side should provide an implementation, and the other",
+                    " * side should call with no arguments or a `resolve` function to receive a caller.",
+                    " *",
+                    " * This function is generated by the workex CLI tool",
+                    " */",
+        ],
+    }
 }
 
-fn emit_gitignore(out_dir: &Path) -> cu::Result<()> {
+fn emit_gitignore(out: &mut Output, out_dir: &Path) -> cu::Result<()> {
     let mut content = String::from("# workex generated files\n");
     let path = out_dir.join(".gitignore");
     content.push_str("*\n");
-    cu::fs::write(&path, &content)?;
+    out.add(&path, content);
 
     Ok(())
 }
 
-fn quoted(s: &str) -> String {
+pub fn quoted(s: &str) -> String {
     if s.contains(['"', '\\']) {
         format!("\"{}\"", s.replace('"', "\\\"").replace('\\', "\\\\"))
     } else {
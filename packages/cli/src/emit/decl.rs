@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use codize::{Code, Concat, cblock, cconcat, clist};
+
+use crate::ir;
+
+/// Emit the TypeScript declaration file for a Rust input file
+pub fn emit_decl_file(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    decl: &ir::DeclFile,
+    dir: &Path,
+) -> cu::Result<()> {
+    let mut code = cconcat![
+        format!(
+            "// Declarations generated from {}. Edit the Rust file instead of this file",
+            decl.source
+        ),
+        "import type { WxPromise } from \"@pistonite/workex\";",
+    ];
+
+    for t in &decl.types {
+        code.push("".into());
+        if let Some(comment) = t.comment.to_code() {
+            code.push(comment);
+        }
+        let name = if t.generics.is_empty() {
+            t.name.clone()
+        } else {
+            format!("{}<{}>", t.name, t.generics.join(", "))
+        };
+        let type_code: Code = match &t.kind {
+            ir::TypeDeclKind::Interface(fields) => cblock! {
+                format!("export interface {name} {{"),
+                fields.iter().map(|field| {
+                    let mut field_code = Concat::empty();
+                    if let Some(comment) = field.comment.to_code() {
+                        field_code.push(comment);
+                    }
+                    field_code.push(format!("{};", field.arg.to_code()).into());
+                    Code::from(field_code)
+                }).collect::<Vec<_>>(),
+                "}"
+            }
+            .never_inlined()
+            .into(),
+            ir::TypeDeclKind::Alias(typ) => format!("export type {name} = {typ};").into(),
+        };
+        code.push(type_code);
+    }
+
+    for name in &decl.interfaces {
+        let Some(interface) = pkg.interfaces.get(name) else {
+            continue;
+        };
+        code.push("".into());
+        if let Some(comment) = interface.comment.to_code() {
+            code.push(comment);
+        }
+        let mut functions: Vec<Code> = Vec::with_capacity(interface.functions.len());
+        for f in &interface.functions {
+            if !functions.is_empty() {
+                functions.push("".into());
+            }
+            if let Some(comment) = f.comment.to_code() {
+                functions.push(comment);
+            }
+            functions.push(
+                cblock! {
+                    format!("{}(", f.name),
+                    [clist!("," => f.args.iter().map(|arg| arg.to_code())).inlined()],
+                    format!("): WxPromise{};", f.retty_ann)
+                }
+                .into(),
+            );
+        }
+        code.push(
+            cblock! {
+                format!("export interface {name} {{"),
+                functions,
+                "}"
+            }
+            .never_inlined()
+            .into(),
+        );
+    }
+
+    // the file only has types, so it can be used as the declarations for JavaScript,
+    // and the generated code imports it as `.js`
+    let filename = match pkg.target {
+        ir::Target::Ts => decl.filename.clone(),
+        ir::Target::Js => {
+            let stem = decl.filename.strip_suffix(".ts").unwrap_or(&decl.filename);
+            format!("{stem}.d.ts")
+        }
+    };
+    super::header::write_ts(out, pkg, &dir.join(filename), &decl.source, &code)?;
+    Ok(())
+}
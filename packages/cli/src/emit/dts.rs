@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use codize::{Code, cblock, cconcat};
+
+use crate::ir;
+
+/// Emit /interfaces/INTERFACE.d.ts for the JavaScript target
+pub fn emit_interface_impl_dts(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    interface: &ir::Interface,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    let mut imports = interface.impl_imports.clone();
+    imports.map_relative_paths(|path| ir::Target::Js.import_path(path));
+
+    let sender_decl = cblock! {
+        format!("export declare class _wxSenderImpl implements {} {{", interface.name),
+        [
+            "private sender;",
+            format!("constructor(sender: {});", imports.ident_wxsender),
+            cconcat!(interface.functions.iter().map(|f| {
+                cconcat!["", f.to_send_declaration(&imports.ident_wxpromise)]
+            })),
+        ],
+        "}"
+    };
+
+    let recver_decl = format!(
+        "export declare const _wxRecverImpl: (handler: {}) => {};",
+        interface.name, imports.ident_wxhandler
+    );
+
+    let mut code = cconcat![
+        import_interface(interface),
+        "",
+        imports.to_code(),
+        "",
+        "/*",
+        " * These generated implementations are used internally by other generated code.",
+        " * They should not be used directly!",
+        " */",
+        "",
+    ];
+
+    let comment = interface.comment.to_code();
+    if let Some(comment) = comment.clone() {
+        code.push(comment);
+    }
+    code.push(sender_decl.into());
+    code.push("".into());
+    if let Some(comment) = comment {
+        code.push(comment);
+    }
+    code.push(recver_decl.into());
+
+    let path = out_dir.join(format!("{}.d.ts", interface.name));
+    super::header::write_ts(out, pkg, &path, &interface.filename, &code)?;
+
+    Ok(())
+}
+
+/// Emit /interfaces/INTERFACE.bus.d.ts for the JavaScript target
+pub fn emit_interface_bus_dts(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    interface: &ir::Interface,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    let name = &interface.name;
+    let links = pkg.links(name);
+
+    let mut linked_imports = vec![];
+    let mut bind_config_decls: Vec<Code> = vec![];
+    if links.is_empty() {
+        let function_name = format!("{}{name}", pkg.prefix);
+        #[rustfmt::skip]
+        bind_config_decls.push(cconcat![
+            super::bind_config_doc(&pkg.protocol, interface, None),
+            format!("export declare function {function_name}(handler: {name}): WxProtocolBindConfig<Record<string, never>>;"),
+            format!("export declare function {function_name}(resolve?: (_: {name}) => (void | Promise<void>)): WxProtocolBindConfig<{name}>;"),
+        ].into());
+    }
+    for link in links {
+        let Some(linked) = pkg.interfaces.get(&link.interface) else {
+            continue;
+        };
+        let function_name = &link.bind_name;
+        let linked_name = &linked.name;
+        // the same interface can be linked under multiple protocols
+        let linked_import = import_interface(linked);
+        if !linked_imports.contains(&linked_import) {
+            linked_imports.push(linked_import);
+        }
+        if !bind_config_decls.is_empty() {
+            bind_config_decls.push("".into());
+        }
+        #[rustfmt::skip]
+        bind_config_decls.push(cconcat![
+            super::bind_config_doc(&link.protocol, interface, Some(linked)),
+            format!("export declare const {function_name}: (handler: {linked_name}, resolve?: (_: {name}) => (void | Promise<void>)) => WxProtocolBindConfig<{name}>;"),
+        ].into());
+    }
+
+    let output = cconcat![
+        "import type { WxProtocolBindConfig } from \"@pistonite/workex\";",
+        import_interface(interface),
+        cconcat!(linked_imports),
+        "",
+        cconcat!(bind_config_decls),
+    ];
+
+    let path = out_dir.join(format!("{}.bus.d.ts", interface.name));
+    super::header::write_ts(out, pkg, &path, &interface.filename, &output)?;
+
+    Ok(())
+}
+
+/// Import the interface type from the parent directory, since we are inside interfaces/
+fn import_interface(interface: &ir::Interface) -> String {
+    let path = ir::Target::Js.import_path(&format!("../{}", interface.filename));
+    format!("import type {{ {} }} from \"{}\";", interface.name, path)
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use codize::{Concat, FormatCode};
+
+use crate::ir;
+
+/// Add a generated TypeScript or JavaScript file to the output, with the header before the code.
+///
+/// `source` is the input file(s) the file is generated from
+pub fn write_ts(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    path: &Path,
+    source: &str,
+    code: &impl FormatCode,
+) -> cu::Result<()> {
+    let body = super::style::render(code, &pkg.style);
+    let header = &pkg.header;
+    let values = ir::HeaderValues {
+        protocol: &pkg.protocol,
+        source,
+        hash: &content_hash(header, &body),
+    };
+
+    let mut code = Concat::empty();
+    if let Some(license) = &header.license {
+        code.push(format!("// SPDX-License-Identifier: {license}").into());
+    }
+    let lines = header.lines(&values);
+    if !lines.is_empty() {
+        code.push("/*".into());
+        for line in lines {
+            if line.is_empty() {
+                code.push(" *".into());
+            } else {
+                code.push(format!(" * {line}").into());
+            }
+        }
+        code.push(" */".into());
+    }
+    for directive in &header.directives {
+        code.push(directive.to_comment().into());
+    }
+    write_with_header(out, path, &code, body, &pkg.style)
+}
+
+/// Add the generated Rust bindings to the output, with the header before the code
+pub fn write_rust(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    path: &Path,
+    code: &impl FormatCode,
+) -> cu::Result<()> {
+    let body = code.format();
+    let header = &pkg.header;
+    let values = ir::HeaderValues {
+        protocol: &pkg.protocol,
+        source: &pkg.source_files().join(", "),
+        hash: &content_hash(header, &body),
+    };
+
+    let mut code = Concat::empty();
+    if let Some(license) = &header.license {
+        code.push(format!("// SPDX-License-Identifier: {license}").into());
+    }
+    for line in header.lines(&values) {
+        if line.is_empty() {
+            code.push("//!".into());
+        } else {
+            code.push(format!("//! {line}").into());
+        }
+    }
+    // the Rust bindings are not formatted with the style
+    write_with_header(out, path, &code, body, &ir::Style::default())
+}
+
+fn write_with_header(
+    out: &mut super::Output,
+    path: &Path,
+    header: &Concat,
+    body: String,
+    style: &ir::Style,
+) -> cu::Result<()> {
+    if header.is_empty() {
+        out.add(path, body);
+        return Ok(());
+    }
+    let header_style = ir::Style {
+        final_newline: false,
+        ..style.clone()
+    };
+    let newline = style.newline();
+    let header = super::style::render(header, &header_style);
+    out.add(path, format!("{header}{newline}{newline}{body}"));
+    Ok(())
+}
+
+/// Hash of the content after the header, for the `{hash}` placeholder.
+///
+/// This is 64-bit FNV-1a, which is stable across versions of the CLI
+/// and platforms, in hex
+fn content_hash(header: &ir::Header, content: &str) -> String {
+    if !header.needs_hash() {
+        return String::new();
+    }
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use codize::{Code, cblock, cconcat};
+
+use crate::ir;
+
+/// Emit /interfaces/index.ts, or index.js and index.d.ts for JavaScript,
+/// with the protocols, the function IDs and the bind functions of all interfaces.
+///
+/// The `_wx` implementations are internal, so they are not exported
+pub fn emit_index(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    let target = pkg.target;
+    let ext = target.extension();
+    let source = pkg.source_files().join(", ");
+
+    let protocol_doc = "/** The protocol identifier */";
+    let protocols_doc = "/** The protocol identifiers of all links, starting with `PROTOCOL` */";
+    // the links under other protocols are bound with their own protocol identifiers
+    let mut protocols = vec![pkg.protocol.as_str()];
+    let link_protocols = pkg
+        .interfaces
+        .keys()
+        .flat_map(|name| pkg.links(name))
+        .map(|link| link.protocol.as_str())
+        .filter(|x| *x != pkg.protocol)
+        .collect::<BTreeSet<_>>();
+    protocols.extend(link_protocols);
+    let protocols = protocols
+        .into_iter()
+        .map(super::quoted)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let function_ids_doc = cconcat![
+        "/**",
+        " * The function IDs used in the messages, for each function in each interface",
+        " *",
+        " * This is generated by the workex CLI tool",
+        " */",
+    ];
+    // (interface, [(function, id)])
+    let function_ids = pkg
+        .interfaces
+        .values()
+        .map(|interface| {
+            let ids = interface
+                .functions
+                .iter()
+                .map(|f| {
+                    let func_ident = format!("{}_{}", interface.name, f.name);
+                    (f.name.as_str(), *func_map.get(&func_ident).unwrap())
+                })
+                .collect::<Vec<_>>();
+            (interface.name.as_str(), ids)
+        })
+        .collect::<Vec<_>>();
+
+    let re_exports = cconcat!(pkg.interfaces.values().map(|interface| {
+        format!(
+            "export {{ {} }} from \"./{}.bus.{ext}\";",
+            pkg.bind_names(&interface.name).join(", "),
+            interface.name
+        )
+    }));
+
+    let function_ids_value: Code = cblock! {
+        "export const FUNCTION_IDS = {",
+        function_ids.iter().map(|(name, ids)| {
+            let ids = ids.iter().map(|(f, id)| format!("{f}: {id}")).collect::<Vec<_>>();
+            Code::from(format!("{name}: {},", object_literal(&ids, ", ")))
+        }).collect::<Vec<_>>(),
+        match target {
+            ir::Target::Ts => "} as const;",
+            ir::Target::Js => "};",
+        }
+    }
+    .never_inlined()
+    .into();
+
+    let code = cconcat![
+        protocol_doc,
+        format!("export const PROTOCOL = {};", super::quoted(&pkg.protocol)),
+        protocols_doc,
+        match target {
+            ir::Target::Ts => format!("export const PROTOCOLS = [{protocols}] as const;"),
+            ir::Target::Js => format!("export const PROTOCOLS = [{protocols}];"),
+        },
+        "",
+        function_ids_doc.clone(),
+        function_ids_value,
+        "",
+        re_exports.clone(),
+    ];
+    let path = out_dir.join(format!("index.{ext}"));
+    super::header::write_ts(out, pkg, &path, &source, &code)?;
+
+    if target == ir::Target::Js {
+        let function_ids_type: Code = cblock! {
+            "export declare const FUNCTION_IDS: {",
+            function_ids.iter().map(|(name, ids)| {
+                let ids = ids.iter().map(|(f, id)| format!("readonly {f}: {id};")).collect::<Vec<_>>();
+                Code::from(format!("readonly {name}: {};", object_literal(&ids, " ")))
+            }).collect::<Vec<_>>(),
+            "};"
+        }
+        .never_inlined()
+        .into();
+        let dts = cconcat![
+            protocol_doc,
+            format!(
+                "export declare const PROTOCOL: {};",
+                super::quoted(&pkg.protocol)
+            ),
+            protocols_doc,
+            format!("export declare const PROTOCOLS: readonly [{protocols}];"),
+            "",
+            function_ids_doc,
+            function_ids_type,
+            "",
+            re_exports,
+        ];
+        super::header::write_ts(out, pkg, &out_dir.join("index.d.ts"), &source, &dts)?;
+    }
+
+    Ok(())
+}
+
+/// Join the members into an object literal or type in one line
+fn object_literal(members: &[String], separator: &str) -> String {
+    if members.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", members.join(separator))
+    }
+}
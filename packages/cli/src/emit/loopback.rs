@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use codize::{Code, cblock, cconcat};
+
+use crate::ir;
+
+/// Emit /interfaces/loopback.ts, or loopback.js and loopback.d.ts for JavaScript,
+/// with a function for each linked pair and each unlinked interface to connect
+/// both sides in the same thread
+pub fn emit_loopback(out: &mut super::Output, pkg: &ir::Package, out_dir: &Path) -> cu::Result<()> {
+    let target = pkg.target;
+    let ext = target.extension();
+    let source = pkg.source_files().join(", ");
+
+    let mut type_imports = vec![];
+    let mut value_imports = vec![];
+    let mut functions: Vec<Code> = vec![];
+    let mut declarations: Vec<Code> = vec![];
+
+    for interface in pkg.interfaces.values() {
+        let name = &interface.name;
+        type_imports.push(format!(
+            "import type {{ {name} }} from \"{}\";",
+            target.import_path(&format!("../{}", interface.filename))
+        ));
+        value_imports.push(format!(
+            "import {{ {} }} from \"./{name}.bus.{ext}\";",
+            pkg.bind_names(name).join(", ")
+        ));
+        let mut loopbacks = vec![];
+        let links = pkg.links(name);
+        if links.is_empty() {
+            loopbacks.push(unlinked_loopback(&pkg.protocol, &pkg.prefix, interface));
+        }
+        for link in links {
+            // each pair is only emitted once
+            if link.interface < *name {
+                continue;
+            }
+            let (Some(linked), Some(linked_link)) = (
+                pkg.interfaces.get(&link.interface),
+                pkg.find_link(&link.interface, name, &link.protocol),
+            ) else {
+                continue;
+            };
+            loopbacks.push(linked_loopback(interface, link, linked, linked_link));
+        }
+        for (doc, signature, body) in loopbacks {
+            functions.push("".into());
+            functions.push(doc.clone().into());
+            declarations.push("".into());
+            declarations.push(doc.into());
+            let function_name = signature.name;
+            match target {
+                ir::Target::Ts => functions.push(
+                    cblock! {
+                        format!("export const {function_name} = async ({}): Promise<WxResult<{}>> => {{", signature.params, signature.output),
+                        body,
+                        "};"
+                    }
+                    .into(),
+                ),
+                ir::Target::Js => {
+                    functions.push(
+                        cblock! {
+                            format!("export const {function_name} = async ({}) => {{", signature.param_idents),
+                            body,
+                            "};"
+                        }
+                        .into(),
+                    );
+                    declarations.push(
+                        format!(
+                            "export declare const {function_name}: ({}) => Promise<WxResult<{}>>;",
+                            signature.params, signature.output
+                        )
+                        .into(),
+                    );
+                }
+            }
+        }
+    }
+
+    let workex_type_import =
+        "import type { WxCloseController, WxResult } from \"@pistonite/workex\";";
+    let workex_value_import = "import { wxLoopback } from \"@pistonite/workex\";";
+    let code = match target {
+        ir::Target::Ts => cconcat![
+            workex_type_import,
+            workex_value_import,
+            cconcat!(type_imports),
+            cconcat!(value_imports),
+            cconcat!(functions),
+        ],
+        ir::Target::Js => {
+            let dts = cconcat![
+                workex_type_import,
+                cconcat!(type_imports),
+                cconcat!(declarations),
+            ];
+            super::header::write_ts(out, pkg, &out_dir.join("loopback.d.ts"), &source, &dts)?;
+            cconcat![
+                workex_value_import,
+                cconcat!(value_imports),
+                cconcat!(functions),
+            ]
+        }
+    };
+
+    let path = out_dir.join(format!("loopback.{ext}"));
+    super::header::write_ts(out, pkg, &path, &source, &code)?;
+
+    Ok(())
+}
+
+/// Signature of a loopback function
+struct Signature {
+    name: String,
+    /// Parameters with types
+    params: String,
+    /// Parameters without types
+    param_idents: String,
+    /// Type of the value returned on success
+    output: String,
+}
+
+/// Generate the loopback function that connects a linked pair of interfaces,
+/// where `link` is the link from `interface` to `linked`, and `linked_link` is the other direction
+#[rustfmt::skip]
+fn linked_loopback(
+    interface: &ir::Interface,
+    link: &ir::Link,
+    linked: &ir::Interface,
+    linked_link: &ir::Link,
+) -> (codize::Concat, Signature, Vec<Code>) {
+    let protocol = &link.protocol;
+    let prefix = &link.prefix;
+    let bind_name = &link.bind_name;
+    let linked_bind_name = &linked_link.bind_name;
+    let name = &interface.name;
+    let linked_name = &linked.name;
+    let key = to_camel_case(name);
+    let linked_key = to_camel_case(linked_name);
+    let doc = cconcat![
+                "/**",
+        format!(" * Connect the {name} and {linked_name} interfaces in the same thread, under the `{protocol}` protocol"),
+                " *",
+                " * The calls go through the generated senders and receivers with the same function IDs,",
+                " * but the messages are passed in memory instead of to a Worker or window.",
+                " * This is meant to be used in unit tests.",
+                " *",
+        format!(" * The handlers are the implementations of each interface. The returned `{key}` and `{linked_key}`"),
+                " * call the handlers through the connection.",
+                " *",
+                " * This function is generated by the workex CLI tool",
+                " */",
+    ];
+    let signature = Signature {
+        name: format!("{prefix}{name}{linked_name}Loopback"),
+        params: format!("handlers: {{ {key}: {name}; {linked_key}: {linked_name} }}"),
+        param_idents: "handlers".to_string(),
+        output: format!("{{ {key}: {name}; {linked_key}: {linked_name}; connection: WxCloseController }}"),
+    };
+    // the side that implements an interface receives the caller of the linked interface
+    let body: Vec<Code> = vec![
+        "const [creatorA, creatorB] = wxLoopback();".into(),
+        cblock! {
+            "const [a, b] = await Promise.all([", [
+                format!("creatorA({{ api: {linked_bind_name}(handlers.{key}) }}),"),
+                format!("creatorB({{ api: {bind_name}(handlers.{linked_key}) }}),"),
+            ],
+            "]);"
+        }.into(),
+        "if (a.err) { return { err: a.err }; }".into(),
+        "if (b.err) { return { err: b.err }; }".into(),
+        format!("return {{ val: {{ {key}: b.val.protocols.api, {linked_key}: a.val.protocols.api, connection: a.val.connection }} }};").into(),
+    ];
+    (doc, signature, body)
+}
+
+/// Generate the loopback function that connects both sides of an unlinked interface
+#[rustfmt::skip]
+fn unlinked_loopback(
+    protocol: &str,
+    prefix: &str,
+    interface: &ir::Interface,
+) -> (codize::Concat, Signature, Vec<Code>) {
+    let name = &interface.name;
+    let key = to_camel_case(name);
+    let doc = cconcat![
+                "/**",
+        format!(" * Connect both sides of the {name} interface in the same thread, under the `{protocol}` protocol"),
+                " *",
+                " * The calls go through the generated senders and receivers with the same function IDs,",
+                " * but the messages are passed in memory instead of to a Worker or window.",
+                " * This is meant to be used in unit tests.",
+                " *",
+        format!(" * The handler is the implementation of {name}. The returned `{key}` calls the handler"),
+                " * through the connection.",
+                " *",
+                " * This function is generated by the workex CLI tool",
+                " */",
+    ];
+    let signature = Signature {
+        name: format!("{prefix}{name}Loopback"),
+        params: format!("handler: {name}"),
+        param_idents: "handler".to_string(),
+        output: format!("{{ {key}: {name}; connection: WxCloseController }}"),
+    };
+    let body: Vec<Code> = vec![
+        "const [creatorA, creatorB] = wxLoopback();".into(),
+        cblock! {
+            "const [a, b] = await Promise.all([", [
+                format!("creatorA({{ api: {prefix}{name}(handler) }}),"),
+                format!("creatorB({{ api: {prefix}{name}() }}),"),
+            ],
+            "]);"
+        }.into(),
+        "if (a.err) { return { err: a.err }; }".into(),
+        "if (b.err) { return { err: b.err }; }".into(),
+        format!("return {{ val: {{ {key}: b.val.protocols.api, connection: b.val.connection }} }};").into(),
+    ];
+    (doc, signature, body)
+}
+
+/// Convert an interface name to the key in the handlers and output object
+fn to_camel_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use codize::{cblock, cconcat};
+
+use crate::ir;
+
+/// Emit /interfaces/INTERFACE.mock.ts, or INTERFACE.mock.js and INTERFACE.mock.d.ts for JavaScript
+pub fn emit_interface_mock(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    interface: &ir::Interface,
+    out_dir: &Path,
+) -> cu::Result<()> {
+    let target = pkg.target;
+    let name = &interface.name;
+    let mut imports = interface.imports.clone();
+    let ident_wxmock = imports.add_workex_value_import("WxMock");
+    imports.map_relative_paths(|path| target.import_path(path));
+    let import_interface = format!(
+        "import type {{ {name} }} from \"{}\";",
+        target.import_path(&format!("../{}", interface.filename))
+    );
+
+    #[rustfmt::skip]
+    let doc = cconcat![
+                "/**",
+        format!(" * Mock implementation of {name} for unit tests"),
+                " *",
+                " * Every call is recorded in `wxCalls`. The results are configured with",
+                " * `wxSetResult` and `wxSetResultOnce`, see `WxMock` for more information",
+                " *",
+                " * This class is generated by the workex CLI tool",
+                " */",
+    ];
+
+    let class_decl = cblock! {
+        match target {
+            ir::Target::Ts => format!("export class {name}Mock extends {ident_wxmock}<{name}> implements {name} {{"),
+            ir::Target::Js => format!("export class {name}Mock extends {ident_wxmock} {{"),
+        },
+        [cconcat!(interface.functions.iter().enumerate().map(|(i, f)| {
+            let function = f.to_mock_function(&imports.ident_wxpromise, target);
+            if i == 0 { function } else { cconcat!["", function].into() }
+        }))],
+        "}"
+    };
+
+    let code = match target {
+        ir::Target::Ts => cconcat![import_interface, "", imports.to_code(), "", doc, class_decl,],
+        ir::Target::Js => {
+            let dts_class_decl = cblock! {
+                format!("export declare class {name}Mock extends {ident_wxmock}<{name}> implements {name} {{"),
+                [cconcat!(interface.functions.iter().enumerate().map(|(i, f)| {
+                    let declaration = f.to_send_declaration(&imports.ident_wxpromise);
+                    if i == 0 { declaration } else { cconcat!["", declaration].into() }
+                }))],
+                "}"
+            };
+            let dts = cconcat![
+                import_interface,
+                "",
+                imports.to_code(),
+                "",
+                doc.clone(),
+                dts_class_decl,
+            ];
+            let path = out_dir.join(format!("{name}.mock.d.ts"));
+            super::header::write_ts(out, pkg, &path, &interface.filename, &dts)?;
+
+            let value_imports = imports.to_value_code().unwrap_or_else(|| cconcat![].into());
+            cconcat![value_imports, "", doc, class_decl]
+        }
+    };
+
+    let path = out_dir.join(format!("{name}.mock.{}", target.extension()));
+    super::header::write_ts(out, pkg, &path, &interface.filename, &code)?;
+
+    Ok(())
+}
@@ -0,0 +1,433 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use codize::{Code, Concat, cblock, cconcat};
+use cu::pre::*;
+
+use crate::{diag, ir};
+
+/// Emit the Rust bindings for all interfaces into one module
+pub fn emit_rust(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    path: &Path,
+) -> cu::Result<()> {
+    let mut code = cconcat![
+        "#![allow(dead_code)]",
+        "",
+        "use serde::{Deserialize, Serialize};",
+        "use serde_json::Value;",
+        "",
+        // traits authored in Rust, and the types they use, are in the parent module
+        if pkg.interfaces.keys().any(|name| pkg.is_from_rust(name)) {
+            cconcat!["#[allow(unused_imports)]", "use super::*;", ""]
+        } else {
+            Concat::empty()
+        },
+        "/// The protocol identifier",
+        format!(
+            "pub const PROTOCOL: &str = {};",
+            super::quoted(&pkg.protocol)
+        ),
+        "",
+        match &pkg.rust_runtime {
+            Some(runtime) => cconcat![
+                format!("pub use {runtime}::{{WxError, WxResult, WxSender}};"),
+                "",
+                helper_decls(),
+            ],
+            None => {
+                let has_oneway = pkg
+                    .interfaces
+                    .values()
+                    .any(|i| i.functions.iter().any(|f| f.oneway.is_some()));
+                cconcat![runtime_decls(has_oneway), "", helper_decls()]
+            }
+        },
+    ];
+
+    for interface in pkg.interfaces.values() {
+        let is_from_rust = pkg.is_from_rust(&interface.name);
+        let interface_code =
+            emit_rust_interface(&pkg.rust_types, interface, is_from_rust, func_map)
+                .with_context(|| format!("failed to emit Rust bindings for {}", interface.name))?;
+        code.push("".into());
+        code.push(interface_code);
+    }
+
+    super::header::write_rust(out, pkg, path, &code)?;
+    Ok(())
+}
+
+/// Types shared by all generated interfaces, if not using a runtime crate.
+///
+/// `send_one_way` is only declared on the sender if there are one-way functions
+fn runtime_decls(has_oneway: bool) -> Concat {
+    cconcat![
+        "/// Workex error object, containing an error code and optionally a message",
+        "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]",
+        cblock! {
+            "pub struct WxError {", [
+                "pub code: String,",
+                "#[serde(default, skip_serializing_if = \"Option::is_none\")]",
+                "pub message: Option<String>,",
+            ],
+            "}"
+        },
+        "",
+        cblock! {
+            "impl WxError {", [
+                cblock! {
+                    "pub fn new(code: impl Into<String>) -> Self {",
+                    [ "Self { code: code.into(), message: None }" ],
+                    "}"
+                },
+            ],
+            "}"
+        },
+        "",
+        "pub type WxResult<T> = Result<T, WxError>;",
+        "",
+        "/// Sender used by generated clients to execute remote calls",
+        "///",
+        "/// The arguments are sent as an array, and the return value is the `val` of the result",
+        cblock! {
+            "pub trait WxSender {", [
+                "fn send(&self, fid: u32, args: Value) -> impl std::future::Future<Output = WxResult<Value>>;",
+                "",
+                "/// Same as `send`, but with a timeout for this call instead of the default",
+                cblock! {
+                    "fn send_with_timeout(&self, fid: u32, args: Value, timeout: std::time::Duration) -> impl std::future::Future<Output = WxResult<Value>> {", [
+                        "let _ = timeout;",
+                        "self.send(fid, args)",
+                    ],
+                    "}"
+                },
+                if has_oneway {
+                    cconcat![
+                        "",
+                        "/// Send a call without waiting for the other side. The other side never replies",
+                        "///",
+                        "/// By default, this is sent as a normal call and the return value is ignored",
+                        cblock! {
+                            "fn send_one_way(&self, fid: u32, args: Value) -> impl std::future::Future<Output = WxResult<()>> {", [
+                                "let result = self.send(fid, args);",
+                                "async move { result.await.map(|_| ()) }",
+                            ],
+                            "}"
+                        },
+                    ]
+                } else {
+                    Concat::empty()
+                },
+            ],
+            "}"
+        },
+    ]
+}
+
+/// Helpers used by the generated code
+fn helper_decls() -> Concat {
+    cconcat![
+        cblock! {
+            "fn wx_to_value<T: Serialize>(value: T) -> WxResult<Value> {", [
+                cblock! {
+                    "serde_json::to_value(value).map_err(|e| WxError {", [
+                        "code: \"Fail\".to_string(),",
+                        "message: Some(e.to_string()),",
+                    ],
+                    "})"
+                }
+            ],
+            "}"
+        },
+        "",
+        cblock! {
+            "fn wx_from_value<T: for<'de> Deserialize<'de>>(value: Value) -> WxResult<T> {", [
+                cblock! {
+                    "serde_json::from_value(value).map_err(|e| WxError {", [
+                        "code: \"InvalidRequestData\".to_string(),",
+                        "message: Some(e.to_string()),",
+                    ],
+                    "})"
+                }
+            ],
+            "}"
+        },
+        "",
+        cblock! {
+            "fn wx_args(args: Value) -> WxResult<std::vec::IntoIter<Value>> {", [
+                cblock! {
+                    "match args {", [
+                        "Value::Array(args) => Ok(args.into_iter()),",
+                        "_ => Err(WxError::new(\"InvalidRequestData\")),",
+                    ],
+                    "}"
+                }
+            ],
+            "}"
+        },
+    ]
+}
+
+/// Emit the trait, client and dispatcher for one interface.
+///
+/// The trait is not emitted if it's authored in Rust
+fn emit_rust_interface(
+    types: &ir::RustTypeMap,
+    interface: &ir::Interface,
+    is_from_rust: bool,
+    func_map: &BTreeMap<String, u32>,
+) -> cu::Result<Code> {
+    let name = &interface.name;
+    let client_name = format!("{name}Client");
+    let dispatch_name = format!("dispatch_{}", to_snake_case(name));
+
+    let mut trait_fns: Vec<Code> = Vec::with_capacity(interface.functions.len());
+    let mut client_fns: Vec<Code> = Vec::with_capacity(interface.functions.len());
+    let mut dispatch_arms = Vec::with_capacity(interface.functions.len());
+
+    for f in &interface.functions {
+        if f.stream.is_some() {
+            cu::bail!(
+                "{}.{} returns a stream, which is not supported in Rust bindings yet",
+                name,
+                f.name
+            );
+        }
+        if let Some(arg) = f.args.iter().find(|arg| arg.callback) {
+            cu::bail!(
+                "{}.{} takes a callback `{}`, which is not supported in Rust bindings yet",
+                name,
+                f.name,
+                arg.ident
+            );
+        }
+        let fn_name = match &f.rust_name {
+            Some(name) => name.clone(),
+            None => rust_ident(&to_snake_case(&f.name)),
+        };
+        let funcid_expr = format!(
+            "{} /* {}.{} */",
+            func_map.get(&format!("{}_{}", name, f.name)).unwrap(),
+            name,
+            f.name
+        );
+
+        // cancellation is not supported in Rust, and the signal is not sent as data
+        if let Some(arg) = f.args.iter().find(|arg| arg.signal) {
+            diag::report(
+                diag::Diagnostic::new(
+                    diag::Code::UnsupportedInRust,
+                    format!(
+                        "{}.{}: the AbortSignal `{}` is not supported in Rust bindings, so the calls from Rust cannot be cancelled",
+                        name, f.name, arg.ident
+                    ),
+                )
+                .with_file(&interface.path)
+                .with_span(f.span),
+            );
+        }
+        let mut params = Vec::with_capacity(f.args.len());
+        for arg in f.args.iter().filter(|arg| !arg.signal) {
+            if let Some(typ) = &arg.rust_typ {
+                params.push((rust_ident(&to_snake_case(&arg.ident)), typ.clone()));
+                continue;
+            }
+            let typ = types.to_rust(&arg.typ).with_context(|| {
+                format!(
+                    "failed to map type of argument `{}` in {}.{}",
+                    arg.ident, name, f.name
+                )
+            })?;
+            let typ = if arg.optional && !typ.starts_with("Option<") {
+                format!("Option<{typ}>")
+            } else {
+                typ
+            };
+            params.push((rust_ident(&to_snake_case(&arg.ident)), typ));
+        }
+        let retty = match &f.rust_retty {
+            Some(retty) => retty.clone(),
+            None => {
+                let retty = f.retty_ann.as_str();
+                let retty = retty.strip_prefix('<').unwrap_or(retty);
+                let retty = retty.strip_suffix('>').unwrap_or(retty);
+                types
+                    .to_rust(retty)
+                    .with_context(|| format!("failed to map return type of {}.{}", name, f.name))?
+            }
+        };
+
+        let signature = format!(
+            "async fn {fn_name}(&self{}) -> WxResult<{retty}>",
+            params
+                .iter()
+                .map(|(ident, typ)| format!(", {ident}: {typ}"))
+                .collect::<String>()
+        );
+
+        if !trait_fns.is_empty() {
+            trait_fns.push("".into());
+        }
+        if let Some(comment) = f.comment.to_rust_code() {
+            trait_fns.push(comment);
+        }
+        trait_fns.push(format!("{signature};").into());
+
+        if !client_fns.is_empty() {
+            client_fns.push("".into());
+        }
+        client_fns.push(
+            cblock! {
+                format!("{signature} {{"), [
+                    format!(
+                        "let args = Value::Array(vec![{}]);",
+                        params
+                            .iter()
+                            .map(|(ident, _)| format!("wx_to_value({ident})?"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    match (&f.oneway, f.timeout) {
+                        (Some(_), _) => format!("self.sender.send_one_way({funcid_expr}, args).await"),
+                        (None, None) => format!("wx_from_value(self.sender.send({funcid_expr}, args).await?)"),
+                        (None, Some(timeout)) => format!(
+                            "wx_from_value(self.sender.send_with_timeout({funcid_expr}, args, std::time::Duration::from_millis({timeout})).await?)"
+                        ),
+                    },
+                ],
+                "}"
+            }
+            .into(),
+        );
+
+        let mut arm = Vec::with_capacity(params.len() + 1);
+        for (i, (_, typ)) in params.iter().enumerate() {
+            arm.push(format!(
+                "let a{i}: {typ} = wx_from_value(args.next().unwrap_or_default())?;"
+            ));
+        }
+        arm.push(format!(
+            "wx_to_value(handler.{fn_name}({}).await?)",
+            (0..params.len())
+                .map(|i| format!("a{i}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        dispatch_arms.push(cblock! {
+            format!("{funcid_expr} => {{"),
+            arm,
+            "}"
+        });
+    }
+
+    let mut code = Concat::empty();
+    if !is_from_rust {
+        if let Some(comment) = interface.comment.to_rust_code() {
+            code.push(comment);
+        }
+        code.push(
+            cconcat![
+                "#[allow(async_fn_in_trait)]",
+                cblock! {
+                    format!("pub trait {name} {{"),
+                    trait_fns,
+                    "}"
+                },
+                "",
+            ]
+            .into(),
+        );
+    }
+    code.push(cconcat![
+        format!("/// Client for calling {name} on the other side"),
+        cblock! {
+            format!("pub struct {client_name}<S: WxSender> {{"),
+            [ "sender: S," ],
+            "}"
+        },
+        "",
+        cblock! {
+            format!("impl<S: WxSender> {client_name}<S> {{"), [
+                cblock! {
+                    "pub fn new(sender: S) -> Self {",
+                    [ "Self { sender }" ],
+                    "}"
+                }
+            ],
+            "}"
+        },
+        "",
+        cblock! {
+            format!("impl<S: WxSender> {name} for {client_name}<S> {{"),
+            client_fns,
+            "}"
+        },
+        "",
+        format!("/// Dispatch an incoming call to the {name} implementation"),
+        "///",
+        "/// The returned value should be sent back to the caller as the return value",
+        cblock! {
+            format!("pub async fn {dispatch_name}<H: {name}>(handler: &H, fid: u32, args: Value) -> WxResult<Value> {{"), [
+                if interface.functions.iter().any(|f| f.args.iter().any(|arg| !arg.signal)) {
+                    "let mut args = wx_args(args)?;"
+                } else {
+                    "let _ = args;"
+                },
+                cblock! {
+                    "match fid {",
+                    [
+                        cconcat!(dispatch_arms),
+                        Code::from("_ => Err(WxError::new(\"UnknownFunction\")),"),
+                    ],
+                    "}"
+                }
+            ],
+            "}"
+        },
+    ].into());
+
+    Ok(code.into())
+}
+
+/// Convert a camelCase or PascalCase identifier to snake_case
+fn to_snake_case(ident: &str) -> String {
+    let mut out = String::with_capacity(ident.len() + 4);
+    let mut prev_lower = false;
+    for c in ident.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else {
+            out.push(c);
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
+
+/// Escape the identifier with `r#` if it's a Rust keyword
+fn rust_ident(ident: &str) -> String {
+    #[rustfmt::skip]
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type",
+        "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+        "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+    ];
+    // these keywords cannot be raw identifiers
+    const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+    if NON_RAW_KEYWORDS.contains(&ident) {
+        format!("{ident}_")
+    } else if KEYWORDS.contains(&ident) {
+        format!("r#{ident}")
+    } else {
+        ident.to_string()
+    }
+}
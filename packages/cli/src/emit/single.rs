@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use codize::{Concat, cconcat};
+use cu::pre::*;
+
+use crate::ir;
+
+/// Emit all sender classes, receivers and bind functions into one module at `path`,
+/// for `--single-file`.
+///
+/// Only the bind functions are exported. The imports of all interfaces are combined,
+/// with the relative paths rebased to the directory of the file
+pub fn emit_single_file(
+    out: &mut super::Output,
+    pkg: &ir::Package,
+    func_map: &BTreeMap<String, u32>,
+    path: &Path,
+) -> cu::Result<()> {
+    let Some(file_dir) = path.parent() else {
+        cu::bail!("cannot get parent directory of {}", path.display());
+    };
+    // the relative paths in the imports are relative to the output directory
+    let rebase = |from: &str| relative_import(file_dir, &pkg.out_dir.join(from));
+
+    let mut imports = ir::Imports::new(vec![]);
+    let mut impls = vec![];
+    for interface in pkg.interfaces.values() {
+        let names = super::ImplNames::internal(interface);
+        let mut interface_impl = super::make_interface_impl(pkg, func_map, interface, &names);
+        interface_impl.imports.map_relative_paths(rebase);
+        imports
+            .merge(&interface_impl.imports)
+            .with_context(|| format!("failed to combine the imports for {}", interface.name))?;
+        imports.add_named_import(
+            true,
+            &interface.name,
+            &rebase(&format!("../{}", interface.filename)),
+        )?;
+        impls.push((interface, names, interface_impl));
+    }
+    imports.add_named_import(true, "WxProtocolBindConfig", ir::WORKEX_IMPORT)?;
+    if pkg.trace {
+        imports.add_named_import(false, "wxTraceRecver", ir::WORKEX_IMPORT)?;
+        imports.add_named_import(false, "wxTraceSender", ir::WORKEX_IMPORT)?;
+    }
+
+    let mut code = Concat::empty();
+    code.push(imports.to_code());
+    code.push("".into());
+    code.push(
+        cconcat![
+            "/*",
+            " * These generated implementations are used internally by the bind functions.",
+            " * They should not be used directly!",
+            " */",
+        ]
+        .into(),
+    );
+    for (interface, _, interface_impl) in &impls {
+        let comment = interface.comment.to_code();
+        code.push("".into());
+        if let Some(comment) = comment.clone() {
+            code.push(comment);
+        }
+        code.push(interface_impl.sender_decl.clone());
+        code.push("".into());
+        if let Some(comment) = comment {
+            code.push(comment);
+        }
+        code.push(interface_impl.recver_decl.clone());
+    }
+
+    for (interface, names, _) in &impls {
+        let links = pkg.links(&interface.name);
+        if links.is_empty() {
+            code.push("".into());
+            code.push(
+                super::make_bind_config(
+                    pkg,
+                    func_map,
+                    interface,
+                    None,
+                    &names.sender,
+                    &names.recver,
+                )
+                .into(),
+            );
+        }
+        for link in links {
+            let Some(linked) = pkg.interfaces.get(&link.interface) else {
+                continue;
+            };
+            // the receiver is for the interface implemented on this side
+            let recver = super::ImplNames::internal(linked).recver;
+            code.push("".into());
+            code.push(
+                super::make_bind_config(
+                    pkg,
+                    func_map,
+                    interface,
+                    Some(link),
+                    &names.sender,
+                    &recver,
+                )
+                .into(),
+            );
+        }
+    }
+
+    super::header::write_ts(out, pkg, path, &pkg.source_files().join(", "), &code)
+}
+
+/// Get the path to import `target` from a module in `dir`, starting with `./` or `../`
+fn relative_import(dir: &Path, target: &Path) -> String {
+    let dir = normalize_lexically(dir);
+    let target = normalize_lexically(target);
+    let dir_components = dir.components().collect::<Vec<_>>();
+    let target_components = target.components().collect::<Vec<_>>();
+    let common = dir_components
+        .iter()
+        .zip(&target_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts = vec![];
+    for _ in common..dir_components.len() {
+        parts.push("..".to_string());
+    }
+    for c in &target_components[common..] {
+        parts.push(c.as_os_str().to_string_lossy().into_owned());
+    }
+    let path = parts.join("/");
+    if path.starts_with("../") {
+        path
+    } else {
+        format!("./{path}")
+    }
+}
+
+/// Remove `.` and resolve `..` in the path without accessing the file system,
+/// since the import paths might not have the extension of the file
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,416 @@
+use codize::{Format, FormatCode};
+
+use crate::ir;
+
+/// Render the code with the style.
+///
+/// codize only handles the indentation, the rest of the style is applied
+/// to the rendered lines, which only needs to understand comments, strings
+/// and brackets in the generated code
+pub fn render(code: &impl FormatCode, style: &ir::Style) -> String {
+    let (format, indent_unit) = if style.use_tabs {
+        (Format::indent_tab(), "\t".to_string())
+    } else {
+        (
+            Format::indent(style.indent_width as i32),
+            " ".repeat(style.indent_width),
+        )
+    };
+
+    let mut lines: Vec<Line> = vec![];
+    let mut in_comment = false;
+    for line in code.format_vec_with(&format) {
+        let start_in_comment = in_comment;
+        let line = Line::new(line, &mut in_comment);
+        let line = line.with_quote(style.quote.as_char(), start_in_comment);
+        if start_in_comment || in_comment {
+            lines.push(line);
+        } else {
+            break_line(line, style, &indent_unit, &mut lines);
+        }
+    }
+
+    let mut out = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        let mut text = line.text.clone();
+        let next = lines[i + 1..].iter().find(|l| !l.text.trim().is_empty());
+        if let Some(last) = line.last_code_char() {
+            let remove = match line.text.as_bytes()[last] {
+                b';' => style.semi == Some(false) && !next.is_some_and(Line::needs_semi_before),
+                b',' => {
+                    style.trailing_comma == Some(ir::TrailingComma::None)
+                        && next.is_some_and(Line::starts_with_close)
+                }
+                _ => false,
+            };
+            if remove {
+                text.remove(last);
+            }
+        }
+        out.push(text);
+    }
+
+    let newline = style.newline();
+    let mut output = out.join(newline);
+    if style.final_newline && !output.is_empty() {
+        output.push_str(newline);
+    }
+    output
+}
+
+/// Kind of each byte in a rendered line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Code,
+    Str,
+    Comment,
+}
+
+/// A rendered line, with the kind of each byte
+struct Line {
+    text: String,
+    kinds: Vec<Kind>,
+}
+
+impl Line {
+    /// Classify the line. `in_comment` is whether the line starts inside a block comment,
+    /// and is updated to whether the line ends inside one
+    fn new(text: String, in_comment: &mut bool) -> Self {
+        let bytes = text.as_bytes();
+        let mut kinds = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if *in_comment {
+                kinds.push(Kind::Comment);
+                if bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/') {
+                    kinds.push(Kind::Comment);
+                    i += 1;
+                    *in_comment = false;
+                }
+                i += 1;
+                continue;
+            }
+            match bytes[i] {
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    kinds.resize(bytes.len(), Kind::Comment);
+                    break;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    kinds.extend([Kind::Comment, Kind::Comment]);
+                    i += 2;
+                    *in_comment = true;
+                    continue;
+                }
+                quote @ (b'"' | b'\'' | b'`') => {
+                    kinds.push(Kind::Str);
+                    i += 1;
+                    while i < bytes.len() {
+                        kinds.push(Kind::Str);
+                        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+                            kinds.push(Kind::Str);
+                            i += 1;
+                        } else if bytes[i] == quote {
+                            break;
+                        }
+                        i += 1;
+                    }
+                }
+                _ => kinds.push(Kind::Code),
+            }
+            i += 1;
+        }
+        Self { text, kinds }
+    }
+
+    /// Change the quote of the string literals, unless the string contains the new quote
+    fn with_quote(self, quote: char, start_in_comment: bool) -> Self {
+        let mut text = String::with_capacity(self.text.len());
+        let mut i = 0;
+        while i < self.text.len() {
+            if self.kinds[i] != Kind::Str {
+                let end = (i..self.text.len())
+                    .find(|j| self.kinds[*j] == Kind::Str)
+                    .unwrap_or(self.text.len());
+                text.push_str(&self.text[i..end]);
+                i = end;
+                continue;
+            }
+            let end = self.string_end(i);
+            text.push_str(&requote(&self.text[i..end], quote));
+            i = end;
+        }
+        let mut in_comment = start_in_comment;
+        Self::new(text, &mut in_comment)
+    }
+
+    /// Get the end (exclusive) of the string literal starting at `start`
+    fn string_end(&self, start: usize) -> usize {
+        let bytes = self.text.as_bytes();
+        let quote = bytes[start];
+        let mut i = start + 1;
+        while i < bytes.len() {
+            if bytes[i] == b'\\' {
+                i += 2;
+                continue;
+            }
+            if bytes[i] == quote {
+                return i + 1;
+            }
+            i += 1;
+        }
+        bytes.len()
+    }
+
+    /// Index of the last non-whitespace code byte, if only comments and whitespaces are after it
+    fn last_code_char(&self) -> Option<usize> {
+        let bytes = self.text.as_bytes();
+        let last = (0..bytes.len())
+            .rev()
+            .find(|i| self.kinds[*i] != Kind::Comment && !bytes[*i].is_ascii_whitespace())?;
+        (self.kinds[last] == Kind::Code).then_some(last)
+    }
+
+    /// The first non-whitespace code byte, if the line starts with code
+    fn first_code_char(&self) -> Option<u8> {
+        let bytes = self.text.as_bytes();
+        let first = (0..bytes.len()).find(|i| !bytes[*i].is_ascii_whitespace())?;
+        (self.kinds[first] == Kind::Code).then_some(bytes[first])
+    }
+
+    /// If the line starts with a closing bracket
+    fn starts_with_close(&self) -> bool {
+        matches!(self.first_code_char(), Some(b')' | b']' | b'}'))
+    }
+
+    /// If the previous statement must end with a semicolon, since
+    /// the line would otherwise continue it
+    fn needs_semi_before(&self) -> bool {
+        let bytes = self.text.as_bytes();
+        let first = (0..bytes.len()).find(|i| !bytes[*i].is_ascii_whitespace());
+        first.is_some_and(|i| matches!(bytes[i], b'(' | b'[' | b'`'))
+    }
+
+    /// Width of the line, with tabs counted as `tab_width`
+    fn width(&self, tab_width: usize) -> usize {
+        self.text
+            .chars()
+            .map(|c| if c == '\t' { tab_width } else { 1 })
+            .sum()
+    }
+}
+
+/// Change the quote of a string literal (including the quotes)
+fn requote(literal: &str, quote: char) -> String {
+    let Some(old) = literal.chars().next() else {
+        return literal.to_string();
+    };
+    if old == quote || old == '`' || literal.len() < 2 || !literal.ends_with(old) {
+        return literal.to_string();
+    }
+    let inner = &literal[1..literal.len() - 1];
+    if inner.contains(quote) {
+        return literal.to_string();
+    }
+    let mut out = String::with_capacity(literal.len());
+    out.push(quote);
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // the old quote does not need to be escaped anymore
+            Some(next) if next == old => out.push(next),
+            Some(next) => {
+                out.push(c);
+                out.push(next);
+            }
+            None => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+/// Split the line into multiple lines if it's longer than the line width,
+/// by putting the items in a bracket on separate lines
+fn break_line(line: Line, style: &ir::Style, indent_unit: &str, out: &mut Vec<Line>) {
+    if style.line_width == 0 || line.width(style.indent_width) <= style.line_width {
+        out.push(line);
+        return;
+    }
+    let Some((open, close, commas)) = find_group(&line) else {
+        out.push(line);
+        return;
+    };
+    let text = &line.text;
+    let indent = &text[..text.len() - text.trim_start().len()];
+    let item_indent = format!("{indent}{indent_unit}");
+
+    let mut items = vec![];
+    let mut start = open + 1;
+    for comma in commas.into_iter().chain(std::iter::once(close)) {
+        let item = text[start..comma].trim();
+        if !item.is_empty() {
+            items.push(item);
+        }
+        start = comma + 1;
+    }
+
+    let mut lines = vec![text[..=open].trim_end().to_string()];
+    let count = items.len();
+    for (i, item) in items.into_iter().enumerate() {
+        // rest parameters cannot have a trailing comma
+        let comma = i + 1 < count
+            || (style.trailing_comma != Some(ir::TrailingComma::None) && !item.starts_with("..."));
+        let comma = if comma { "," } else { "" };
+        lines.push(format!("{item_indent}{item}{comma}"));
+    }
+    lines.push(format!("{indent}{}", &text[close..]));
+
+    for line in lines {
+        let mut in_comment = false;
+        let line = Line::new(line, &mut in_comment);
+        if in_comment {
+            out.push(line);
+        } else {
+            break_line(line, style, indent_unit, out);
+        }
+    }
+}
+
+/// Find the bracket to break the line at, returning the position of the open and close
+/// brackets, and the commas that separate the items.
+///
+/// The outermost bracket with multiple items is used, or the first one if there are multiple.
+/// Brackets with one item or with statements (i.e. `;`) are not broken
+fn find_group(line: &Line) -> Option<(usize, usize, Vec<usize>)> {
+    let bytes = line.text.as_bytes();
+    // (open position, commas, has statements)
+    let mut stack: Vec<(usize, Vec<usize>, bool)> = vec![];
+    let mut angle_depth: Vec<usize> = vec![];
+    // (open, close, depth, commas)
+    let mut groups = vec![];
+    for (i, c) in bytes.iter().enumerate() {
+        if line.kinds[i] != Kind::Code {
+            continue;
+        }
+        match c {
+            b'(' | b'[' | b'{' => {
+                stack.push((i, vec![], false));
+                angle_depth.push(0);
+            }
+            b')' | b']' | b'}' => {
+                angle_depth.pop();
+                let Some((open, commas, has_statements)) = stack.pop() else {
+                    continue;
+                };
+                if !has_statements {
+                    groups.push((open, i, stack.len(), commas));
+                }
+            }
+            // type arguments, such as `Record<string, never>`
+            b'<' if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') => {
+                if let Some(depth) = angle_depth.last_mut() {
+                    *depth += 1;
+                }
+            }
+            b'>' if i > 0 && bytes[i - 1] != b'=' => {
+                if let Some(depth) = angle_depth.last_mut() {
+                    *depth = depth.saturating_sub(1);
+                }
+            }
+            b',' => {
+                if angle_depth.last() == Some(&0)
+                    && let Some((_, commas, _)) = stack.last_mut()
+                {
+                    commas.push(i);
+                }
+            }
+            b';' => {
+                if let Some((_, _, has_statements)) = stack.last_mut() {
+                    *has_statements = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(_, _, _, commas)| !commas.is_empty())
+        .min_by_key(|(open, _, depth, _)| (*depth, *open))
+        .map(|(open, close, _, commas)| (open, close, commas))
+}
+
+#[cfg(test)]
+mod tests {
+    use codize::{Code, cblock, cconcat};
+
+    use super::*;
+
+    fn line(text: &str) -> Line {
+        Line::new(text.to_string(), &mut false)
+    }
+
+    #[test]
+    fn test_requote() {
+        assert_eq!(requote("\"hello\"", '\''), "'hello'");
+        assert_eq!(requote("'hello'", '"'), "\"hello\"");
+        assert_eq!(requote("\"it's\"", '\''), "\"it's\"");
+        assert_eq!(requote("'say \\'hi\\''", '"'), "\"say 'hi'\"");
+        assert_eq!(requote("\"a\\nb\"", '\''), "'a\\nb'");
+        assert_eq!(requote("`template`", '\''), "`template`");
+        assert_eq!(requote("\"", '\''), "\"");
+    }
+
+    #[test]
+    fn test_find_group() {
+        let text = "foo(a, bar(b, c), d);";
+        let (open, close, commas) = find_group(&line(text)).unwrap();
+        assert_eq!((open, close), (3, 19));
+        assert_eq!(commas, [5, 16]);
+
+        // type arguments are not split
+        let text = "f(x: Record<string, number>)";
+        assert_eq!(find_group(&line(text)), None);
+        // brackets with statements are not split
+        let text = "{ a(); b(); }";
+        assert_eq!(find_group(&line(text)), None);
+        // commas in strings are not split
+        let text = "f(\"a, b\")";
+        assert_eq!(find_group(&line(text)), None);
+    }
+
+    #[test]
+    fn test_render_no_semi() {
+        let style = ir::Style {
+            semi: Some(false),
+            ..Default::default()
+        };
+        let code = cconcat![
+            "const a = 1;",
+            "[a].forEach(f);",
+            cblock! {"function f() {", ["return a;"], "}"},
+            "// comment;",
+        ];
+        assert_eq!(
+            render(&code, &style),
+            "const a = 1;\n[a].forEach(f)\nfunction f() {\n    return a\n}\n// comment;"
+        );
+    }
+
+    #[test]
+    fn test_render_line_width() {
+        let style = ir::Style {
+            line_width: 20,
+            quote: ir::Quote::Single,
+            ..Default::default()
+        };
+        let code = Code::from("call(\"first\", \"second\");");
+        assert_eq!(
+            render(&code, &style),
+            "call(\n    'first',\n    'second',\n);"
+        );
+    }
+}
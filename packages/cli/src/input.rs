@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use cu::pre::*;
+
+/// Reads the files needed for generating the code: the input files, the header template
+/// and the `.editorconfig` files.
+///
+/// The generator doesn't read the files in other ways, so it can run without direct
+/// access to the file system (for example, in WASI), or with inputs that are not saved yet
+pub trait InputReader {
+    /// Read the file as a UTF-8 string
+    fn read_string(&self, path: &Path) -> cu::Result<String>;
+
+    /// Check if the path is an existing file
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Get the absolute path with `.` and `..` resolved, for finding the output paths
+    fn normalize(&self, path: &Path) -> cu::Result<PathBuf>;
+}
+
+/// Read the files from the file system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FsReader;
+
+impl InputReader for FsReader {
+    fn read_string(&self, path: &Path) -> cu::Result<String> {
+        cu::fs::read_string(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn normalize(&self, path: &Path) -> cu::Result<PathBuf> {
+        // relative paths cannot be canonicalized in WASI, so make it absolute first,
+        // which gives the same result on other platforms
+        let path = cu::check!(
+            std::path::absolute(path),
+            "failed to get absolute path: {}",
+            path.display()
+        )?;
+        path.normalize()
+    }
+}
+
+/// Content of some files in memory, such as the files opened in an editor,
+/// with the other files read by another reader
+pub struct MemoryReader<'a> {
+    /// Content of the files, by the path as given in the options
+    pub sources: &'a BTreeMap<String, String>,
+    /// Reader for the files not in `sources`
+    pub fallback: &'a dyn InputReader,
+}
+
+impl MemoryReader<'_> {
+    fn get(&self, path: &Path) -> Option<&String> {
+        self.sources.get(path.to_str()?)
+    }
+}
+
+impl InputReader for MemoryReader<'_> {
+    fn read_string(&self, path: &Path) -> cu::Result<String> {
+        match self.get(path) {
+            Some(source) => Ok(source.clone()),
+            None => self.fallback.read_string(path),
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.get(path).is_some() || self.fallback.is_file(path)
+    }
+
+    fn normalize(&self, path: &Path) -> cu::Result<PathBuf> {
+        self.fallback.normalize(path)
+    }
+}
@@ -1,9 +1,16 @@
 /// An argument, with an identifier, type, and optional flag
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Arg {
     pub ident: String,
     pub optional: bool,
     pub typ: String,
+    /// If the argument is a function, which is passed to the other side as a callback
+    pub callback: bool,
+    /// If the argument is a trailing `AbortSignal` for cancelling the call,
+    /// which is not sent as data
+    pub signal: bool,
+    /// The type in the source trait, if the interface is authored in Rust
+    pub rust_typ: Option<String>,
 }
 
 impl Arg {
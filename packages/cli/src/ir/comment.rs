@@ -1,7 +1,7 @@
 use codize::{Code, cconcat};
 
 /// A block of documentation comments
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct CommentBlock {
     /// The style of the comment block
     pub style: CommentStyle,
@@ -12,10 +12,16 @@ pub struct CommentBlock {
 impl CommentBlock {
     /// Convert this comment block to string representation
     pub fn to_code(&self) -> Option<Code> {
+        self.to_code_with_style(self.style)
+    }
+
+    /// Convert this comment block to string representation, with the given style
+    /// instead of the original style
+    pub fn to_code_with_style(&self, style: CommentStyle) -> Option<Code> {
         if self.lines.is_empty() {
             return None;
         }
-        match self.style {
+        match style {
             CommentStyle::TripleSlash => {
                 Some(cconcat!(self.lines.iter().map(|line| format!("/// {line}"))).into())
             }
@@ -35,6 +41,90 @@ impl CommentBlock {
             ),
         }
     }
+
+    /// Get the content after each `@tag` in this comment block, for example
+    /// `tags("transfer")` returns `buffer` for a line `@transfer buffer`.
+    ///
+    /// Only the line with the tag is included, see [`CommentBlock::doc_tags`]
+    /// for the full content of the tags
+    pub fn tags<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a str> {
+        self.lines
+            .iter()
+            .filter_map(move |line| match split_tag_line(line) {
+                Some((name, rest)) if name == tag => Some(rest),
+                _ => None,
+            })
+    }
+
+    /// Parse the block tags in this comment block, such as `@param` and `@deprecated`.
+    ///
+    /// The content of a tag continues on the following lines until the next tag.
+    pub fn doc_tags(&self) -> Vec<DocTag> {
+        let mut tags = vec![];
+        let mut current: Option<(&str, Vec<&str>)> = None;
+        for line in &self.lines {
+            match split_tag_line(line) {
+                Some((name, rest)) => {
+                    if let Some((name, content)) = current.take() {
+                        tags.push(DocTag::new(name, &content));
+                    }
+                    current = Some((name, vec![rest]));
+                }
+                None => {
+                    if let Some((_, content)) = &mut current {
+                        content.push(line.trim());
+                    }
+                }
+            }
+        }
+        if let Some((name, content)) = current {
+            tags.push(DocTag::new(name, &content));
+        }
+        tags
+    }
+
+    /// Get the reason in the `@deprecated` tag, if the tag exists.
+    /// The reason is empty if the tag has no content
+    pub fn deprecated(&self) -> Option<String> {
+        self.doc_tags().into_iter().find_map(|tag| match tag {
+            DocTag::Deprecated(reason) => Some(reason),
+            _ => None,
+        })
+    }
+
+    /// Add a tag line to the end of this comment block, separated from
+    /// the description by an empty line
+    pub fn push_tag(&mut self, name: &str, content: &str) {
+        if self
+            .lines
+            .last()
+            .is_some_and(|x| split_tag_line(x).is_none())
+        {
+            self.lines.push(String::new());
+        }
+        if content.is_empty() {
+            self.lines.push(format!("@{name}"));
+        } else {
+            self.lines.push(format!("@{name} {content}"));
+        }
+    }
+
+    /// Convert this comment block to Rust doc comments
+    pub fn to_rust_code(&self) -> Option<Code> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        Some(
+            cconcat!(self.lines.iter().map(|line| {
+                if line.is_empty() {
+                    "///".to_string()
+                } else {
+                    format!("/// {line}")
+                }
+            }))
+            .into(),
+        )
+    }
 }
 
 /// Style of a comment block
@@ -46,3 +136,121 @@ pub enum CommentStyle {
     /// `/** ... */` comments
     JsDoc,
 }
+
+/// A block tag in a JSDoc comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocTag {
+    /// `@deprecated reason`
+    Deprecated(String),
+    /// `@param name description`
+    Param { name: String, description: String },
+    /// `@returns description`, or `@return description`
+    Returns(String),
+    /// `@since version`
+    Since(String),
+    /// Any other tag, including the tags for workex like `@transfer` and `@timeout`
+    Custom { name: String, content: String },
+}
+
+impl DocTag {
+    /// Create a tag from the name (without `@`) and the content lines
+    fn new(name: &str, lines: &[&str]) -> Self {
+        // drop trailing empty lines between this tag and the next
+        let end = lines
+            .iter()
+            .rposition(|x| !x.is_empty())
+            .map_or(0, |x| x + 1);
+        let content = lines[..end].join("\n");
+        match name {
+            "deprecated" => Self::Deprecated(content),
+            "param" => {
+                // type annotations like `@param {string} name` are not used in TS
+                let content = content.trim_start();
+                let (name, description) = content
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((content, ""));
+                Self::Param {
+                    name: name.to_string(),
+                    description: description.trim_start().to_string(),
+                }
+            }
+            "returns" | "return" => Self::Returns(content),
+            "since" => Self::Since(content),
+            _ => Self::Custom {
+                name: name.to_string(),
+                content,
+            },
+        }
+    }
+}
+
+/// Split a line that starts with `@tag` into the tag name and the rest of the line
+fn split_tag_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim().strip_prefix('@')?;
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-')
+        .unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(end);
+    if name.is_empty() || !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((name, rest.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(lines: &[&str]) -> CommentBlock {
+        CommentBlock {
+            lines: lines.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_doc_tags() {
+        let block = comment(&[
+            "Say hello",
+            "@example.com is not a tag",
+            "",
+            "@param name the name",
+            "  to greet",
+            "@returns the greeting",
+            "@deprecated use `greet`",
+            "",
+            "@since 0.2.0",
+            "@timeout 1s",
+        ]);
+        assert_eq!(
+            block.doc_tags(),
+            [
+                DocTag::Param {
+                    name: "name".to_string(),
+                    description: "the name\nto greet".to_string(),
+                },
+                DocTag::Returns("the greeting".to_string()),
+                DocTag::Deprecated("use `greet`".to_string()),
+                DocTag::Since("0.2.0".to_string()),
+                DocTag::Custom {
+                    name: "timeout".to_string(),
+                    content: "1s".to_string(),
+                },
+            ]
+        );
+        assert_eq!(block.deprecated(), Some("use `greet`".to_string()));
+        assert_eq!(comment(&["@deprecated"]).deprecated(), Some(String::new()));
+        assert_eq!(comment(&["Not deprecated"]).deprecated(), None);
+    }
+
+    #[test]
+    fn test_push_tag() {
+        let mut block = comment(&["Say hello"]);
+        block.push_tag("deprecated", "");
+        block.push_tag("since", "0.2.0");
+        assert_eq!(
+            block.lines,
+            ["Say hello", "", "@deprecated", "@since 0.2.0"]
+        );
+    }
+}
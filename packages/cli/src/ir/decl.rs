@@ -0,0 +1,45 @@
+use crate::ir;
+
+/// A TypeScript declaration file generated from a Rust input file
+#[derive(Debug)]
+pub struct DeclFile {
+    /// Name of the generated file, including the `.ts` extension
+    pub filename: String,
+
+    /// Name of the Rust source file
+    pub source: String,
+
+    /// Types declared in the source file, in source order
+    pub types: Vec<ir::TypeDecl>,
+
+    /// Names of the interfaces declared in the source file, in source order
+    pub interfaces: Vec<String>,
+}
+
+/// A type declared in a Rust input file, to be emitted as a TypeScript type
+#[derive(Debug)]
+pub struct TypeDecl {
+    pub name: String,
+    /// The documentation comment block for this type
+    pub comment: ir::CommentBlock,
+    /// Names of the type parameters
+    pub generics: Vec<String>,
+    pub kind: TypeDeclKind,
+}
+
+#[derive(Debug)]
+pub enum TypeDeclKind {
+    /// Struct with named fields, emitted as an `interface`
+    Interface(Vec<FieldDecl>),
+    /// Everything else, emitted as a `type` alias
+    Alias(String),
+}
+
+/// A field in a struct, emitted as a property in an `interface`
+#[derive(Debug)]
+pub struct FieldDecl {
+    /// The documentation comment block for this field
+    pub comment: ir::CommentBlock,
+    /// The property, using the serialized name
+    pub arg: ir::Arg,
+}
@@ -1,41 +1,168 @@
 use codize::{Code, cblock, cconcat, clist};
 
-use crate::ir;
+use crate::{diag, ir};
 
 /// Data for a function inside an interface
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
+    /// Location of the function name in the input file
+    pub span: Option<diag::Span>,
     /// The documentation comment block for this function
     pub comment: ir::CommentBlock,
     /// Arguments for the function
     pub args: Vec<ir::Arg>,
     /// The return type parameter annotation inside WxPromise, with surrounding `<>`
     pub retty_ann: String,
+    /// Values to transfer instead of clone, annotated with `@transfer`
+    pub transfer: Vec<ir::Transfer>,
+    /// Timeout in milliseconds for calls to this function, annotated with `@timeout`
+    pub timeout: Option<u32>,
+    /// If calls to this function are one-way, i.e. the caller does not wait for
+    /// the other side, and the other side never replies
+    pub oneway: Option<OneWay>,
+    /// Identifier for `WxStream` if the function returns a stream. `retty_ann`
+    /// is the type of the items in the stream
+    pub stream: Option<String>,
+    /// Indices of the callback arguments that are not disposed when the call completes,
+    /// annotated with `@keepalive`
+    pub keep_alive: Vec<usize>,
+    /// The function name in the source trait, if the interface is authored in Rust
+    pub rust_name: Option<String>,
+    /// The return type inside `WxResult` in the source trait, if the interface is authored in Rust
+    pub rust_retty: Option<String>,
+}
+
+/// How a one-way function is declared
+#[derive(Debug, Clone, PartialEq)]
+pub enum OneWay {
+    /// Returns `WxPromise<void>` and annotated with `@oneway`. The promise
+    /// resolves as soon as the call is sent
+    Tag,
+    /// Returns `WxOneWay`, with the identifier used in the source
+    Marker(String),
 }
 
 impl Function {
-    /// Generate code for implementation in the sender impl class
-    pub fn to_send_function(&self, funcid_expr: &str, ident_wxpromise: &str) -> Code {
-        let comment = self.comment.to_code();
-        let is_void_return = self.retty_ann == "<void>";
+    /// Get the comment to put on the sender method
+    fn send_comment(&self) -> Option<Code> {
+        // IDEs only show the deprecation for JSDoc comments
+        if self.comment.deprecated().is_some() {
+            self.comment.to_code_with_style(ir::CommentStyle::JsDoc)
+        } else {
+            self.comment.to_code()
+        }
+    }
 
+    /// Get the return type of the sender method
+    fn send_return_type(&self, ident_wxpromise: &str) -> String {
+        match (&self.oneway, &self.stream) {
+            (Some(OneWay::Marker(ident_wxoneway)), _) => ident_wxoneway.clone(),
+            (_, Some(ident_wxstream)) => format!("{}{}", ident_wxstream, self.retty_ann),
+            _ => format!("{}{}", ident_wxpromise, self.retty_ann),
+        }
+    }
+
+    /// Generate the method declaration in the sender impl class, for `.d.ts` files
+    pub fn to_send_declaration(&self, ident_wxpromise: &str) -> Code {
         let function_decl = cblock! {
-            format!("public {}(", self.name),
+            format!("{}(", self.name),
             [clist!("," => self.args.iter().map(|arg| arg.to_code())).inlined()],
-            format!("): {}{}", ident_wxpromise, self.retty_ann)
+            format!("): {};", self.send_return_type(ident_wxpromise))
+        };
+        match self.send_comment() {
+            Some(comment) => cconcat![comment, function_decl].into(),
+            None => function_decl.into(),
+        }
+    }
+
+    /// Generate code for implementation in the sender impl class
+    pub fn to_send_function(
+        &self,
+        funcid_expr: &str,
+        ident_wxpromise: &str,
+        target: ir::Target,
+    ) -> Code {
+        let comment = self.send_comment();
+        // the return type is only used as the type argument in TypeScript
+        let retty_ann = match target {
+            ir::Target::Ts => self.retty_ann.as_str(),
+            ir::Target::Js => "",
+        };
+        let is_void_return = self.retty_ann == "<void>";
+        let transfer_list = self
+            .transfer
+            .iter()
+            .filter_map(|t| t.arg.map(|i| t.to_expr(&self.args[i].ident)))
+            .collect::<Vec<_>>();
+        let mut options = vec![];
+        if !transfer_list.is_empty() {
+            options.push(format!("transfer: [{}]", transfer_list.join(", ")));
+        }
+        if let Some(timeout) = self.timeout {
+            options.push(format!("timeout: {timeout}"));
+        }
+        let callbacks = self
+            .args
+            .iter()
+            .enumerate()
+            .filter(|(_, arg)| arg.callback)
+            .map(|(i, _)| i.to_string())
+            .collect::<Vec<_>>();
+        if !callbacks.is_empty() {
+            options.push(format!("callbacks: [{}]", callbacks.join(", ")));
+        }
+        if let Some(signal) = self.args.iter().find(|arg| arg.signal) {
+            options.push(format!("signal: {}", signal.ident));
+        }
+        // callbacks in one-way calls are always kept alive
+        if !self.keep_alive.is_empty() && self.oneway.is_none() {
+            let keep_alive = self.keep_alive.iter().map(|i| i.to_string());
+            options.push(format!(
+                "keepAlive: [{}]",
+                keep_alive.collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        let function_decl = match target {
+            ir::Target::Ts => cblock! {
+                format!("public {}(", self.name),
+                [clist!("," => self.args.iter().map(|arg| arg.to_code())).inlined()],
+                format!("): {}", self.send_return_type(ident_wxpromise))
+            },
+            ir::Target::Js => cblock! {
+                format!("{}(", self.name),
+                [clist!("," => self.args.iter().map(|arg| arg.ident.as_str())).inlined()],
+                ")"
+            },
         };
 
         let function_body = cblock! {
             "{",
             [cblock! {
-                if is_void_return {
+                if let Some(oneway) = &self.oneway {
+                    match oneway {
+                        // the marker type is void, so the result is not returned
+                        OneWay::Marker(_) => format!("this.sender.sendOneWay({funcid_expr}, ["),
+                        OneWay::Tag => format!("return Promise.resolve(this.sender.sendOneWay({funcid_expr}, ["),
+                    }
+                } else if self.stream.is_some() {
+                    format!("return this.sender.sendStream{retty_ann}({funcid_expr}, [")
+                } else if is_void_return {
                     format!("return this.sender.sendVoid({funcid_expr}, [")
                 } else {
-                    format!("return this.sender.send{}({}, [", self.retty_ann, funcid_expr)
+                    format!("return this.sender.send{retty_ann}({funcid_expr}, [")
                 },
-                [clist!("," => self.args.iter().map(|arg| arg.ident.as_str())).inlined()],
-                "]);"
+                // the signal is passed in the options instead of as data
+                [clist!("," => self.args.iter().filter(|arg| !arg.signal).map(|arg| arg.ident.as_str())).inlined()],
+                {
+                    let close = if self.oneway == Some(OneWay::Tag) { "))" } else { ")" };
+                    if options.is_empty() {
+                        format!("]{close};")
+                    } else {
+                        format!("], {{ {} }}{close};", options.join(", "))
+                    }
+                }
             }],
             "}"
         }
@@ -48,38 +175,329 @@ impl Function {
         }
     }
 
+    /// Generate code for implementation in the mock class, which records the call
+    /// with the methods in `WxMock`
+    pub fn to_mock_function(&self, ident_wxpromise: &str, target: ir::Target) -> Code {
+        let function_decl = match target {
+            ir::Target::Ts => cblock! {
+                format!("public {}(", self.name),
+                [clist!("," => self.args.iter().map(|arg| arg.to_code())).inlined()],
+                format!("): {}", self.send_return_type(ident_wxpromise))
+            },
+            ir::Target::Js => cblock! {
+                format!("{}(", self.name),
+                [clist!("," => self.args.iter().map(|arg| arg.ident.as_str())).inlined()],
+                ")"
+            },
+        };
+        let method = if matches!(self.oneway, Some(OneWay::Marker(_))) {
+            // the marker type is void, so the result is not returned
+            "this.wxOneWay"
+        } else if self.stream.is_some() {
+            "return this.wxStream"
+        } else if self.retty_ann == "<void>" {
+            "return this.wxCallVoid"
+        } else {
+            "return this.wxCall"
+        };
+        let function_body = cblock! {
+            "{",
+            [cblock! {
+                format!("{method}(\"{}\", [", self.name),
+                [clist!("," => self.args.iter().map(|arg| arg.ident.as_str())).inlined()],
+                "]);"
+            }],
+            "}"
+        }
+        .connected()
+        .never_inlined();
+
+        match self.send_comment() {
+            Some(comment) => cconcat![comment, function_decl, function_body].into(),
+            None => cconcat![function_decl, function_body].into(),
+        }
+    }
+
+    /// Parse the `@timeout` tag in the comment block of a function, such as `@timeout 300s`,
+    /// and return the timeout in milliseconds
+    pub fn parse_timeout(comment: &ir::CommentBlock) -> Result<Option<u32>, String> {
+        let mut tags = comment.tags("timeout");
+        let Some(tag) = tags.next() else {
+            return Ok(None);
+        };
+        if tags.next().is_some() {
+            return Err("@timeout can only be specified once".to_string());
+        }
+        let Some(unit_start) = tag.find(|c: char| !c.is_ascii_digit() && c != '.') else {
+            return Err(format!(
+                "@timeout {tag}: missing unit. Use `ms`, `s`, `m` or `h`, for example `@timeout 30s`"
+            ));
+        };
+        let (value, unit) = tag.split_at(unit_start);
+        let Ok(value) = value.parse::<f64>() else {
+            return Err(format!("@timeout {tag}: invalid number"));
+        };
+        let multiplier = match unit.trim() {
+            "ms" => 1.0,
+            "s" => 1000.0,
+            "m" => 60_000.0,
+            "h" => 3_600_000.0,
+            _ => {
+                return Err(format!(
+                    "@timeout {tag}: unknown unit `{unit}`. Use `ms`, `s`, `m` or `h`"
+                ));
+            }
+        };
+        let ms = (value * multiplier).round();
+        if ms < 1.0 {
+            return Err(format!("@timeout {tag}: timeout must be at least 1ms"));
+        }
+        // setTimeout fires immediately if the delay doesn't fit in 32-bit signed integer
+        if ms > i32::MAX as f64 {
+            return Err(format!(
+                "@timeout {tag}: timeout is too large. The maximum is about 24 days"
+            ));
+        }
+        Ok(Some(ms as u32))
+    }
+
+    /// Parse if the function is one-way, from the `@oneway` tag in the comment block,
+    /// or from `marker`, which is the `WxOneWay` identifier if the return type is declared
+    /// with it
+    pub fn parse_oneway(
+        comment: &ir::CommentBlock,
+        retty_ann: &str,
+        timeout: Option<u32>,
+        marker: Option<&str>,
+    ) -> Result<Option<OneWay>, String> {
+        let oneway = match marker {
+            Some(ident) => OneWay::Marker(ident.to_string()),
+            None => {
+                if comment.tags("oneway").next().is_none() {
+                    return Ok(None);
+                }
+                OneWay::Tag
+            }
+        };
+        if retty_ann != "<void>" {
+            return Err(
+                "@oneway: one-way functions cannot return a value, since the other side never replies. Change the return type to WxPromise<void>".to_string(),
+            );
+        }
+        if timeout.is_some() {
+            return Err(
+                "@oneway: one-way functions cannot have a @timeout, since the call does not wait for the other side".to_string(),
+            );
+        }
+        Ok(Some(oneway))
+    }
+
+    /// Parse the `@keepalive` tags in the comment block of a function, such as `@keepalive onChange`,
+    /// and return the indices of the callback arguments
+    ///
+    /// Callbacks in one-way functions must be kept alive, since the call never completes
+    pub fn parse_keep_alive(
+        comment: &ir::CommentBlock,
+        args: &[ir::Arg],
+        is_oneway: bool,
+    ) -> Result<Vec<usize>, String> {
+        let mut out = vec![];
+        for tag in comment.tags("keepalive") {
+            if tag.is_empty() {
+                return Err("@keepalive requires the name of a callback argument".to_string());
+            }
+            for name in tag.split(|c: char| c == ',' || c.is_whitespace()) {
+                if name.is_empty() {
+                    continue;
+                }
+                match args.iter().position(|x| x.ident == name) {
+                    Some(i) if args[i].callback => {
+                        if !out.contains(&i) {
+                            out.push(i);
+                        }
+                    }
+                    Some(_) => {
+                        return Err(format!(
+                            "@keepalive {name}: `{name}` is not a callback. Only function arguments can be kept alive"
+                        ));
+                    }
+                    None => {
+                        return Err(format!(
+                            "@keepalive {name}: `{name}` is not an argument of the function"
+                        ));
+                    }
+                }
+            }
+        }
+        if is_oneway
+            && let Some(arg) = args
+                .iter()
+                .enumerate()
+                .find(|(i, arg)| arg.callback && !out.contains(i))
+                .map(|(_, arg)| arg)
+        {
+            return Err(format!(
+                "@keepalive: callback `{}` in a one-way function must be annotated with `@keepalive {}`, since the call never completes",
+                arg.ident, arg.ident
+            ));
+        }
+        out.sort();
+        Ok(out)
+    }
+
     /// Generate code for implementation in the receiver "switch" statement
     ///
-    /// Note the implementation uses switch statement. In my testing,
+    /// The switch statement is used by default. In my testing,
     /// switch statement is much faster than an array of functions even for 30 functions,
-    /// in both JSC (bun) and V8 (deno). Since it's unlikely for me to write more than 30 functions
-    /// in a single interface, we only use switch statement for now. However it will be interesting
-    /// to see in the future at how many functions does the array of functions becomes better on
-    /// average
-    pub fn to_recv_switch_case(&self, funcid_expr: &str) -> Code {
-        let arg_list = clist!("," => (0..self.args.len()).map(|x| format!("a{x}"))).inlined();
-        let call: Code = if self.args.is_empty() {
-            format!("return handler.{}();", self.name).into()
+    /// in both JSC (bun) and V8 (deno). For interfaces with a lot more functions,
+    /// the table can be used instead with `--dispatch`, see [`Self::to_recv_table_entry`]
+    ///
+    /// `prelude` is a statement to run before calling the handler, such as
+    /// logging the call to a deprecated function
+    pub fn to_recv_switch_case(&self, funcid_expr: &str, prelude: Option<String>) -> Code {
+        let data_len = self.args.iter().filter(|arg| !arg.signal).count();
+        let call = self.to_recv_call(|i| format!("a{i}"));
+        let call: Code = if data_len == 0 {
+            call
         } else {
+            let arg_list = clist!("," => (0..data_len).map(|x| format!("a{x}"))).inlined();
             cconcat![
                 cblock! {
                     "const [",
-                    [arg_list.clone()],
+                    [arg_list],
                     format!("] = args;")
                 },
-                cblock! {
-                    format!("return handler.{}(", self.name),
-                    [arg_list],
-                    ");"
-                }
+                call
             ]
             .into()
         };
+        let body: Code = match prelude {
+            Some(prelude) => cconcat![prelude, call].into(),
+            None => call,
+        };
         cblock! {
             format!("case {funcid_expr}: {{"),
-            [call],
+            [body],
             "}"
         }
         .into()
     }
+
+    /// Generate code for implementation in the receiver table, which is an array
+    /// of closures indexed by the function ID
+    ///
+    /// The closure reads the arguments from the array directly instead of
+    /// destructuring them. `funcid_comment` is the function ID and name to put in a comment,
+    /// and `prelude` is the same as in [`Self::to_recv_switch_case`]
+    pub fn to_recv_table_entry(&self, funcid_comment: &str, prelude: Option<String>) -> Code {
+        let uses_context = self.args.iter().any(|arg| arg.callback || arg.signal);
+        let params = if uses_context {
+            "args, context"
+        } else if self.args.is_empty() {
+            ""
+        } else {
+            "args"
+        };
+        let call = self.to_recv_call(|i| format!("args[{i}]"));
+        let body: Code = match prelude {
+            Some(prelude) => cconcat![prelude, call].into(),
+            None => call,
+        };
+        cblock! {
+            format!("/* {funcid_comment} */ ({params}) => {{"),
+            [body],
+            "},"
+        }
+        .never_inlined()
+        .into()
+    }
+
+    /// Generate the statement that calls the handler in the receiver and returns the result.
+    ///
+    /// `arg_expr` is the expression for the data argument at the index
+    fn to_recv_call(&self, arg_expr: impl Fn(usize) -> String) -> Code {
+        let data_len = self.args.iter().filter(|arg| !arg.signal).count();
+        // callbacks are passed as stubs that call back to the other side,
+        // and the signal is aborted when the other side cancels the call
+        let call_arg_list = clist!("," => self.args.iter().enumerate().map(|(i, arg)| {
+            if arg.signal {
+                "context.signal()".to_string()
+            } else if arg.callback {
+                format!("context.callback({})", arg_expr(i))
+            } else {
+                arg_expr(i)
+            }
+        }))
+        .inlined();
+        // attach the transfer list for the return value to the result
+        let return_transfer_list = self
+            .transfer
+            .iter()
+            .filter(|t| t.arg.is_none())
+            .map(|t| t.to_expr("r.val"))
+            .collect::<Vec<_>>();
+        let call_end = if self.stream.is_some() {
+            // the bus iterates the stream and sends the chunks
+            ") });".to_string()
+        } else if return_transfer_list.is_empty() {
+            ");".to_string()
+        } else {
+            format!(
+                ").then((r) => (r.err ? r : {{ val: r.val, transfer: [{}] }}));",
+                return_transfer_list.join(", ")
+            )
+        };
+        let call_start = if self.stream.is_some() {
+            format!("return Promise.resolve({{ stream: handler.{}(", self.name)
+        } else {
+            format!("return handler.{}(", self.name)
+        };
+        if self.args.is_empty() {
+            format!("{call_start}{call_end}").into()
+        } else if data_len == 0 {
+            // only the signal is passed
+            format!("{call_start} context.signal() {call_end}").into()
+        } else {
+            cblock! {
+                call_start,
+                [call_arg_list],
+                call_end
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_timeout(lines: &[&str]) -> Result<Option<u32>, String> {
+        let comment = ir::CommentBlock {
+            lines: lines.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        };
+        Function::parse_timeout(&comment)
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout(&["No timeout"]), Ok(None));
+        assert_eq!(parse_timeout(&["@timeout 250ms"]), Ok(Some(250)));
+        assert_eq!(parse_timeout(&["@timeout 30s"]), Ok(Some(30_000)));
+        assert_eq!(parse_timeout(&["@timeout 1.5s"]), Ok(Some(1500)));
+        assert_eq!(parse_timeout(&["@timeout 2 m"]), Ok(Some(120_000)));
+        assert_eq!(parse_timeout(&["@timeout 1h"]), Ok(Some(3_600_000)));
+    }
+
+    #[test]
+    fn test_parse_timeout_errors() {
+        let error = |lines: &[&str]| parse_timeout(lines).unwrap_err();
+        assert!(error(&["@timeout 1s", "@timeout 2s"]).contains("only be specified once"));
+        assert!(error(&["@timeout 30"]).contains("missing unit"));
+        assert!(error(&["@timeout 1.2.3s"]).contains("invalid number"));
+        assert!(error(&["@timeout 3d"]).contains("unknown unit"));
+        assert!(error(&["@timeout 0.1ms"]).contains("at least 1ms"));
+        assert!(error(&["@timeout 600h"]).contains("too large"));
+    }
 }
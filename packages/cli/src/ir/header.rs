@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use cu::pre::*;
+
+use crate::{InputReader, Options};
+
+/// The header at the start of each generated file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Lines of the header comment, with the placeholders not substituted yet
+    template: Vec<String>,
+    /// SPDX license expression
+    pub license: Option<String>,
+    /// Directives for lint tools, added after the header comment in TypeScript and JavaScript files
+    pub directives: Vec<LintDirective>,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            template: vec![
+                "This file is generated by the workex CLI Tool".to_string(),
+                String::new(),
+                "Please visit https://workex.pistonite.dev for more information".to_string(),
+            ],
+            license: None,
+            directives: vec![],
+        }
+    }
+}
+
+/// Directive for lint tools in the generated files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LintDirective {
+    /// `/* eslint-disable */`
+    EslintDisable,
+    /// `// @ts-nocheck`
+    TsNocheck,
+}
+
+impl LintDirective {
+    /// The comment for the directive
+    pub fn to_comment(self) -> &'static str {
+        match self {
+            Self::EslintDisable => "/* eslint-disable */",
+            Self::TsNocheck => "// @ts-nocheck",
+        }
+    }
+}
+
+/// Values to substitute into the header template
+pub struct HeaderValues<'a> {
+    /// Protocol identifier
+    pub protocol: &'a str,
+    /// Input file(s) the generated file is generated from
+    pub source: &'a str,
+    /// Hash of the generated content after the header
+    pub hash: &'a str,
+}
+
+/// Placeholders supported in the header template
+const PLACEHOLDERS: &[&str] = &["version", "protocol", "source", "hash"];
+
+impl Header {
+    /// Create the header from the CLI options
+    pub fn try_new(cli: &Options, reader: &dyn InputReader) -> cu::Result<Self> {
+        let mut header = Self::default();
+        if let Some(path) = &cli.header_file {
+            let content = reader.read_string(Path::new(path))?;
+            let template = content
+                .trim_end()
+                .lines()
+                .map(|line| line.trim_end().to_string())
+                .collect::<Vec<_>>();
+            for line in &template {
+                if line.contains("*/") {
+                    cu::bail!("header template cannot contain `*/`, since it's put in a comment");
+                }
+                validate_placeholders(line).context("invalid header template")?;
+            }
+            header.template = template;
+        }
+        if let Some(license) = &cli.license {
+            let license = license.trim();
+            if license.is_empty() || license.contains(['\n', '\r']) {
+                cu::bail!("--license must be a SPDX license expression, for example `MIT`");
+            }
+            header.license = Some(license.to_string());
+        }
+        for directive in &cli.lint_directive {
+            if !header.directives.contains(directive) {
+                header.directives.push(*directive);
+            }
+        }
+        Ok(header)
+    }
+
+    /// If the header uses the content hash. If not, the hash does not need to be computed
+    pub fn needs_hash(&self) -> bool {
+        self.template.iter().any(|line| line.contains("{hash}"))
+    }
+
+    /// Get the lines of the header comment, with the placeholders substituted
+    pub fn lines(&self, values: &HeaderValues) -> Vec<String> {
+        self.template
+            .iter()
+            .map(|line| {
+                line.replace("{version}", env!("CARGO_PKG_VERSION"))
+                    .replace("{protocol}", values.protocol)
+                    .replace("{source}", values.source)
+                    .replace("{hash}", values.hash)
+            })
+            .collect()
+    }
+}
+
+/// Check that the placeholders in the line are supported
+fn validate_placeholders(line: &str) -> cu::Result<()> {
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let name = &rest[..end];
+        if !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !PLACEHOLDERS.contains(&name)
+        {
+            cu::bail!(
+                "unknown placeholder `{{{name}}}`, supported placeholders are: {}",
+                PLACEHOLDERS
+                    .iter()
+                    .map(|x| format!("{{{x}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Create the header with the template as the content of `--header-file`
+    fn from_template(template: &str) -> cu::Result<Header> {
+        let path = "header.txt".to_string();
+        let sources = BTreeMap::from([(path.clone(), template.to_string())]);
+        let reader = crate::MemoryReader {
+            sources: &sources,
+            fallback: &crate::FsReader,
+        };
+        let options = Options {
+            header_file: Some(path),
+            ..Default::default()
+        };
+        Header::try_new(&options, &reader)
+    }
+
+    #[test]
+    fn test_header_placeholders() {
+        let template = "Generated by workex {version}  \n\nprotocol: {protocol}, from {source}\nhash: {hash}\n\n";
+        let header = from_template(template).unwrap();
+        assert!(header.needs_hash());
+        let values = HeaderValues {
+            protocol: "test",
+            source: "src/api.ts",
+            hash: "0123456789abcdef",
+        };
+        assert_eq!(
+            header.lines(&values),
+            vec![
+                format!("Generated by workex {}", env!("CARGO_PKG_VERSION")),
+                String::new(),
+                "protocol: test, from src/api.ts".to_string(),
+                "hash: 0123456789abcdef".to_string(),
+            ]
+        );
+
+        // braces that are not placeholders are kept
+        let header = from_template("map: { [key: string]: {} }").unwrap();
+        assert!(!header.needs_hash());
+        let values = HeaderValues {
+            protocol: "",
+            source: "",
+            hash: "",
+        };
+        assert_eq!(header.lines(&values), vec!["map: { [key: string]: {} }"]);
+        assert!(!Header::default().needs_hash());
+    }
+
+    #[test]
+    fn test_header_empty() {
+        let header = from_template("\n").unwrap();
+        let values = HeaderValues {
+            protocol: "test",
+            source: "",
+            hash: "",
+        };
+        assert_eq!(header.lines(&values), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_header_invalid() {
+        let error = from_template("end */").unwrap_err();
+        assert!(error.to_string().contains("cannot contain `*/`"));
+        let error = from_template("{name}").unwrap_err();
+        assert!(format!("{error:?}").contains("unknown placeholder `{name}`"));
+
+        for license in ["", "  ", "MIT\nApache-2.0"] {
+            let options = Options {
+                license: Some(license.to_string()),
+                ..Default::default()
+            };
+            assert!(Header::try_new(&options, &crate::FsReader).is_err());
+        }
+    }
+
+    #[test]
+    fn test_header_license_and_directives() {
+        let options = Options {
+            license: Some(" MIT OR Apache-2.0 ".to_string()),
+            lint_directive: vec![
+                LintDirective::TsNocheck,
+                LintDirective::EslintDisable,
+                LintDirective::TsNocheck,
+            ],
+            ..Default::default()
+        };
+        let header = Header::try_new(&options, &crate::FsReader).unwrap();
+        assert_eq!(header.license.as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(
+            header.directives,
+            vec![LintDirective::TsNocheck, LintDirective::EslintDisable]
+        );
+    }
+}
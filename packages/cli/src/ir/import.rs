@@ -1,10 +1,10 @@
 use codize::{Code, cblock, cconcat, clist};
 use cu::pre::*;
 
-static WORKEX_IMPORT: &str = "@pistonite/workex";
+pub static WORKEX_IMPORT: &str = "@pistonite/workex";
 
 /// Imports used in the generated implementation files
-#[derive(Debug, Deref, DerefMut)]
+#[derive(Debug, Clone, Deref, DerefMut)]
 pub struct ImplImports {
     #[deref]
     #[deref_mut]
@@ -21,7 +21,6 @@ impl ImplImports {
     pub fn new(mut imports: Imports) -> Self {
         let (_, ident_wxhandler) = imports.add_workex_type_import("WxBusRecvHandler");
         let (_, ident_wxsender) = imports.add_workex_type_import("WxProtocolBoundSender");
-        imports.adjust_relative_to_from_parent();
         Self {
             inner: imports,
             ident_wxhandler,
@@ -39,6 +38,10 @@ pub struct Imports {
     pub ident_wxpromise: String,
     /// Check if WxPromise exists in the original imports
     pub was_wxpromise_imported: bool,
+    /// Identifier for `WxOneWay`, if it's imported in the original imports
+    pub ident_wxoneway: Option<String>,
+    /// Identifier for `WxStream`, if it's imported in the original imports
+    pub ident_wxstream: Option<String>,
 }
 
 impl Imports {
@@ -47,7 +50,11 @@ impl Imports {
             statements,
             was_wxpromise_imported: false,
             ident_wxpromise: String::new(),
+            ident_wxoneway: None,
+            ident_wxstream: None,
         };
+        imports.ident_wxoneway = imports.find_workex_import("WxOneWay");
+        imports.ident_wxstream = imports.find_workex_import("WxStream");
         // make sure the WxPromise import is available
         let (added, ident) = imports.add_workex_type_import("WxPromise");
         imports.ident_wxpromise = ident;
@@ -55,6 +62,17 @@ impl Imports {
         imports
     }
 
+    /// Find the identifier used in code for an existing workex import
+    pub fn find_workex_import(&self, ident: &str) -> Option<String> {
+        self.statements.iter().find_map(|x| match x {
+            Import::Import { idents, .. } if x.is_workex() => idents
+                .iter()
+                .find(|x| x.ident == ident)
+                .map(|x| x.active_ident().to_string()),
+            _ => None,
+        })
+    }
+
     /// Add a workex type import to existing imports if it doesn't exist yet,
     /// and return the type identifier to use in code.
     ///
@@ -95,6 +113,44 @@ impl Imports {
         }
     }
 
+    /// Add a workex value (non-type) import if it doesn't exist yet,
+    /// and return the identifier to use in code.
+    ///
+    /// Type-only import statements cannot have value imports, so a new statement
+    /// is added if there is no existing value import statement from workex
+    pub fn add_workex_value_import(&mut self, ident: &str) -> String {
+        let existing = self
+            .statements
+            .iter_mut()
+            .find(|x| matches!(x, Import::Import { is_type: false, .. }) && x.is_workex());
+        match existing {
+            Some(Import::Import { idents, .. }) => {
+                for x in idents.iter() {
+                    if x.ident == ident && !x.is_type {
+                        return x.active_ident().to_string();
+                    }
+                }
+                idents.push(ImportIdent {
+                    is_type: false,
+                    ident: ident.to_string(),
+                    rename: None,
+                });
+            }
+            _ => {
+                self.statements.push(Import::Import {
+                    is_type: false,
+                    idents: vec![ImportIdent {
+                        is_type: false,
+                        ident: ident.to_string(),
+                        rename: None,
+                    }],
+                    from: WORKEX_IMPORT.to_string(),
+                });
+            }
+        }
+        ident.to_string()
+    }
+
     /// Adjust the import paths so that relative paths (starting with ./ or ../) are resolved
     /// from the parent directory (i.e. with ../ added before)
     ///
@@ -116,9 +172,139 @@ impl Imports {
         }
     }
 
+    /// Change the relative import paths with the function, used to
+    /// rewrite the extensions for the output target
+    pub fn map_relative_paths(&mut self, f: impl Fn(&str) -> String) {
+        for import in &mut self.statements {
+            if let Import::Import { from, .. } = import
+                && (from.starts_with("./") || from.starts_with("../"))
+            {
+                *from = f(from);
+            }
+        }
+    }
+
+    /// Add the import statements of another module that are not already imported,
+    /// used to combine multiple modules into one file.
+    ///
+    /// Errors if the same identifier is imported from different places
+    pub fn merge(&mut self, other: &Imports) -> cu::Result<()> {
+        for statement in &other.statements {
+            self.merge_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    /// Add `import { ident } from "from"`, or `import type` if `is_type`,
+    /// if it's not already imported
+    pub fn add_named_import(&mut self, is_type: bool, ident: &str, from: &str) -> cu::Result<()> {
+        self.merge_statement(&Import::Import {
+            is_type,
+            idents: vec![ImportIdent {
+                is_type: false,
+                ident: ident.to_string(),
+                rename: None,
+            }],
+            from: from.to_string(),
+        })
+    }
+
+    fn merge_statement(&mut self, statement: &Import) -> cu::Result<()> {
+        let Import::Import {
+            is_type,
+            idents,
+            from,
+        } = statement
+        else {
+            if !self.statements.contains(statement) {
+                self.statements.push(statement.clone());
+            }
+            return Ok(());
+        };
+        for ident in idents {
+            let active_ident = ident.active_ident();
+            if let Some((existing_from, existing_ident)) = self.find_binding(active_ident) {
+                if existing_from == from && existing_ident == ident.ident {
+                    continue;
+                }
+                cu::bail!(
+                    "`{active_ident}` is imported from both \"{existing_from}\" and \"{from}\""
+                );
+            }
+            let existing = self.statements.iter_mut().find(|x| {
+                matches!(x, Import::Import { is_type: t, from: f, .. } if t == is_type && f == from)
+            });
+            match existing {
+                Some(Import::Import { idents, .. }) => idents.push(ident.clone()),
+                _ => self.statements.push(Import::Import {
+                    is_type: *is_type,
+                    idents: vec![ident.clone()],
+                    from: from.clone(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the identifiers bound by the import statements, as `(active_ident, ident, from)`.
+    /// Opaque imports are not included
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.statements.iter().flat_map(|x| match x {
+            Import::Import { idents, from, .. } => idents
+                .iter()
+                .map(|x| (x.active_ident(), x.ident.as_str(), from.as_str()))
+                .collect::<Vec<_>>(),
+            Import::Opaque(_) => vec![],
+        })
+    }
+
+    /// Find where an identifier used in code is imported from, as `(from, ident)`
+    fn find_binding(&self, active_ident: &str) -> Option<(&str, &str)> {
+        self.statements.iter().find_map(|x| match x {
+            Import::Import { idents, from, .. } => idents
+                .iter()
+                .find(|x| x.active_ident() == active_ident)
+                .map(|x| (from.as_str(), x.ident.as_str())),
+            _ => None,
+        })
+    }
+
     pub fn to_code(&self) -> Code {
         cconcat!(self.statements.iter().map(|x| x.to_code())).into()
     }
+
+    /// Emit only the value imports from workex, for JavaScript output where
+    /// the types are not used. Returns `None` if there are no value imports
+    pub fn to_value_code(&self) -> Option<Code> {
+        let statements = self.statements.iter().filter_map(|x| match x {
+            Import::Import {
+                is_type: false,
+                idents,
+                from,
+            } if x.is_workex() => {
+                let idents = idents
+                    .iter()
+                    .filter(|x| !x.is_type)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if idents.is_empty() {
+                    return None;
+                }
+                let import = Import::Import {
+                    is_type: false,
+                    idents,
+                    from: from.clone(),
+                };
+                Some(import.to_code())
+            }
+            _ => None,
+        });
+        let statements = statements.collect::<Vec<_>>();
+        if statements.is_empty() {
+            return None;
+        }
+        Some(cconcat!(statements).into())
+    }
 }
 
 /// An `import` statement
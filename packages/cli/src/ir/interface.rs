@@ -1,6 +1,8 @@
-use crate::ir;
+use std::collections::BTreeMap;
 
-#[derive(Debug)]
+use crate::{diag, ir};
+
+#[derive(Debug, Clone)]
 pub struct Interface {
     /// The name for this interface
     pub name: String,
@@ -9,9 +11,20 @@ pub struct Interface {
     /// This is for generating the `import` statements to import this interface
     pub filename: String,
 
+    /// Path of the input file where the interface is defined, as given to the CLI.
+    /// For interfaces authored in Rust, this is the Rust file
+    pub path: String,
+
+    /// Location of the interface name in the input file
+    pub span: Option<diag::Span>,
+
     /// The comment block for this interface
     pub comment: ir::CommentBlock,
 
+    /// Import statements from the source file, adjusted to use in the generated files
+    /// in the output directory
+    pub imports: ir::Imports,
+
     /// Import statements from the source file, adjusted to use in the generated implementation
     /// file
     pub impl_imports: ir::ImplImports,
@@ -24,17 +37,37 @@ impl Interface {
     pub fn new(
         name: String,
         filename: String,
+        path: String,
+        span: Option<diag::Span>,
         comment: ir::CommentBlock,
-        imports: ir::Imports,
+        mut imports: ir::Imports,
         functions: Vec<ir::Function>,
     ) -> Self {
-        let impl_imports = ir::ImplImports::new(imports);
+        imports.adjust_relative_to_from_parent();
+        let impl_imports = ir::ImplImports::new(imports.clone());
         Self {
             name,
             filename,
+            path,
+            span,
             comment,
+            imports,
             impl_imports,
             functions,
         }
     }
 }
+
+/// Make map from function name (`{Interface}_{function}`) to function id
+pub fn make_func_id_map(interfaces: &BTreeMap<String, Interface>) -> BTreeMap<String, u32> {
+    let mut map = BTreeMap::new();
+    // 0-15 are reserved function id for internal use in the workex library
+    let mut i = 16;
+    for interface in interfaces.values() {
+        for function in &interface.functions {
+            map.insert(format!("{}_{}", interface.name, function.name), i);
+            i += 1;
+        }
+    }
+    map
+}
@@ -2,11 +2,23 @@ mod arg;
 pub use arg::*;
 mod comment;
 pub use comment::*;
+mod decl;
+pub use decl::*;
 mod function;
 pub use function::*;
+mod header;
+pub use header::*;
 mod import;
 pub use import::*;
 mod interface;
 pub use interface::*;
 mod package;
 pub use package::*;
+mod rust_type;
+pub use rust_type::*;
+mod style;
+pub use style::*;
+mod transfer;
+pub use transfer::*;
+mod validate;
+pub use validate::*;
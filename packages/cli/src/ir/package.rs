@@ -1,9 +1,9 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
 use cu::pre::*;
 
-use crate::{CliOptions, ir};
+use crate::{InputReader, Options, diag, ir};
 
 /// Struct to store information about all parsed inputs and CLI inputs
 #[derive(Debug)]
@@ -14,66 +14,333 @@ pub struct Package {
     /// Function prefix
     pub prefix: String,
 
-    /// The pairs of interfaces that are linked together
+    /// The links of each interface, sorted by the name of the linked interface
     ///
     /// Both directions are stored
-    pub linkage: BTreeMap<String, String>,
+    pub linkage: BTreeMap<String, Vec<Link>>,
 
     /// All interfaces in the package, sorted by name
     pub interfaces: BTreeMap<String, ir::Interface>,
 
+    /// TypeScript declaration files to generate for the Rust inputs
+    pub decl_files: Vec<ir::DeclFile>,
+
     /// Output directory for the generated files
     ///
     /// This is inferred from the input directory, plus the `dir` CLI option
     pub out_dir: PathBuf,
 
+    /// Generate everything into this module instead of the output directory
+    pub single_file: Option<PathBuf>,
+
     /// Do not generate the .gitignore file
     pub no_gitignore: bool,
+
+    /// Output path for the Rust bindings, if enabled
+    pub rust_out: Option<PathBuf>,
+
+    /// Mapping from TypeScript types to Rust types for the Rust bindings
+    pub rust_types: ir::RustTypeMap,
+
+    /// Crate to import the runtime types from in the Rust bindings
+    pub rust_runtime: Option<String>,
+
+    /// Log the first call to each deprecated function in the generated receivers
+    pub log_deprecated: bool,
+
+    /// Language of the generated files
+    pub target: Target,
+
+    /// Generate mock implementations for the interfaces
+    pub mock: bool,
+
+    /// Generate the helpers to connect both sides in the same thread
+    pub loopback: bool,
+
+    /// Generate the index file that exports the public API of the generated code
+    pub index: bool,
+
+    /// Wrap the generated senders and receivers with tracing hooks
+    pub trace: bool,
+
+    /// How the generated receivers dispatch the calls to the handler
+    pub dispatch: Dispatch,
+
+    /// Minimum number of functions in an interface to use the table with `--dispatch auto`
+    pub dispatch_threshold: usize,
+
+    /// Code style of the generated TypeScript and JavaScript files
+    pub style: ir::Style,
+
+    /// Header at the start of each generated file
+    pub header: ir::Header,
+}
+
+/// Language of the generated files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Target {
+    /// TypeScript files
+    #[default]
+    Ts,
+    /// ESM JavaScript files, with `.d.ts` declaration files for the types
+    Js,
+}
+
+/// How the generated receivers dispatch the calls to the handler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Dispatch {
+    /// A switch statement on the function ID
+    #[default]
+    Switch,
+    /// An array of closures indexed by the function ID
+    Table,
+    /// The table for interfaces with at least `--dispatch-threshold` functions,
+    /// and the switch statement otherwise
+    Auto,
+}
+
+impl Target {
+    /// Extension of the generated implementation files
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ts => "ts",
+            Self::Js => "js",
+        }
+    }
+
+    /// Rewrite a relative import path to a TypeScript file for this target.
+    ///
+    /// For JavaScript, the extension is changed to the one of the emitted file
+    /// (for example, `./foo.ts` becomes `./foo.js`), which TypeScript resolves
+    /// to the source file or the `.d.ts` declarations
+    pub fn import_path(self, path: &str) -> String {
+        if self == Self::Ts || !(path.starts_with("./") || path.starts_with("../")) {
+            return path.to_string();
+        }
+        for (from, to) in [
+            (".d.ts", ".js"),
+            (".tsx", ".js"),
+            (".mts", ".mjs"),
+            (".cts", ".cjs"),
+            (".ts", ".js"),
+        ] {
+            if let Some(stem) = path.strip_suffix(from) {
+                return format!("{stem}{to}");
+            }
+        }
+        path.to_string()
+    }
 }
 
 impl Package {
+    /// Get the names of the input files that have interfaces, sorted
+    pub fn source_files(&self) -> Vec<&str> {
+        let mut files = self
+            .interfaces
+            .values()
+            .map(|i| i.filename.as_str())
+            .collect::<Vec<_>>();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Check if the receiver of the interface should use the table for dispatching
+    pub fn use_dispatch_table(&self, interface: &ir::Interface) -> bool {
+        match self.dispatch {
+            Dispatch::Switch => false,
+            Dispatch::Table => true,
+            Dispatch::Auto => interface.functions.len() >= self.dispatch_threshold,
+        }
+    }
+
     pub fn try_new(
-        cli: &CliOptions,
+        cli: &Options,
+        reader: &dyn InputReader,
         interfaces: BTreeMap<String, ir::Interface>,
+        decl_files: Vec<ir::DeclFile>,
     ) -> cu::Result<Self> {
-        let out_dir = get_out_dir(cli).context("Failed to infer output directory")?;
+        let out_dir = get_out_dir(cli, reader).context("Failed to infer output directory")?;
 
-        let protocol = cli.protocol.clone();
+        let protocol = cli.protocol.clone().unwrap_or_default();
         if protocol.is_empty() {
             cu::bail!("Protocol identifier cannot be empty");
         }
 
-        let prefix = cli.prefix.as_deref().unwrap_or(&cli.protocol).to_string();
-        if prefix.is_empty() {
-            cu::bail!("Prefix must be a valid non-empty JS identifier");
-        }
+        let prefix = match cli.prefix.as_deref() {
+            Some(prefix) => {
+                if !ir::is_identifier(prefix) {
+                    cu::bail!("Prefix must be a valid non-empty JS identifier, but got `{prefix}`");
+                }
+                prefix.to_string()
+            }
+            None => {
+                if !ir::is_default_prefix(&protocol) {
+                    cu::bail!(
+                        "The protocol `{protocol}` can only be used as the prefix if it only contains lowercase alphabetic characters, specify one with --prefix"
+                    );
+                }
+                protocol.clone()
+            }
+        };
 
         // process interface linkage
-        let mut linkage = BTreeMap::new();
-        for link_str in &cli.link {
-            let (first, second) = parse_link(link_str)?;
+        let mut pairs = vec![];
+        // the group of interfaces connected by the links, by protocol and interface name,
+        // to find cycles
+        let mut groups = BTreeMap::new();
+        for (i, link_str) in cli.link.iter().enumerate() {
+            let link = parse_link(link_str)?;
+            let (first, second) = (link.first, link.second);
             if !interfaces.contains_key(first) {
                 cu::bail!("Interface not found: {}", first);
             }
             if !interfaces.contains_key(second) {
                 cu::bail!("Interface not found: {}", second);
             }
-            if let Some(old_second) = linkage.insert(first.to_string(), second.to_string()) {
+            if first == second {
+                cu::bail!("Cannot link {} with itself", first);
+            }
+            let (link_protocol, link_prefix) = match link.protocol {
+                None => (protocol.as_str(), prefix.as_str()),
+                Some(link_protocol) => {
+                    let link_prefix = match link.prefix {
+                        Some(link_prefix) => {
+                            if !ir::is_identifier(link_prefix) {
+                                cu::bail!(
+                                    "Prefix of the link {first},{second} must be a valid non-empty JS identifier, but got `{link_prefix}`"
+                                );
+                            }
+                            link_prefix
+                        }
+                        None => {
+                            if !ir::is_default_prefix(link_protocol) {
+                                cu::bail!(
+                                    "The protocol `{link_protocol}` of the link {first},{second} can only be used as the prefix if it only contains lowercase alphabetic characters, specify one with `{first},{second}@{link_protocol}=PREFIX`"
+                                );
+                            }
+                            link_protocol
+                        }
+                    };
+                    (link_protocol, link_prefix)
+                }
+            };
+            if pairs.iter().any(|(a, b, p, _)| {
+                *p == link_protocol && ((*a, *b) == (first, second) || (*a, *b) == (second, first))
+            }) {
                 cu::bail!(
-                    "Linking {0} with {1}, but {0} is already linked with {2}",
+                    "{} and {} are linked more than once under the `{}` protocol",
                     first,
                     second,
-                    old_second
+                    link_protocol
                 );
             }
-            if let Some(old_first) = linkage.insert(second.to_string(), first.to_string()) {
-                cu::bail!(
-                    "Linking {0} with {1}, but {0} is already linked with {2}",
-                    second,
-                    first,
-                    old_first
-                );
+            let group_first = groups.get(&(link_protocol, first)).copied();
+            let group_second = groups.get(&(link_protocol, second)).copied();
+            match (group_first, group_second) {
+                (Some(a), Some(b)) if a == b => {
+                    cu::bail!(
+                        "Linking {0} with {1} creates a cycle under the `{2}` protocol, since {0} and {1} are already connected through other links",
+                        first,
+                        second,
+                        link_protocol
+                    );
+                }
+                (Some(a), Some(b)) => {
+                    for group in groups.values_mut() {
+                        if *group == b {
+                            *group = a;
+                        }
+                    }
+                }
+                (Some(a), None) => {
+                    groups.insert((link_protocol, second), a);
+                }
+                (None, Some(b)) => {
+                    groups.insert((link_protocol, first), b);
+                }
+                (None, None) => {
+                    groups.insert((link_protocol, first), i);
+                    groups.insert((link_protocol, second), i);
+                }
             }
+            pairs.push((first, second, link_protocol, link_prefix));
+        }
+        let mut linkage: BTreeMap<String, Vec<Link>> = BTreeMap::new();
+        for (first, second, link_protocol, link_prefix) in pairs {
+            for (name, linked) in [(first, second), (second, first)] {
+                linkage.entry(name.to_string()).or_default().push(Link {
+                    interface: linked.to_string(),
+                    protocol: link_protocol.to_string(),
+                    prefix: link_prefix.to_string(),
+                    bind_name: String::new(),
+                });
+            }
+        }
+        // the links are in the order of the --link options here. The first link with
+        // a prefix is the primary one, and the others with the same prefix have the
+        // bind functions named after the linked interface, so adding a link does not
+        // rename the existing bind functions
+        for (name, links) in &mut linkage {
+            let mut seen_prefixes = BTreeSet::new();
+            for link in links.iter_mut() {
+                link.bind_name = if seen_prefixes.insert(link.prefix.clone()) {
+                    format!("{}{name}", link.prefix)
+                } else {
+                    format!("{}{name}With{}", link.prefix, link.interface)
+                };
+            }
+            links.sort_by(|a, b| (&a.interface, &a.protocol).cmp(&(&b.interface, &b.protocol)));
+        }
+
+        // the comments are copied to the generated code, so check that they are up to date
+        for interface in interfaces.values() {
+            for f in &interface.functions {
+                for tag in f.comment.doc_tags() {
+                    if let ir::DocTag::Param { name, .. } = tag
+                        && !f.args.iter().any(|arg| arg.ident == name)
+                    {
+                        diag::report(
+                            diag::Diagnostic::new(
+                                diag::Code::ParamMismatch,
+                                format!(
+                                    "{}.{}: @param {} does not match any argument",
+                                    interface.name, f.name, name
+                                ),
+                            )
+                            .with_file(&interface.path)
+                            .with_span(f.span),
+                        );
+                    }
+                }
+            }
+        }
+
+        let single_file = match &cli.single_file {
+            Some(path) => {
+                if cli.target == Target::Js {
+                    cu::bail!("--single-file is not supported with --target js");
+                }
+                Some(reader.normalize(Path::new(path))?)
+            }
+            None => None,
+        };
+
+        let style_dir = match single_file.as_ref().and_then(|x| x.parent()) {
+            Some(dir) => dir,
+            None => &out_dir,
+        };
+        let style = ir::Style::try_new(cli, reader, style_dir, cli.target.extension())?;
+        let header = ir::Header::try_new(cli, reader)?;
+
+        let rust_out = cli.rust.as_ref().map(PathBuf::from);
+        let rust_types = ir::RustTypeMap::try_new(&cli.rust_type)?;
+        // the traits authored in Rust return the `WxResult` of the runtime crate,
+        // so the bindings cannot declare their own
+        if rust_out.is_some() && cli.rust_runtime.is_none() && !decl_files.is_empty() {
+            cu::bail!(
+                "--rust-runtime is required with --rust when the interfaces are authored in Rust, so the bindings use the same `WxResult` as the traits"
+            );
         }
 
         Ok(Self {
@@ -81,13 +348,133 @@ impl Package {
             prefix,
             linkage,
             interfaces,
+            decl_files,
             out_dir,
+            single_file,
             no_gitignore: cli.no_gitignore,
+            rust_out,
+            rust_types,
+            rust_runtime: cli.rust_runtime.clone(),
+            log_deprecated: cli.log_deprecated,
+            target: cli.target,
+            mock: cli.mock,
+            loopback: cli.loopback,
+            index: cli.index,
+            trace: cli.trace,
+            dispatch: cli.dispatch,
+            dispatch_threshold: cli.dispatch_threshold,
+            style,
+            header,
         })
     }
 }
 
-fn parse_link(link_str: &str) -> cu::Result<(&str, &str)> {
+impl Package {
+    /// Get the links of the interface, which is empty if the interface is not linked
+    pub fn links(&self, interface: &str) -> &[Link] {
+        self.linkage
+            .get(interface)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Get the link from `interface` to `linked` under the protocol
+    pub fn find_link(&self, interface: &str, linked: &str, protocol: &str) -> Option<&Link> {
+        self.links(interface)
+            .iter()
+            .find(|x| x.interface == linked && x.protocol == protocol)
+    }
+
+    /// Get the names of the bind functions of the interface, one for each link,
+    /// or one for both sides if the interface is not linked
+    pub fn bind_names(&self, interface: &str) -> Vec<String> {
+        match self.links(interface) {
+            [] => vec![format!("{}{interface}", self.prefix)],
+            links => links.iter().map(|x| x.bind_name.clone()).collect(),
+        }
+    }
+
+    /// Get the deprecated functions as `(interface, function, reason)`,
+    /// with `@since` added to the reason if present
+    pub fn deprecated_functions(&self) -> Vec<(&ir::Interface, &ir::Function, String)> {
+        let mut out = vec![];
+        for interface in self.interfaces.values() {
+            for f in &interface.functions {
+                let mut reason = None;
+                let mut since = None;
+                for tag in f.comment.doc_tags() {
+                    match tag {
+                        ir::DocTag::Deprecated(x) => reason = Some(x),
+                        ir::DocTag::Since(x) => since = Some(x),
+                        _ => {}
+                    }
+                }
+                let Some(reason) = reason else {
+                    continue;
+                };
+                let reason = reason.replace('\n', " ");
+                let reason = match since {
+                    Some(since) if reason.is_empty() => format!("(since {since})"),
+                    Some(since) => format!("{reason} (since {since})"),
+                    None => reason,
+                };
+                out.push((interface, f, reason));
+            }
+        }
+        out
+    }
+
+    /// Check if the interface is authored as a Rust trait
+    pub fn is_from_rust(&self, interface: &str) -> bool {
+        self.decl_files
+            .iter()
+            .any(|x| x.interfaces.iter().any(|x| x == interface))
+    }
+}
+
+/// A link from an interface to another interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Name of the linked interface
+    pub interface: String,
+    /// Protocol identifier used for the connections through this link
+    pub protocol: String,
+    /// Prefix of the bind functions for this link
+    pub prefix: String,
+    /// Name of the bind function that sends calls to the interface through this link,
+    /// with an implementation of the linked interface as the handler
+    pub bind_name: String,
+}
+
+/// Value of the `--link` option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkOption<'a> {
+    /// Name of the first interface
+    pub first: &'a str,
+    /// Name of the second interface
+    pub second: &'a str,
+    /// Protocol of the link, if different from the package
+    pub protocol: Option<&'a str>,
+    /// Prefix of the bind functions, if different from the protocol of the link
+    pub prefix: Option<&'a str>,
+}
+
+/// Parse the value of `--link`, in the format of `A,B`, `A,B@PROTOCOL` or `A,B@PROTOCOL=PREFIX`
+pub fn parse_link(link_str: &str) -> cu::Result<LinkOption<'_>> {
+    let (link_str, protocol, prefix) = match link_str.split_once('@') {
+        Some((link_str, protocol)) => {
+            let (protocol, prefix) = match protocol.split_once('=') {
+                Some((protocol, prefix)) => (protocol, Some(prefix.trim())),
+                None => (protocol, None),
+            };
+            let protocol = protocol.trim();
+            if protocol.is_empty() {
+                cu::bail!("Invalid format for --link option: protocol after @ is empty");
+            }
+            (link_str, Some(protocol), prefix)
+        }
+        None => (link_str, None, None),
+    };
     let mut parts = link_str.split(',');
     let Some(first) = parts.next() else {
         cu::bail!("Invalid format for --link option: missing comma separator (,)");
@@ -106,18 +493,23 @@ fn parse_link(link_str: &str) -> cu::Result<(&str, &str)> {
     if second.is_empty() {
         cu::bail!("Invalid format for --link option: second interface name is empty");
     }
-    Ok((first, second))
+    Ok(LinkOption {
+        first,
+        second,
+        protocol,
+        prefix,
+    })
 }
 
-fn get_out_dir(cli: &CliOptions) -> cu::Result<PathBuf> {
+fn get_out_dir(cli: &Options, reader: &dyn InputReader) -> cu::Result<PathBuf> {
     let mut out_dir = match cli.inputs.first() {
         None => {
             cu::bail!("No input files provided");
         }
-        Some(path) => Path::new(path).parent_abs()?,
+        Some(path) => parent_dir(reader, path)?,
     };
     for input in cli.inputs.iter().skip(1) {
-        let path = Path::new(input).parent_abs()?;
+        let path = parent_dir(reader, input)?;
         if path != out_dir {
             cu::bail!(
                 "Input files are not in the same directory: {} and {}",
@@ -132,3 +524,12 @@ fn get_out_dir(cli: &CliOptions) -> cu::Result<PathBuf> {
     out_dir.push(&cli.dir);
     Ok(out_dir)
 }
+
+/// Get the absolute path of the directory of the input file
+fn parent_dir(reader: &dyn InputReader, input: &str) -> cu::Result<PathBuf> {
+    let mut path = reader.normalize(Path::new(input))?;
+    if !path.pop() {
+        cu::bail!("cannot get parent directory of {}", path.display());
+    }
+    Ok(path)
+}
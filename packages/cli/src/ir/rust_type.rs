@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+/// Mapping table from TypeScript types to Rust types, used by the Rust emitter
+#[derive(Debug, Default)]
+pub struct RustTypeMap {
+    /// User-provided mappings, keyed by the TypeScript type with whitespaces removed
+    custom: BTreeMap<String, String>,
+}
+
+impl RustTypeMap {
+    /// Create the mapping table from the `--rust-type TS=RUST` CLI options
+    pub fn try_new(mappings: &[String]) -> cu::Result<Self> {
+        let mut custom = BTreeMap::new();
+        for mapping in mappings {
+            let Some((ts, rust)) = mapping.split_once('=') else {
+                cu::bail!("Invalid format for --rust-type option: missing `=` separator");
+            };
+            let ts = ts.trim();
+            let rust = rust.trim();
+            if ts.is_empty() || rust.is_empty() {
+                cu::bail!("Invalid format for --rust-type option: type cannot be empty");
+            }
+            if let Some(old) = custom.insert(normalize(ts), rust.to_string()) {
+                cu::bail!("Duplicate --rust-type mapping for {ts}: {old} and {rust}");
+            }
+        }
+        Ok(Self { custom })
+    }
+
+    /// Convert a TypeScript type annotation to a Rust type.
+    ///
+    /// User-provided mappings take precedence, then the builtin mappings
+    /// for primitives, arrays, tuples, records and unions with `undefined`/`null`
+    /// are applied recursively.
+    pub fn to_rust(&self, typ: &str) -> cu::Result<String> {
+        let typ = typ.trim();
+        if let Some(rust) = self.custom.get(&normalize(typ)) {
+            return Ok(rust.clone());
+        }
+
+        // strip redundant parentheses, e.g. `(string | undefined)[]`
+        if typ.starts_with('(') && find_matching(typ, 0) == Some(typ.len() - 1) {
+            return self.to_rust(&typ[1..typ.len() - 1]);
+        }
+
+        // T | undefined, T | null
+        let union = split_top_level(typ, '|');
+        if union.len() > 1 {
+            let (nullish, rest): (Vec<_>, Vec<_>) = union
+                .into_iter()
+                .partition(|x| matches!(*x, "undefined" | "null"));
+            if nullish.is_empty() || rest.len() != 1 {
+                cu::bail!("no Rust type mapping for union type `{typ}`");
+            }
+            return Ok(format!("Option<{}>", self.to_rust(rest[0])?));
+        }
+
+        if let Some(inner) = typ.strip_prefix("readonly ") {
+            return self.to_rust(inner);
+        }
+
+        // T[]
+        if let Some(inner) = typ.strip_suffix("[]") {
+            return Ok(format!("Vec<{}>", self.to_rust(inner)?));
+        }
+
+        // [A, B]
+        if typ.starts_with('[') && find_matching(typ, 0) == Some(typ.len() - 1) {
+            let elems = split_top_level(&typ[1..typ.len() - 1], ',');
+            let elems = elems
+                .into_iter()
+                .filter(|x| !x.is_empty())
+                .map(|x| self.to_rust(x))
+                .collect::<cu::Result<Vec<_>>>()?;
+            return Ok(match elems.len() {
+                1 => format!("({},)", elems[0]),
+                _ => format!("({})", elems.join(", ")),
+            });
+        }
+
+        // Generic<A, B>
+        if let Some(lt) = typ.find('<') {
+            if find_matching(typ, lt) != Some(typ.len() - 1) {
+                cu::bail!("no Rust type mapping for `{typ}`");
+            }
+            let name = typ[..lt].trim();
+            let params = split_top_level(&typ[lt + 1..typ.len() - 1], ',');
+            return match (name, params.as_slice()) {
+                ("Array" | "ReadonlyArray", [inner]) => {
+                    Ok(format!("Vec<{}>", self.to_rust(inner)?))
+                }
+                // they are serialized as `{}` by JSON.stringify, so the data is lost
+                ("Map" | "Set" | "ReadonlyMap" | "ReadonlySet", _) => cu::bail!(
+                    "`{typ}` cannot be sent to Rust, since `{name}` is serialized as an empty object. Use a `Record` or an array instead"
+                ),
+                ("Record", [key, value]) => Ok(format!(
+                    "std::collections::HashMap<{}, {}>",
+                    self.to_rust(key)?,
+                    self.to_rust(value)?
+                )),
+                _ => cu::bail!("no Rust type mapping for `{typ}`"),
+            };
+        }
+
+        let rust = match typ {
+            "string" => "String",
+            "number" => "f64",
+            "boolean" => "bool",
+            "void" | "undefined" | "null" => "()",
+            "unknown" | "any" => "serde_json::Value",
+            "Uint8Array" => "Vec<u8>",
+            _ => cu::bail!("no Rust type mapping for `{typ}`, please specify one with --rust-type"),
+        };
+        Ok(rust.to_string())
+    }
+}
+
+/// Remove all whitespaces from the type so that lookups are not sensitive
+/// to formatting in the source file
+fn normalize(typ: &str) -> String {
+    typ.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Split the type by the separator, ignoring separators nested inside brackets
+fn split_top_level(typ: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in typ.char_indices() {
+        match c {
+            '<' | '[' | '(' | '{' => depth += 1,
+            '>' | ']' | ')' | '}' => depth -= 1,
+            _ if c == sep && depth == 0 => {
+                parts.push(typ[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(typ[start..].trim());
+    parts
+}
+
+/// Find the position of the bracket matching the one at `open`
+fn find_matching(typ: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in typ.char_indices().skip_while(|(i, _)| *i < open) {
+        match c {
+            '<' | '[' | '(' | '{' => depth += 1,
+            '>' | ']' | ')' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,338 @@
+use std::path::Path;
+
+use crate::{InputReader, Options};
+
+/// Code style of the generated TypeScript and JavaScript files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+    /// Indent with tabs instead of spaces
+    pub use_tabs: bool,
+    /// Number of spaces per indent level, also the width of a tab when measuring lines
+    pub indent_width: usize,
+    /// Quote for string literals
+    pub quote: Quote,
+    /// End all statements with semicolons, or remove them.
+    ///
+    /// If not set, the statements are kept as generated, which is the same as
+    /// older versions, where a few statements are not ended with semicolons
+    pub semi: Option<bool>,
+    /// Trailing commas in multi-line lists. If not set, the lists are kept
+    /// as generated, like `semi`
+    pub trailing_comma: Option<TrailingComma>,
+    /// Maximum width of a line before lists are split into multiple lines, 0 for no limit
+    pub line_width: usize,
+    /// End the files with a new line
+    pub final_newline: bool,
+    /// Use `\r\n` as line endings
+    pub crlf: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            use_tabs: false,
+            indent_width: 4,
+            quote: Quote::Double,
+            semi: None,
+            trailing_comma: None,
+            line_width: 0,
+            final_newline: false,
+            crlf: false,
+        }
+    }
+}
+
+/// Quote for string literals in the generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Quote {
+    #[default]
+    Double,
+    Single,
+}
+
+impl Quote {
+    pub fn as_char(self) -> char {
+        match self {
+            Self::Double => '"',
+            Self::Single => '\'',
+        }
+    }
+}
+
+/// Indent style of the generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum IndentStyle {
+    Space,
+    Tab,
+}
+
+/// Trailing commas in the generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TrailingComma {
+    /// Add trailing commas wherever possible in multi-line lists
+    #[default]
+    All,
+    /// Remove trailing commas
+    None,
+}
+
+impl Style {
+    /// Create the style for files generated in `out_dir`, for files with the extension.
+    ///
+    /// The `.editorconfig` files in `out_dir` and its parents are applied first,
+    /// then the style options from the CLI
+    pub fn try_new(
+        cli: &Options,
+        reader: &dyn InputReader,
+        out_dir: &Path,
+        extension: &str,
+    ) -> cu::Result<Self> {
+        let mut style = Self::default();
+        if !cli.no_editorconfig {
+            let file_name = format!("file.{extension}");
+            for (key, value) in load_editorconfig(reader, out_dir, &file_name)? {
+                style.apply_editorconfig(&key, &value);
+            }
+        }
+        if let Some(indent_style) = cli.indent_style {
+            style.use_tabs = indent_style == IndentStyle::Tab;
+        }
+        if let Some(indent_width) = cli.indent_width {
+            if indent_width == 0 {
+                cu::bail!("--indent-width must be greater than 0");
+            }
+            style.indent_width = indent_width;
+        }
+        if let Some(quote) = cli.quote {
+            style.quote = quote;
+        }
+        if cli.semi {
+            style.semi = Some(true);
+        }
+        if cli.no_semi {
+            style.semi = Some(false);
+        }
+        if let Some(trailing_comma) = cli.trailing_comma {
+            style.trailing_comma = Some(trailing_comma);
+        }
+        if let Some(line_width) = cli.line_width {
+            style.line_width = line_width;
+        }
+        Ok(style)
+    }
+
+    /// The line ending
+    pub fn newline(&self) -> &'static str {
+        if self.crlf { "\r\n" } else { "\n" }
+    }
+
+    /// The semicolon for the statements that are not ended with one by default
+    pub fn optional_semi(&self) -> &'static str {
+        if self.semi == Some(true) { ";" } else { "" }
+    }
+
+    /// The semicolon for the statements that are followed by more code on the same line,
+    /// so it's not removed when rendering
+    pub fn inline_semi(&self) -> &'static str {
+        if self.semi == Some(false) { "" } else { ";" }
+    }
+
+    /// The trailing comma for the lists that don't have one by default
+    pub fn optional_trailing_comma(&self) -> &'static str {
+        if self.trailing_comma == Some(TrailingComma::All) {
+            ","
+        } else {
+            ""
+        }
+    }
+
+    /// Apply one property from `.editorconfig`. Unknown properties and values are ignored
+    fn apply_editorconfig(&mut self, key: &str, value: &str) {
+        match key {
+            "indent_style" => match value {
+                "tab" => self.use_tabs = true,
+                "space" => self.use_tabs = false,
+                _ => {}
+            },
+            "indent_size" | "tab_width" => {
+                if let Ok(width) = value.parse::<usize>()
+                    && width > 0
+                {
+                    self.indent_width = width;
+                }
+            }
+            "max_line_length" => match value {
+                "off" => self.line_width = 0,
+                _ => {
+                    if let Ok(width) = value.parse() {
+                        self.line_width = width;
+                    }
+                }
+            },
+            // not in the spec, but supported by some editors and formatters
+            "quote_type" => match value {
+                "double" => self.quote = Quote::Double,
+                "single" => self.quote = Quote::Single,
+                _ => {}
+            },
+            "insert_final_newline" => match value {
+                "true" => self.final_newline = true,
+                "false" => self.final_newline = false,
+                _ => {}
+            },
+            "end_of_line" => match value {
+                "crlf" => self.crlf = true,
+                "lf" => self.crlf = false,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Load the properties that apply to a file in `dir` from the `.editorconfig` files,
+/// in the order they should be applied (i.e. the later ones take precedence)
+fn load_editorconfig(
+    reader: &dyn InputReader,
+    dir: &Path,
+    file_name: &str,
+) -> cu::Result<Vec<(String, String)>> {
+    // files closer to the directory take precedence, so collect them first
+    // and apply them from the root
+    let mut configs = vec![];
+    let mut current = Some(dir);
+    while let Some(d) = current {
+        let path = d.join(".editorconfig");
+        if reader.is_file(&path) {
+            let content = reader.read_string(&path)?;
+            let is_root = parse_editorconfig(&content)
+                .0
+                .iter()
+                .any(|(k, v)| k == "root" && v == "true");
+            configs.push((d.to_path_buf(), content));
+            if is_root {
+                break;
+            }
+        }
+        current = d.parent();
+    }
+
+    let mut out = vec![];
+    for (config_dir, content) in configs.iter().rev() {
+        // path of the file relative to the directory of the .editorconfig
+        let mut relative = match dir.strip_prefix(config_dir) {
+            Ok(relative) => relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/"),
+            Err(_) => continue,
+        };
+        if !relative.is_empty() {
+            relative.push('/');
+        }
+        relative.push_str(file_name);
+        for (glob, properties) in parse_editorconfig(content).1 {
+            if glob_matches(&glob, &relative) {
+                for (key, value) in properties {
+                    out.push((key, value));
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+type Properties = Vec<(String, String)>;
+
+/// Parse the content of a `.editorconfig` file into the preamble properties
+/// and the sections. Keys and values are lowercased
+fn parse_editorconfig(content: &str) -> (Properties, Vec<(String, Properties)>) {
+    let mut preamble = vec![];
+    let mut sections: Vec<(String, Properties)> = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            sections.push((glob.to_string(), vec![]));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let property = (
+            key.trim().to_lowercase(),
+            value.trim().to_lowercase().to_string(),
+        );
+        match sections.last_mut() {
+            Some((_, properties)) => properties.push(property),
+            None => preamble.push(property),
+        }
+    }
+    (preamble, sections)
+}
+
+/// Check if the path (relative to the `.editorconfig`, separated by `/`) matches the glob.
+///
+/// Globs without `/` match the file name in any directory. `*`, `**`, `?`, `[...]`
+/// and `{a,b}` are supported
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let glob = match glob.strip_prefix('/') {
+        Some(glob) => glob.to_string(),
+        None if !glob.contains('/') => format!("**/{glob}"),
+        None => glob.to_string(),
+    };
+    expand_braces(&glob).iter().any(|glob| {
+        let glob = glob.chars().collect::<Vec<_>>();
+        let path = path.chars().collect::<Vec<_>>();
+        // `**/` also matches no directory
+        match_chars(&glob, &path)
+            || glob.starts_with(&['*', '*', '/']) && match_chars(&glob[3..], &path)
+    })
+}
+
+/// Expand `{a,b}` in the glob into multiple globs
+fn expand_braces(glob: &str) -> Vec<String> {
+    let Some(start) = glob.find('{') else {
+        return vec![glob.to_string()];
+    };
+    let Some(len) = glob[start..].find('}') else {
+        return vec![glob.to_string()];
+    };
+    let end = start + len;
+    let prefix = &glob[..start];
+    let suffix = &glob[end + 1..];
+    glob[start + 1..end]
+        .split(',')
+        .flat_map(|option| expand_braces(&format!("{prefix}{option}{suffix}")))
+        .collect()
+}
+
+fn match_chars(glob: &[char], path: &[char]) -> bool {
+    match glob.first() {
+        None => path.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            (0..=path.len()).any(|i| match_chars(&glob[2..], &path[i..]))
+        }
+        Some('*') => (0..=path.len())
+            .take_while(|i| *i == 0 || path[i - 1] != '/')
+            .any(|i| match_chars(&glob[1..], &path[i..])),
+        Some('?') => path.first().is_some_and(|c| *c != '/') && match_chars(&glob[1..], &path[1..]),
+        Some('[') => {
+            let Some(len) = glob.iter().position(|c| *c == ']') else {
+                return path.first() == Some(&'[') && match_chars(&glob[1..], &path[1..]);
+            };
+            let Some(c) = path.first() else {
+                return false;
+            };
+            let (negate, set) = match glob[1..len].split_first() {
+                Some(('!', set)) => (true, set),
+                _ => (false, &glob[1..len]),
+            };
+            set.contains(c) != negate && match_chars(&glob[len + 1..], &path[1..])
+        }
+        Some(c) => path.first() == Some(c) && match_chars(&glob[1..], &path[1..]),
+    }
+}
@@ -0,0 +1,175 @@
+use crate::ir;
+
+/// A value to transfer to the other side instead of cloning, annotated with
+/// `@transfer` in the function's documentation comments.
+///
+/// For example, `@transfer image` transfers the `image` argument,
+/// `@transfer frame.data` transfers the `data` property of the `frame` argument,
+/// and `@transfer return` transfers the return value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transfer {
+    /// Index of the argument, or `None` for the return value
+    pub arg: Option<usize>,
+    /// Property path inside the value
+    pub path: Vec<String>,
+}
+
+impl Transfer {
+    /// Parse the `@transfer` tags in the comment block of a function
+    pub fn parse_all(
+        comment: &ir::CommentBlock,
+        args: &[ir::Arg],
+        retty_ann: &str,
+    ) -> Result<Vec<Self>, String> {
+        let mut out = vec![];
+        for tag in comment.tags("transfer") {
+            if tag.is_empty() {
+                return Err(
+                    "@transfer requires an argument name, a property path, or `return`".to_string(),
+                );
+            }
+            // allow multiple values in one tag, separated by comma or spaces
+            for value in tag.split(|c: char| c == ',' || c.is_whitespace()) {
+                if value.is_empty() {
+                    continue;
+                }
+                let mut parts = value.split('.');
+                let name = parts.next().unwrap_or_default();
+                let path = parts.map(|x| x.to_string()).collect::<Vec<_>>();
+                if let Some(part) = path.iter().find(|x| !is_js_ident(x)) {
+                    return Err(format!(
+                        "@transfer {value}: `{part}` is not a valid property name"
+                    ));
+                }
+                let arg = if name == "return" {
+                    if retty_ann == "<void>" {
+                        return Err(
+                            "@transfer return: the function does not return a value".to_string()
+                        );
+                    }
+                    None
+                } else {
+                    match args.iter().position(|x| x.ident == name) {
+                        Some(i) if args[i].signal => {
+                            return Err(format!(
+                                "@transfer {value}: `{name}` is an AbortSignal, which is not sent to the other side"
+                            ));
+                        }
+                        Some(i) if args[i].callback => {
+                            return Err(format!(
+                                "@transfer {value}: `{name}` is a callback, which cannot be transferred"
+                            ));
+                        }
+                        Some(i) => Some(i),
+                        None => {
+                            return Err(format!(
+                                "@transfer {value}: `{name}` is not an argument of the function. Use an argument name or `return`"
+                            ));
+                        }
+                    }
+                };
+                let transfer = Self { arg, path };
+                if !out.contains(&transfer) {
+                    out.push(transfer);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Get the JS expression for the transferred value, where the argument
+    /// or the return value is `root`.
+    ///
+    /// Optional chaining is used since the value might be missing. Missing values
+    /// are removed from the transfer list by the SDK
+    pub fn to_expr(&self, root: &str) -> String {
+        let mut out = root.to_string();
+        for part in &self.path {
+            out.push_str("?.");
+            out.push_str(part);
+        }
+        out
+    }
+}
+
+fn is_js_ident(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(lines: &[&str]) -> ir::CommentBlock {
+        ir::CommentBlock {
+            lines: lines.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn arg(ident: &str) -> ir::Arg {
+        ir::Arg {
+            ident: ident.to_string(),
+            optional: false,
+            typ: "unknown".to_string(),
+            callback: false,
+            signal: false,
+            rust_typ: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let args = [arg("image"), arg("frame")];
+        let comment = comment(&[
+            "Draw the frame",
+            "@transfer image, frame.data",
+            "@transfer return image",
+        ]);
+        let transfers = Transfer::parse_all(&comment, &args, "<ImageData>").unwrap();
+        assert_eq!(
+            transfers,
+            [
+                Transfer {
+                    arg: Some(0),
+                    path: vec![]
+                },
+                Transfer {
+                    arg: Some(1),
+                    path: vec!["data".to_string()]
+                },
+                Transfer {
+                    arg: None,
+                    path: vec![]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_all_none() {
+        let transfers = Transfer::parse_all(&comment(&["No transfer"]), &[], "<void>").unwrap();
+        assert!(transfers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        let mut signal = arg("signal");
+        signal.signal = true;
+        let mut callback = arg("onProgress");
+        callback.callback = true;
+        let args = [arg("image"), callback, signal];
+        let parse = |line: &str, retty_ann: &str| {
+            Transfer::parse_all(&comment(&[line]), &args, retty_ann).unwrap_err()
+        };
+        assert!(parse("@transfer", "<void>").contains("requires an argument name"));
+        assert!(parse("@transfer image.0", "<void>").contains("not a valid property name"));
+        assert!(parse("@transfer return", "<void>").contains("does not return a value"));
+        assert!(parse("@transfer signal", "<void>").contains("is an AbortSignal"));
+        assert!(parse("@transfer onProgress", "<void>").contains("is a callback"));
+        assert!(parse("@transfer frame", "<void>").contains("is not an argument"));
+    }
+}
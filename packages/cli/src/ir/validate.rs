@@ -0,0 +1,423 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{diag, ir};
+
+/// Reserved words in JavaScript, including the ones only reserved in strict mode,
+/// since the generated classes and modules are always in strict mode
+static RESERVED_WORDS: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Types from workex that are imported by the generated code
+static WORKEX_TYPES: &[&str] = &[
+    "WxBusRecvContext",
+    "WxBusRecvHandler",
+    "WxCloseController",
+    "WxOneWay",
+    "WxPromise",
+    "WxProtocolBindConfig",
+    "WxProtocolBoundSender",
+    "WxResult",
+    "WxSendOptions",
+    "WxStream",
+];
+
+/// Values from workex that are imported by the generated code
+static WORKEX_VALUES: &[&str] = &[
+    "WxMock",
+    "wxLoopback",
+    "wxTraceRecver",
+    "wxTraceSender",
+    "wxWarnDeprecatedCall",
+];
+
+/// Global types used in the generated code, which cannot be shadowed by the interfaces
+static GLOBAL_TYPES: &[&str] = &["Parameters", "Promise", "Record"];
+
+/// Members of the generated sender class, other than the methods of the interface
+static SENDER_MEMBERS: &[(&str, &str)] = &[
+    ("constructor", "the constructor"),
+    ("sender", "the field that stores the sender"),
+];
+
+/// Members of `WxMock`, which the generated mock classes extend
+static MOCK_MEMBERS: &[&str] = &[
+    "wxCall",
+    "wxCallVoid",
+    "wxCalls",
+    "wxCallsTo",
+    "wxOneWay",
+    "wxRecord",
+    "wxReset",
+    "wxResults",
+    "wxResultsOnce",
+    "wxSetResult",
+    "wxSetResultOnce",
+    "wxStream",
+];
+
+/// Check if the string is a valid JavaScript identifier (not including reserved words)
+pub fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Check if the protocol can be used as the prefix when one is not specified,
+/// which is when it only contains lowercase alphabetic characters
+pub fn is_default_prefix(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// Check if the identifier is a reserved word in JavaScript
+pub fn is_reserved_word(s: &str) -> bool {
+    RESERVED_WORDS.contains(&s)
+}
+
+impl ir::Package {
+    /// Check that the names in the interfaces do not clash with the names
+    /// in the generated code, and can be used where they are generated.
+    ///
+    /// All clashes are reported before returning the error. Note that types and values
+    /// are in different namespaces in TypeScript, so the argument and return types can use
+    /// the same names as the parameters and locals in the generated code (`handler`, `args`, etc).
+    /// The types are only at risk when they are imported with the names of the generated declarations
+    pub fn validate_names(&self) -> cu::Result<()> {
+        let errors = self.name_clashes();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let count = errors.len();
+        for error in errors {
+            diag::report(error);
+        }
+        cu::bail!("found {count} names that clash with the generated code");
+    }
+
+    /// Get the diagnostics for the names that clash with the names in the generated code
+    pub fn name_clashes(&self) -> Vec<diag::Diagnostic> {
+        let mut errors: Vec<diag::Diagnostic> = vec![];
+        let clash = |interface: &ir::Interface, span: Option<diag::Span>, message: String| {
+            diag::Diagnostic::new(diag::Code::NameClash, message)
+                .with_file(&interface.path)
+                .with_span(span)
+        };
+        let mut checked_files = BTreeSet::new();
+        // the bind functions are exported together from the index or the single file
+        let mut bind_names = BTreeMap::new();
+        for interface in self.interfaces.values() {
+            for bind_name in self.bind_names(&interface.name) {
+                bind_names.entry(bind_name).or_insert(interface);
+            }
+        }
+
+        // the bind functions checked so far, to find the ones with the same name
+        let mut seen_bind_names = BTreeMap::new();
+
+        for interface in self.interfaces.values() {
+            let name = &interface.name;
+            let file = &interface.filename;
+            let span = interface.span;
+            for bind_name in self.bind_names(name) {
+                if is_reserved_word(&bind_name) {
+                    let msg = format!(
+                        "{name}: the bind function `{bind_name}` is a reserved word, use a different --prefix"
+                    );
+                    errors.push(clash(interface, span, msg));
+                }
+                if let Some(other) = seen_bind_names.insert(bind_name.clone(), name) {
+                    let msg = format!(
+                        "{name}: the bind function `{bind_name}` has the same name as a bind function of {other}"
+                    );
+                    errors.push(clash(interface, span, msg));
+                }
+            }
+
+            if name.starts_with("_wx") {
+                let msg = format!(
+                    "{name}: interface names starting with `_wx` are reserved for the generated code"
+                );
+                errors.push(clash(interface, span, msg));
+            } else if WORKEX_TYPES.contains(&name.as_str())
+                || WORKEX_VALUES.contains(&name.as_str())
+            {
+                let msg = format!(
+                    "{name}: the interface has the same name as `{name}` from workex, which is imported by the generated code"
+                );
+                errors.push(clash(interface, span, msg));
+            } else if GLOBAL_TYPES.contains(&name.as_str()) {
+                let msg = format!(
+                    "{name}: the interface would shadow the global type `{name}` used by the generated code"
+                );
+                errors.push(clash(interface, span, msg));
+            }
+            if let Some(other) = bind_names.get(name) {
+                let msg = format!(
+                    "{name}: the interface has the same name as the bind function of {}",
+                    other.name
+                );
+                errors.push(clash(interface, span, msg));
+            }
+            for other in self.interfaces.values() {
+                if self.mock && *name == format!("{}Mock", other.name) {
+                    let msg = format!(
+                        "{name}: the interface has the same name as the mock class of {}",
+                        other.name
+                    );
+                    errors.push(clash(interface, span, msg));
+                }
+            }
+
+            // the imports are copied to the generated files of the interface,
+            // and are the same for the interfaces in the same file
+            if checked_files.insert(file.as_str()) {
+                for (active_ident, ident, from) in interface.imports.bindings() {
+                    if active_ident.starts_with("_wx") {
+                        let msg = format!(
+                            "`{active_ident}` is imported, but names starting with `_wx` are reserved for the generated code"
+                        );
+                        errors.push(clash(interface, None, msg));
+                        continue;
+                    }
+                    let is_from_workex = from == ir::WORKEX_IMPORT && ident == active_ident;
+                    if !is_from_workex
+                        && (WORKEX_TYPES.contains(&active_ident)
+                            || WORKEX_VALUES.contains(&active_ident))
+                    {
+                        let msg = format!(
+                            "`{active_ident}` is imported, but it's not `{active_ident}` from workex, which is imported by the generated code"
+                        );
+                        errors.push(clash(interface, None, msg));
+                    }
+                }
+            }
+
+            for f in &interface.functions {
+                let fn_name = &f.name;
+                if let Some((_, member)) = SENDER_MEMBERS.iter().find(|(x, _)| x == fn_name) {
+                    let msg = format!(
+                        "{name}.{fn_name}: the method name conflicts with {member} of the generated sender class"
+                    );
+                    errors.push(clash(interface, f.span, msg));
+                }
+                if self.mock && MOCK_MEMBERS.contains(&fn_name.as_str()) {
+                    let msg = format!(
+                        "{name}.{fn_name}: the method name conflicts with `WxMock.{fn_name}`, which the generated mock class extends"
+                    );
+                    errors.push(clash(interface, f.span, msg));
+                }
+                for arg in &f.args {
+                    if is_reserved_word(&arg.ident) {
+                        let msg = format!(
+                            "{name}.{fn_name}: the argument `{}` is a reserved word in strict mode, which the generated code is in",
+                            arg.ident
+                        );
+                        errors.push(clash(interface, f.span, msg));
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load the source as the only input, with the options, and return the messages
+    /// of the name clashes
+    fn clashes(source: &str, options: crate::Options) -> Vec<String> {
+        let path = "src/test_validate.ts".to_string();
+        let sources = BTreeMap::from([(path.clone(), source.to_string())]);
+        let reader = crate::MemoryReader {
+            sources: &sources,
+            fallback: &crate::FsReader,
+        };
+        let options = crate::Options {
+            inputs: vec![path],
+            protocol: Some("t".to_string()),
+            no_editorconfig: true,
+            ..options
+        };
+        let (interfaces, decl_files) =
+            crate::parse::load_interfaces_from_inputs(&options.inputs, &reader).unwrap();
+        let package = ir::Package::try_new(&options, &reader, interfaces, decl_files).unwrap();
+        package
+            .name_clashes()
+            .into_iter()
+            .map(|x| {
+                assert_eq!(x.code, diag::Code::NameClash);
+                x.message
+            })
+            .collect()
+    }
+
+    fn link(link: &[&str]) -> crate::Options {
+        crate::Options {
+            link: link.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_names_ok() {
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface A { run(input: string): WxPromise<void>; }
+export interface B { run(args: string, handler: number): WxPromise<void>; }
+"#;
+        assert_eq!(clashes(source, link(&["A,B"])), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_validate_names_clash() {
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface A {
+    sender(): WxPromise<void>;
+    run(package: string): WxPromise<void>;
+}
+export interface tA { run(): WxPromise<void>; }
+"#;
+        assert_eq!(
+            clashes(source, link(&[])),
+            [
+                "A.run: the argument `package` is a reserved word in strict mode, which the generated code is in",
+                "A.sender: the method name conflicts with the field that stores the sender of the generated sender class",
+                "tA: the interface has the same name as the bind function of A",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_names_workex_imports() {
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+import type { WxStream } from "./stream";
+export interface WxSendOptions { run(): WxPromise<void>; }
+export interface Record { run(): WxPromise<void>; }
+export interface A { run(s: WxStream): WxPromise<void>; }
+"#;
+        assert_eq!(
+            clashes(source, link(&[])),
+            [
+                "`WxStream` is imported, but it's not `WxStream` from workex, which is imported by the generated code",
+                "Record: the interface would shadow the global type `Record` used by the generated code",
+                "WxSendOptions: the interface has the same name as `WxSendOptions` from workex, which is imported by the generated code",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_names_mock() {
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface A { wxReset(): WxPromise<void>; }
+export interface AMock { run(): WxPromise<void>; }
+"#;
+        assert_eq!(clashes(source, link(&[])), Vec::<String>::new());
+        let options = crate::Options {
+            mock: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            clashes(source, options),
+            [
+                "A.wxReset: the method name conflicts with `WxMock.wxReset`, which the generated mock class extends",
+                "AMock: the interface has the same name as the mock class of A",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_names_bind_function() {
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface A { run(): WxPromise<void>; }
+export interface B { run(): WxPromise<void>; }
+export interface C { run(): WxPromise<void>; }
+export interface AWithC { run(): WxPromise<void>; }
+"#;
+        // the second link of A is named `tAWithC`, which is also the bind function of `AWithC`
+        assert_eq!(
+            clashes(source, link(&["A,B", "A,C"])),
+            ["AWithC: the bind function `tAWithC` has the same name as a bind function of A"]
+        );
+        assert_eq!(
+            clashes(source, link(&["A,B", "A,C@other"])),
+            Vec::<String>::new()
+        );
+        let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface eof { run(): WxPromise<void>; }
+"#;
+        let options = crate::Options {
+            prefix: Some("typ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            clashes(source, options),
+            ["eof: the bind function `typeof` is a reserved word, use a different --prefix"]
+        );
+    }
+
+    #[test]
+    fn test_default_prefix() {
+        assert!(is_default_prefix("testapp"));
+        assert!(!is_default_prefix("testApp"));
+        assert!(!is_default_prefix("myproto-1"));
+        assert!(!is_default_prefix(""));
+    }
+}
@@ -0,0 +1,95 @@
+//! Library for generating the bindings with workex, which is used by the `workex` CLI.
+//!
+//! The inputs are loaded into an [`ir::Package`] with [`load`], which also validates them
+//! with the [`Options`]. Then, [`emit::render`] renders the generated files into memory,
+//! and [`emit::Output::write`] writes them. [`emit::emit`] does both.
+//!
+//! Errors in the input files are reported with [`diag`] before an error is returned.
+//! [`check`] returns them instead, without stopping at the first file with errors,
+//! which is used by the language server.
+//!
+//! All files needed for the generation are read through an [`InputReader`], so the
+//! library also works without direct access to the file system, such as in WASI.
+use std::collections::BTreeMap;
+
+use cu::pre::*;
+
+pub mod diag;
+pub mod emit;
+pub mod ir;
+mod parse;
+
+mod input;
+pub use input::*;
+mod options;
+pub use options::*;
+
+/// Parse the inputs and load them into a package with the options.
+///
+/// The files are read with the reader, which is [`FsReader`] for reading from the disk.
+/// Use [`MemoryReader`] for inputs that are in memory. The paths in `options.inputs`
+/// are still used to find the output directory.
+///
+/// With `--message-format json`, the error is also reported with [`diag::fatal`].
+pub fn load(options: &Options, reader: &dyn InputReader) -> cu::Result<ir::Package> {
+    let (interfaces, decl_files) = parse::load_interfaces_from_inputs(&options.inputs, reader)
+        .context("failed to parse input files")
+        .map_err(|e| diag::fatal(diag::Code::InvalidInput, e))?;
+    let package = ir::Package::try_new(options, reader, interfaces, decl_files)
+        .map_err(|e| diag::fatal(diag::Code::InvalidOption, e))?;
+    package
+        .validate_names()
+        .map_err(|e| diag::fatal(diag::Code::InvalidOption, e))?;
+    Ok(package)
+}
+
+/// Result of [`check`]
+#[derive(Debug)]
+pub struct Check {
+    /// The interfaces in the inputs, including the ones in files with errors
+    pub interfaces: BTreeMap<String, ir::Interface>,
+    /// The package, if the inputs are parsed without errors and are valid with the options
+    pub package: Option<ir::Package>,
+    /// All errors and warnings found in the inputs
+    pub diagnostics: Vec<diag::Diagnostic>,
+}
+
+/// Check the inputs with the options, like [`load`], but without stopping at the errors
+/// in the input files. The diagnostics are returned instead of reported.
+///
+/// The errors in the options, such as links to interfaces that don't exist,
+/// are returned as diagnostics without a file
+pub fn check(options: &Options, reader: &dyn InputReader) -> Check {
+    let (interfaces, decl_files, mut diagnostics) = parse::check_inputs(&options.inputs, reader);
+    let has_error = diagnostics
+        .iter()
+        .any(|x| x.code.severity() == diag::Severity::Error);
+    if has_error {
+        return Check {
+            interfaces,
+            package: None,
+            diagnostics,
+        };
+    }
+    match ir::Package::try_new(options, reader, interfaces.clone(), decl_files) {
+        Ok(package) => {
+            diagnostics.extend(package.name_clashes());
+            Check {
+                interfaces,
+                package: Some(package),
+                diagnostics,
+            }
+        }
+        Err(e) => {
+            diagnostics.push(diag::Diagnostic::new(
+                diag::Code::InvalidOption,
+                format!("{e:#}"),
+            ));
+            Check {
+                interfaces,
+                package: None,
+                diagnostics,
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write as _};
+use std::path::Path;
+
+use cu::pre::*;
+use serde_json::{Value, json};
+
+use workex::{FsReader, InputReader as _, MemoryReader, diag, ir};
+
+/// Options for the language server, which are the same as the options for generating the code.
+///
+/// The files opened in the editor are used instead of the files on the disk.
+/// The function IDs depend on all interfaces, so all inputs should be specified
+/// for the IDs to be the same as the generated code. If there are no inputs,
+/// all TypeScript and Rust files opened in the editor are checked.
+#[derive(Debug, clap::Args)]
+pub struct LspOptions {
+    #[clap(flatten)]
+    pub options: workex::Options,
+}
+
+/// Error code for requests that are not supported
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Run the language server until the editor sends the `exit` notification
+pub fn run(options: &LspOptions) -> cu::Result<()> {
+    let mut options = options.options.clone();
+    for input in &mut options.inputs {
+        *input = FsReader.normalize(Path::new(input))?.into_utf8()?;
+    }
+    let mut server = Server::new(options);
+
+    let mut stdin = std::io::stdin().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // responses from the editor are not used
+            continue;
+        };
+        if method == "exit" {
+            break;
+        }
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = server.handle(method, &params);
+        for notification in server.outbox.drain(..) {
+            write_message(&notification)?;
+        }
+        // notifications don't have an ID and don't need a response
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+        let response = match result {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": METHOD_NOT_FOUND, "message": format!("unsupported method: {method}") },
+            }),
+        };
+        write_message(&response)?;
+    }
+    Ok(())
+}
+
+/// State of the language server
+struct Server {
+    /// Options from the CLI, with the inputs as absolute paths
+    options: workex::Options,
+    /// Content of the documents opened in the editor, by path
+    documents: BTreeMap<String, String>,
+    /// URIs of the documents from the editor, by path
+    uris: BTreeMap<String, String>,
+    /// Interfaces from the last check. The interfaces in a file that cannot be
+    /// parsed anymore are kept from the check before, so hover still works while editing
+    interfaces: BTreeMap<String, ir::Interface>,
+    /// Links of each interface from the last check with valid options,
+    /// formatted for the hover messages
+    linkage: BTreeMap<String, Vec<String>>,
+    /// Diagnostics from the last check, by path
+    diagnostics: BTreeMap<String, Vec<diag::Diagnostic>>,
+    /// Notifications to send to the editor
+    outbox: Vec<Value>,
+}
+
+impl Server {
+    fn new(options: workex::Options) -> Self {
+        Self {
+            options,
+            documents: BTreeMap::new(),
+            uris: BTreeMap::new(),
+            interfaces: BTreeMap::new(),
+            linkage: BTreeMap::new(),
+            diagnostics: BTreeMap::new(),
+            outbox: vec![],
+        }
+    }
+
+    /// Handle a request or notification, and return the result for requests.
+    /// `None` is returned if the method is not supported
+    fn handle(&mut self, method: &str, params: &Value) -> Option<Value> {
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    // full content of the document is sent on change
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "codeActionProvider": { "codeActionKinds": ["quickfix"] },
+                },
+                "serverInfo": { "name": "workex", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                if let Some(path) = self.track_uri(&document["uri"]) {
+                    let text = document["text"].as_str().unwrap_or_default();
+                    self.documents.insert(path, text.to_string());
+                    self.check();
+                }
+                Value::Null
+            }
+            "textDocument/didChange" => {
+                // with full sync, the last change has the full content
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(path) = self.track_uri(&params["textDocument"]["uri"])
+                    && let Some(text) = text
+                {
+                    self.documents.insert(path, text.to_string());
+                    self.check();
+                }
+                Value::Null
+            }
+            "textDocument/didClose" => {
+                if let Some(path) = uri_to_path(&params["textDocument"]["uri"]) {
+                    self.documents.remove(&path);
+                    self.check();
+                }
+                Value::Null
+            }
+            "textDocument/hover" => self.hover(params).unwrap_or(Value::Null),
+            "textDocument/codeAction" => self.code_actions(params),
+            // other notifications are ignored, since they don't need a response
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// Get the path of the document, and remember the URI used by the editor
+    fn track_uri(&mut self, uri: &Value) -> Option<String> {
+        let path = uri_to_path(uri)?;
+        self.uris.insert(path.clone(), uri.as_str()?.to_string());
+        Some(path)
+    }
+
+    /// Check the inputs, and publish the diagnostics to the editor
+    fn check(&mut self) {
+        let mut options = self.options.clone();
+        if options.inputs.is_empty() {
+            options.inputs = self
+                .documents
+                .keys()
+                .filter(|x| x.ends_with(".ts") || x.ends_with(".rs"))
+                .cloned()
+                .collect();
+        }
+        let reader = MemoryReader {
+            sources: &self.documents,
+            fallback: &FsReader,
+        };
+        let check = workex::check(&options, &reader);
+
+        // clear the diagnostics published last time
+        for list in self.diagnostics.values_mut() {
+            list.clear();
+        }
+        for input in &options.inputs {
+            self.diagnostics.entry(input.clone()).or_default();
+        }
+        for diagnostic in check.diagnostics {
+            match diagnostic.file.clone() {
+                Some(file) => self.diagnostics.entry(file).or_default().push(diagnostic),
+                // errors in the options are shown at the start of each input
+                None => {
+                    for input in &options.inputs {
+                        let diagnostic = diagnostic.clone().with_file(input);
+                        self.diagnostics
+                            .entry(input.clone())
+                            .or_default()
+                            .push(diagnostic);
+                    }
+                }
+            }
+        }
+
+        let mut interfaces = check.interfaces;
+        for (name, interface) in std::mem::take(&mut self.interfaces) {
+            let has_error = self.diagnostics.get(&interface.path).is_some_and(|list| {
+                list.iter()
+                    .any(|x| x.code.severity() == diag::Severity::Error)
+            });
+            if has_error && !interfaces.values().any(|x| x.path == interface.path) {
+                interfaces.entry(name).or_insert(interface);
+            }
+        }
+        self.interfaces = interfaces;
+
+        if let Some(package) = &check.package {
+            self.linkage.clear();
+            for name in package.interfaces.keys() {
+                let links = match package.links(name) {
+                    [] => vec![format!(
+                        "not linked with another interface, bound with `{}{name}`",
+                        package.prefix
+                    )],
+                    links => links
+                        .iter()
+                        .map(|x| {
+                            format!(
+                                "linked with `{}` under `{}`, bound with `{}`",
+                                x.interface, x.protocol, x.bind_name
+                            )
+                        })
+                        .collect(),
+                };
+                self.linkage.insert(name.clone(), links);
+            }
+        }
+
+        for (path, list) in &self.diagnostics {
+            let uri = match self.uris.get(path) {
+                Some(uri) => uri.clone(),
+                None => path_to_uri(path),
+            };
+            let text = self.text(path);
+            let lines = Lines::new(&text);
+            let list = list
+                .iter()
+                .map(|x| to_lsp_diagnostic(x, &lines))
+                .collect::<Vec<_>>();
+            self.outbox.push(json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": list },
+            }));
+        }
+        self.diagnostics.retain(|_, list| !list.is_empty());
+    }
+
+    /// Get the content of the document, or the file on the disk if it's not opened
+    fn text(&self, path: &str) -> String {
+        match self.documents.get(path) {
+            Some(text) => text.clone(),
+            None => FsReader.read_string(Path::new(path)).unwrap_or_default(),
+        }
+    }
+
+    /// Show the function IDs and the links when hovering over
+    /// the name of an interface or a function
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let path = uri_to_path(&params["textDocument"]["uri"])?;
+        let text = self.text(&path);
+        let lines = Lines::new(&text);
+        let (line, column) = lines.position(&params["position"])?;
+        let contains = |span: Option<diag::Span>| {
+            span.is_some_and(|span| {
+                (span.line_start, span.column_start) <= (line, column)
+                    && (line, column) < (span.line_end, span.column_end)
+            })
+        };
+
+        let func_map = ir::make_func_id_map(&self.interfaces);
+        let func_id = |interface: &ir::Interface, function: &ir::Function| {
+            func_map
+                .get(&format!("{}_{}", interface.name, function.name))
+                .copied()
+        };
+        for interface in self.interfaces.values().filter(|x| x.path == path) {
+            let links = match self.linkage.get(&interface.name) {
+                Some(links) => links
+                    .iter()
+                    .map(|x| format!("- {x}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                None => "- links are not available until the errors are fixed".to_string(),
+            };
+            if contains(interface.span) {
+                let ids = interface
+                    .functions
+                    .iter()
+                    .filter_map(|f| func_id(interface, f));
+                let ids = match (ids.clone().min(), ids.max()) {
+                    (Some(first), Some(last)) if first == last => format!("function ID `{first}`"),
+                    (Some(first), Some(last)) => format!("function IDs `{first}` to `{last}`"),
+                    _ => "no functions".to_string(),
+                };
+                return Some(hover_markdown(
+                    format!("`{}`: {ids}\n\n{links}", interface.name),
+                    interface.span,
+                    &lines,
+                ));
+            }
+            for function in &interface.functions {
+                if !contains(function.span) {
+                    continue;
+                }
+                let id = func_id(interface, function)?;
+                return Some(hover_markdown(
+                    format!(
+                        "`{}.{}`: function ID `{id}`\n\n`{}` is:\n{links}",
+                        interface.name, function.name, interface.name
+                    ),
+                    function.span,
+                    &lines,
+                ));
+            }
+        }
+        None
+    }
+
+    /// Get the quick fixes for the diagnostics in the range
+    fn code_actions(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+        let Some(path) = uri_to_path(uri) else {
+            return json!([]);
+        };
+        let text = self.text(&path);
+        let lines = Lines::new(&text);
+        let start = lines.position(&params["range"]["start"]);
+        let end = lines.position(&params["range"]["end"]);
+        let (Some(start), Some(end)) = (start, end) else {
+            return json!([]);
+        };
+
+        let mut actions = vec![];
+        for diagnostic in self.diagnostics.get(&path).into_iter().flatten() {
+            let (Some(span), Some(fix)) = (diagnostic.span, &diagnostic.fix) else {
+                continue;
+            };
+            let overlaps = (span.line_start, span.column_start) <= end
+                && start <= (span.line_end, span.column_end);
+            if !overlaps {
+                continue;
+            }
+            actions.push(json!({
+                "title": fix.title,
+                "kind": "quickfix",
+                "diagnostics": [to_lsp_diagnostic(diagnostic, &lines)],
+                "isPreferred": true,
+                "edit": {
+                    "changes": {
+                        uri.as_str().unwrap_or_default(): [{
+                            "range": lines.range(Some(fix.span)),
+                            "newText": fix.replacement,
+                        }],
+                    },
+                },
+            }));
+        }
+        Value::Array(actions)
+    }
+}
+
+fn hover_markdown(value: String, span: Option<diag::Span>, lines: &Lines) -> Value {
+    json!({
+        "contents": { "kind": "markdown", "value": value },
+        "range": lines.range(span),
+    })
+}
+
+fn to_lsp_diagnostic(diagnostic: &diag::Diagnostic, lines: &Lines) -> Value {
+    let severity = match diagnostic.code.severity() {
+        diag::Severity::Error => 1,
+        diag::Severity::Warning => 2,
+    };
+    json!({
+        "range": lines.range(diagnostic.span),
+        "severity": severity,
+        "code": diagnostic.code.as_str(),
+        "source": "workex",
+        "message": diagnostic.message,
+    })
+}
+
+/// Lines of a document, to convert between the columns in the diagnostics,
+/// which are 1-based and count characters, and the positions in the editor,
+/// which are 0-based and count UTF-16 code units
+struct Lines<'a>(Vec<&'a str>);
+
+impl<'a> Lines<'a> {
+    fn new(text: &'a str) -> Self {
+        Self(text.lines().collect())
+    }
+
+    /// Convert a 1-based line and column to a position in the editor
+    fn to_lsp(&self, line: usize, column: usize) -> Value {
+        let text = self.0.get(line - 1).copied().unwrap_or_default();
+        let character: usize = text.chars().take(column - 1).map(char::len_utf16).sum();
+        json!({ "line": line - 1, "character": character })
+    }
+
+    /// Convert a position from the editor to a 1-based line and column
+    fn position(&self, position: &Value) -> Option<(usize, usize)> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let text = self.0.get(line).copied().unwrap_or_default();
+        let mut units = 0;
+        let mut column = 1;
+        for c in text.chars() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            column += 1;
+        }
+        Some((line + 1, column))
+    }
+
+    /// Convert the span to a range in the editor. Diagnostics without a span
+    /// are shown at the start of the file
+    fn range(&self, span: Option<diag::Span>) -> Value {
+        let span = span.unwrap_or(diag::Span {
+            line_start: 1,
+            column_start: 1,
+            line_end: 1,
+            column_end: 1,
+        });
+        json!({
+            "start": self.to_lsp(span.line_start, span.column_start),
+            "end": self.to_lsp(span.line_end, span.column_end),
+        })
+    }
+}
+
+/// Convert a `file://` URI from the editor to an absolute path
+fn uri_to_path(uri: &Value) -> Option<String> {
+    let path = uri.as_str()?.strip_prefix("file://")?;
+    let mut bytes = vec![];
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+        let hex = [iter.next()?, iter.next()?];
+        bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    let mut path = String::from_utf8(bytes).ok()?;
+    // `/C:/...` on Windows
+    if path.as_bytes().get(2) == Some(&b':') {
+        path.remove(0);
+    }
+    match FsReader.normalize(Path::new(&path)) {
+        Ok(normalized) => normalized.into_utf8().ok(),
+        Err(_) => Some(path),
+    }
+}
+
+/// Convert an absolute path to a `file://` URI, for files not opened in the editor
+fn path_to_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~:".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{b:02X}"));
+        }
+    }
+    uri
+}
+
+/// Read a message from the editor. Returns `None` at the end of the input
+fn read_message(reader: &mut impl BufRead) -> cu::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = Some(cu::check!(
+                value.trim().parse::<usize>(),
+                "invalid Content-Length: {value}"
+            )?);
+        }
+    }
+    let Some(length) = length else {
+        cu::bail!("missing Content-Length in the message header");
+    };
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    let message = cu::check!(
+        serde_json::from_slice(&content),
+        "failed to parse message from the editor"
+    )?;
+    Ok(Some(message))
+}
+
+/// Send a message to the editor
+fn write_message(message: &Value) -> cu::Result<()> {
+    let content = message.to_string();
+    let mut stdout = std::io::stdout().lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start a server with the options, with a file in a temporary directory
+    /// opened in the editor
+    fn open(name: &str, link: &[&str], text: &str) -> (Server, String) {
+        let dir = std::env::temp_dir().join(format!("workex-lsp-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("api.ts");
+        // the file needs to exist, but the content in the editor is used
+        std::fs::write(&path, "").unwrap();
+        let path = FsReader.normalize(&path).unwrap().into_utf8().unwrap();
+        let uri = path_to_uri(&path);
+        let mut server = Server::new(workex::Options {
+            protocol: Some("test".to_string()),
+            link: link.iter().map(|x| x.to_string()).collect(),
+            no_editorconfig: true,
+            ..Default::default()
+        });
+        let params = json!({ "textDocument": { "uri": uri, "text": text } });
+        server.handle("textDocument/didOpen", &params);
+        (server, uri)
+    }
+
+    /// Get the diagnostics published for the URI
+    fn published(server: &mut Server, uri: &str) -> Vec<Value> {
+        let mut out = vec![];
+        for message in server.outbox.drain(..) {
+            if message["params"]["uri"] == uri {
+                out = message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+        out
+    }
+
+    fn hover(server: &mut Server, uri: &str, line: u64, character: u64) -> Option<String> {
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        });
+        let result = server.handle("textDocument/hover", &params).unwrap();
+        Some(result["contents"]["value"].as_str()?.to_string())
+    }
+
+    #[test]
+    fn test_diagnostics_and_quick_fix() {
+        let text = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    /** 😀 */ foo: (a: string) => WxPromise<void>;
+    bar(): WxPromise<void>;
+}
+"#;
+        let (mut server, uri) = open("fix", &[], text);
+        let diagnostics = published(&mut server, &uri);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E0002");
+        assert_eq!(diagnostics[0]["severity"], 1);
+        // the emoji is 2 UTF-16 code units
+        let range = diagnostics[0]["range"].clone();
+        assert_eq!(range["start"], json!({ "line": 2, "character": 14 }));
+
+        let params = json!({ "textDocument": { "uri": uri }, "range": range });
+        let actions = server.handle("textDocument/codeAction", &params).unwrap();
+        let edit = &actions[0]["edit"]["changes"][&uri][0];
+        assert_eq!(actions[0]["kind"], "quickfix");
+        assert_eq!(edit["newText"], "foo(a: string): WxPromise<void>");
+        assert_eq!(edit["range"]["start"], range["start"]);
+
+        // the fix is not offered outside of the diagnostic
+        let range = json!({
+            "start": { "line": 3, "character": 4 },
+            "end": { "line": 3, "character": 4 },
+        });
+        let params = json!({ "textDocument": { "uri": uri }, "range": range });
+        let actions = server.handle("textDocument/codeAction", &params).unwrap();
+        assert_eq!(actions, json!([]));
+    }
+
+    #[test]
+    fn test_hover() {
+        let text = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    /* ü */ hello(): WxPromise<void>;
+    world(): WxPromise<void>;
+}
+export interface Other {
+    ping(): WxPromise<void>;
+}
+"#;
+        let links = ["Api,Other", "Api,Other@other=oth"];
+        let (mut server, uri) = open("hover", &links, text);
+        assert_eq!(published(&mut server, &uri), Vec::<Value>::new());
+
+        let interface = hover(&mut server, &uri, 1, 18).unwrap();
+        assert_eq!(
+            interface,
+            "`Api`: function IDs `16` to `17`\n\n\
+            - linked with `Other` under `other`, bound with `othApi`\n\
+            - linked with `Other` under `test`, bound with `testApi`"
+        );
+        // after the non-ASCII character
+        let function = hover(&mut server, &uri, 2, 13).unwrap();
+        assert!(function.starts_with("`Api.hello`: function ID `16`\n\n`Api` is:\n"));
+        let unlinked = hover(&mut server, &uri, 6, 5);
+        assert!(
+            unlinked
+                .unwrap()
+                .starts_with("`Other.ping`: function ID `18`")
+        );
+        assert_eq!(hover(&mut server, &uri, 0, 0), None);
+
+        // the interfaces are kept when the file cannot be parsed while editing
+        let text = format!("{text}export interface {{\n");
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "contentChanges": [{ "text": text }],
+        });
+        server.handle("textDocument/didChange", &params);
+        let diagnostics = published(&mut server, &uri);
+        assert_eq!(diagnostics[0]["code"], "E0001");
+        let function = hover(&mut server, &uri, 2, 13).unwrap();
+        assert!(function.starts_with("`Api.hello`: function ID `16`"));
+    }
+
+    #[test]
+    fn test_option_errors() {
+        let text = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    constructor(): WxPromise<void>;
+}
+"#;
+        let (mut server, uri) = open("clash", &[], text);
+        let diagnostics = published(&mut server, &uri);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E0101");
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+
+        let text = text.replace("constructor", "hello");
+        let (mut server, uri) = open("link", &["Api,Missing"], &text);
+        let diagnostics = published(&mut server, &uri);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E0100");
+        assert_eq!(diagnostics[0]["message"], "Interface not found: Missing");
+        assert_eq!(
+            diagnostics[0]["range"]["start"],
+            json!({ "line": 0, "character": 0 })
+        );
+    }
+}
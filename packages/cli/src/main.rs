@@ -1,59 +1,125 @@
 use clap::Parser;
 use cu::pre::*;
 
-mod emit;
-mod ir;
-mod parse;
+use workex::{diag, emit};
+
+mod lsp;
+#[cfg(target_os = "wasi")]
+mod wasi;
 
 /// Workex CLI Tool
 #[derive(Debug, Parser)]
-#[command(author, about, version, arg_required_else_help(true))]
+#[command(
+    author,
+    about,
+    version,
+    arg_required_else_help(true),
+    args_conflicts_with_subcommands(true),
+    subcommand_negates_reqs(true)
+)]
 pub struct CliOptions {
-    /// Input TypeScript files with `export interface` declarations
-    ///
-    /// The input files must be in the same directory, which will also be
-    /// used as the output directory.
-    pub inputs: Vec<String>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 
-    /// A string that will be used as the protocol identifier.
-    ///
-    /// If the string only contains lowercase alphabetic characters, it will be
-    /// also used as the prefix for generated functions. Otherwise, a prefix
-    /// is required to be specified.
-    #[clap(short, long)]
-    pub protocol: String,
-
-    /// Prefix for generated functions. The generated function names will
-    /// be this prefix + the interface name.
+    #[clap(flatten)]
+    pub options: workex::Options,
+
+    /// Format of the errors and warnings.
     ///
-    /// Default is the same as protocol
-    #[clap(long)]
-    pub prefix: Option<String>,
+    /// With `json`, each diagnostic is printed to stdout as a JSON object in one line,
+    /// with the file, the span, the severity, a stable code and the message.
+    /// A summary object is printed at the end. Other messages are not printed.
+    #[clap(long, value_enum, default_value_t)]
+    pub message_format: diag::MessageFormat,
 
-    /// Do not generate the .gitignore file
-    #[clap(long)]
-    pub no_gitignore: bool,
+    #[cfg(not(target_os = "wasi"))]
+    #[clap(flatten)]
+    common: cu::cli::Flags,
 
-    /// Link 2 interfaces together. The 2 interfaces should be separated with a comma (,).
-    /// Multiple `-l` flags can be used to link more pairs of interfaces.
-    #[clap(short, long)]
-    pub link: Vec<String>,
+    #[cfg(target_os = "wasi")]
+    #[clap(flatten)]
+    common: wasi::Flags,
+}
 
-    /// Specify the name of the output directory.
-    #[clap(long, default_value = "interfaces")]
-    pub dir: String,
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Start a language server for the input files, which communicates with the editor
+    /// through stdin and stdout.
+    ///
+    /// The options are the same as generating the code. The errors in the input files
+    /// and the options are shown as you edit them. Hovering over an interface or a function
+    /// shows the function IDs, and the links of the interface with the protocols and bind functions.
+    Lsp(lsp::LspOptions),
+}
 
-    #[clap(flatten)]
-    common: cu::cli::Flags,
+impl CliOptions {
+    /// Adjust the options before the logging is set up
+    fn preprocess(&mut self) {
+        // the log messages are printed to stdout, which is only for the JSON objects
+        // or the messages to the editor
+        if self.message_format == diag::MessageFormat::Json || self.command.is_some() {
+            self.common.verbose = 0;
+            self.common.quiet = 2;
+        }
+    }
 }
 
-#[cu::cli(flags = "common")]
+#[cfg(not(target_os = "wasi"))]
+#[cu::cli(flags = "common", preprocess = CliOptions::preprocess)]
 fn main(cli: CliOptions) -> cu::Result<()> {
-    let interfaces =
-        parse::load_interfaces_from_inputs(&cli.inputs).context("failed to parse input files")?;
-    let package = ir::Package::try_new(&cli, interfaces)?;
+    main_internal(cli)
+}
+
+#[cfg(target_os = "wasi")]
+fn main() -> std::process::ExitCode {
+    wasi::run(CliOptions::preprocess, |cli| &cli.common, main_internal)
+}
+
+fn main_internal(cli: CliOptions) -> cu::Result<()> {
+    if let Some(Command::Lsp(options)) = &cli.command {
+        return lsp::run(options);
+    }
+    diag::set_format(cli.message_format);
+    let result = run(&cli);
+    diag::print_summary(result.is_ok());
+    result
+}
+
+fn run(cli: &CliOptions) -> cu::Result<()> {
+    let options = &cli.options;
+    let package = workex::load(options, &workex::FsReader)?;
 
-    emit::emit(&package).context("failed to emit output")?;
+    emit::emit(&package)
+        .context("failed to emit output")
+        .map_err(|e| diag::fatal(diag::Code::Other, e))?;
     cu::info!("{} interfaces generated", package.interfaces.len());
+    let deprecated = package.deprecated_functions();
+    if diag::is_json() {
+        for (interface, function, reason) in deprecated {
+            let message = if reason.is_empty() {
+                format!("{}.{} is deprecated", interface.name, function.name)
+            } else {
+                format!(
+                    "{}.{} is deprecated: {reason}",
+                    interface.name, function.name
+                )
+            };
+            diag::report(
+                diag::Diagnostic::new(diag::Code::Deprecated, message)
+                    .with_file(&interface.path)
+                    .with_span(function.span),
+            );
+        }
+    } else if !deprecated.is_empty() {
+        cu::warn!("{} deprecated functions:", deprecated.len());
+        for (interface, function, reason) in deprecated {
+            diag::count(diag::Severity::Warning);
+            if reason.is_empty() {
+                cu::warn!("  {}.{}", interface.name, function.name);
+            } else {
+                cu::warn!("  {}.{}: {reason}", interface.name, function.name);
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,231 @@
+use crate::ir;
+
+/// Options for generating the code, which are the same as the options of the CLI
+#[derive(Debug, Clone, clap::Args)]
+pub struct Options {
+    /// Input TypeScript files with `export interface` declarations,
+    /// or Rust files with traits marked with `#[workex::interface]`
+    ///
+    /// The input files must be in the same directory, which will also be
+    /// used as the output directory. For each Rust file, a TypeScript file
+    /// with the same name is generated next to it, with the interfaces and serializable types
+    /// declared in the Rust file.
+    pub inputs: Vec<String>,
+
+    /// A string that will be used as the protocol identifier.
+    ///
+    /// If the string only contains lowercase alphabetic characters, it will be
+    /// also used as the prefix for generated functions. Otherwise, a prefix
+    /// is required to be specified.
+    #[clap(short, long, required = true)]
+    pub protocol: Option<String>,
+
+    /// Prefix for generated functions. The generated function names will
+    /// be this prefix + the interface name.
+    ///
+    /// Default is the same as protocol
+    #[clap(long)]
+    pub prefix: Option<String>,
+
+    /// Do not generate the .gitignore file
+    #[clap(long)]
+    pub no_gitignore: bool,
+
+    /// Link 2 interfaces together. The 2 interfaces should be separated with a comma (,).
+    /// Multiple `-l` flags can be used to link more pairs of interfaces.
+    ///
+    /// An interface can be linked with multiple interfaces, and the same pair can be linked
+    /// under a different protocol with `A,B@PROTOCOL`, or `A,B@PROTOCOL=PREFIX` if the protocol
+    /// is not only lowercase alphabetic characters. The links under the same protocol
+    /// cannot form a cycle.
+    ///
+    /// The bind function of the first link of an interface is `{PREFIX}{INTERFACE}`. The other
+    /// links of the interface with the same prefix are `{PREFIX}{INTERFACE}With{LINKED}`.
+    #[clap(short, long)]
+    pub link: Vec<String>,
+
+    /// Specify the name of the output directory.
+    #[clap(long, default_value = "interfaces")]
+    pub dir: String,
+
+    /// Generate one module at this path, with the sender classes, receivers and bind
+    /// functions of all interfaces, instead of the files in the output directory.
+    ///
+    /// Only the bind functions are exported from the module.
+    #[clap(long, conflicts_with_all = ["dir", "mock", "loopback", "index"])]
+    pub single_file: Option<String>,
+
+    /// Also generate Rust bindings for the protocol into this file.
+    ///
+    /// The generated module contains a trait and a client struct for each interface,
+    /// and a dispatcher function for handling incoming calls. It depends on
+    /// the `serde` and `serde_json` crates.
+    #[clap(long)]
+    pub rust: Option<String>,
+
+    /// Map a TypeScript type to a Rust type in the generated Rust bindings,
+    /// in the format of `TS=RUST` (for example, `-r "FileInfo=crate::FileInfo"`).
+    /// Multiple `-r` flags can be used to add more mappings.
+    ///
+    /// Mappings for primitives, arrays, tuples, records and optionals are built in.
+    #[clap(short = 'r', long, requires = "rust")]
+    pub rust_type: Vec<String>,
+
+    /// Use `WxError`, `WxResult` and `WxSender` from this crate in the generated
+    /// Rust bindings instead of generating them, for example `workex_runtime`.
+    ///
+    /// This is required if the interfaces are authored in Rust.
+    #[clap(long, requires = "rust")]
+    pub rust_runtime: Option<String>,
+
+    /// Language of the generated files.
+    ///
+    /// With `js`, ESM `.js` files are generated, with `.d.ts` files for the
+    /// types. For Rust inputs, the declarations are also generated as `.d.ts` files.
+    #[clap(long, value_enum, default_value_t)]
+    pub target: ir::Target,
+
+    /// Also generate a mock implementation for each interface, in `INTERFACE.mock.ts`.
+    ///
+    /// The mock records the calls and returns configurable results, for unit
+    /// tests of the code that depends on the interface.
+    #[clap(long)]
+    pub mock: bool,
+
+    /// Also generate `loopback.ts`, with a function for each pair of linked interfaces
+    /// (and each unlinked interface) to connect both sides in the same thread.
+    ///
+    /// The calls go through the generated code with an in-memory channel,
+    /// for testing without a Worker or window.
+    #[clap(long)]
+    pub loopback: bool,
+
+    /// Also generate `index.ts`, which exports the bind functions of all interfaces,
+    /// the protocol identifier as `PROTOCOL`, the identifiers of all protocols used by the links
+    /// as `PROTOCOLS`, and the function IDs as `FUNCTION_IDS`.
+    ///
+    /// The internal implementations used by the bind functions are not exported.
+    #[clap(long)]
+    pub index: bool,
+
+    /// Wrap the generated senders and receivers with tracing hooks.
+    ///
+    /// Each call is reported to the hook set with `wxSetTraceHook` in the SDK,
+    /// with the function, the arguments, the duration and the result.
+    #[clap(long)]
+    pub trace: bool,
+
+    /// How the generated receivers dispatch the calls to the handler.
+    ///
+    /// `switch` uses a switch statement on the function ID, which is the fastest
+    /// for most interfaces. `table` uses an array of closures indexed by the function ID.
+    /// `auto` uses the table for interfaces with at least `--dispatch-threshold` functions.
+    #[clap(long, value_enum, default_value_t)]
+    pub dispatch: ir::Dispatch,
+
+    /// Minimum number of functions in an interface to use the table with `--dispatch auto`
+    #[clap(long, default_value_t = 64)]
+    pub dispatch_threshold: usize,
+
+    /// Indent the generated code with spaces or tabs.
+    ///
+    /// This and the other style options default to the `.editorconfig` in the output directory
+    /// or its parents, then to the same style as older versions: 4 spaces, double quotes,
+    /// no line width limit and no new line at the end of the files.
+    #[clap(long, value_enum)]
+    pub indent_style: Option<ir::IndentStyle>,
+
+    /// Number of spaces per indent level in the generated code
+    #[clap(long)]
+    pub indent_width: Option<usize>,
+
+    /// Quote for string literals in the generated code
+    #[clap(long, value_enum)]
+    pub quote: Option<ir::Quote>,
+
+    /// End all statements with semicolons in the generated code.
+    ///
+    /// By default, a few statements in the generated classes are not ended with semicolons,
+    /// which is the same as older versions
+    #[clap(long, conflicts_with = "no_semi")]
+    pub semi: bool,
+
+    /// Do not end statements with semicolons in the generated code
+    #[clap(long)]
+    pub no_semi: bool,
+
+    /// Trailing commas in multi-line lists in the generated code.
+    /// By default, the lists are kept as generated
+    #[clap(long, value_enum)]
+    pub trailing_comma: Option<ir::TrailingComma>,
+
+    /// Split lists into multiple lines in the generated code when a line is longer than this.
+    /// 0 for no limit
+    #[clap(long)]
+    pub line_width: Option<usize>,
+
+    /// Do not read the style from `.editorconfig`
+    #[clap(long)]
+    pub no_editorconfig: bool,
+
+    /// Replace the comment at the start of each generated file with the content of this file.
+    ///
+    /// Each line is put in the comment. `{version}`, `{protocol}`, `{source}` and `{hash}`
+    /// are replaced with the version of the CLI, the protocol identifier, the input file(s)
+    /// and a hash of the generated content after the header. An empty file removes the comment.
+    #[clap(long)]
+    pub header_file: Option<String>,
+
+    /// Add a `SPDX-License-Identifier` comment with this license expression
+    /// at the start of each generated file, for example `MIT`
+    #[clap(long)]
+    pub license: Option<String>,
+
+    /// Add the directive for lint tools after the header in each generated TypeScript
+    /// and JavaScript file. Can be specified multiple times
+    #[clap(long, value_enum)]
+    pub lint_directive: Vec<ir::LintDirective>,
+
+    /// Log a warning in the generated receivers the first time each function
+    /// marked with `@deprecated` is called by the other side
+    #[clap(long)]
+    pub log_deprecated: bool,
+}
+
+impl Default for Options {
+    /// The default values of the CLI options. The protocol and the inputs still
+    /// need to be specified
+    fn default() -> Self {
+        Self {
+            inputs: vec![],
+            protocol: None,
+            prefix: None,
+            no_gitignore: false,
+            link: vec![],
+            dir: "interfaces".to_string(),
+            single_file: None,
+            rust: None,
+            rust_type: vec![],
+            rust_runtime: None,
+            target: ir::Target::default(),
+            mock: false,
+            loopback: false,
+            index: false,
+            trace: false,
+            dispatch: ir::Dispatch::default(),
+            dispatch_threshold: 64,
+            indent_style: None,
+            indent_width: None,
+            quote: None,
+            semi: false,
+            no_semi: false,
+            trailing_comma: None,
+            line_width: None,
+            no_editorconfig: false,
+            header_file: None,
+            license: None,
+            lint_directive: vec![],
+            log_deprecated: false,
+        }
+    }
+}
@@ -6,12 +6,14 @@ use cu::pre::*;
 use swc_common::comments::SingleThreadedComments;
 use swc_common::errors::Handler;
 use swc_common::sync::Lrc;
-use swc_common::{SourceFile, SourceMap, Spanned};
+use swc_common::{FileName, SourceFile, SourceMap, Spanned};
 use swc_core::ecma::ast::{Decl, EsVersion, ModuleDecl, ModuleItem, TsInterfaceDecl};
 use swc_ecma_parser::lexer::Lexer;
 use swc_ecma_parser::{Parser, StringInput, Syntax};
 
-use crate::ir;
+use crate::{InputReader, diag, ir};
+
+use super::rust;
 
 /// Parsing context state for all input files
 pub struct Context {
@@ -22,6 +24,10 @@ pub struct Context {
 
     /// Tracks how many errors are discovered during parsing
     pub errors: usize,
+
+    /// If set, the diagnostics are collected here instead of being reported,
+    /// for the language server
+    pub diagnostics: Option<Vec<diag::Diagnostic>>,
 }
 
 impl Default for Context {
@@ -38,26 +44,63 @@ impl Default for Context {
             source_map,
             errors: 0,
             handler,
+            diagnostics: None,
         }
     }
 }
 
 impl Context {
-    pub fn parse(mut self, inputs: &[String]) -> cu::Result<BTreeMap<String, ir::Interface>> {
+    pub fn parse(
+        mut self,
+        inputs: &[String],
+        reader: &dyn InputReader,
+    ) -> cu::Result<(BTreeMap<String, ir::Interface>, Vec<ir::DeclFile>)> {
+        // the declaration file generated for a Rust input
+        // cannot be another input
+        for input in inputs.iter().filter(|x| x.ends_with(".rs")) {
+            let decl_filename = rust::rust_decl_filename(Path::new(input).file_name_str()?);
+            for other in inputs {
+                if Path::new(other).file_name_str()? == decl_filename {
+                    cu::bail!(
+                        "{decl_filename} is generated from {input}, and cannot be used as an input at the same time"
+                    );
+                }
+            }
+        }
+
         let mut out = BTreeMap::new();
+        let mut decl_files = vec![];
         for input in inputs {
-            let file_ctx = cu::check!(
-                FileContext::try_new(&mut self, input),
+            let decl_file = cu::check!(
+                self.load_input(input, reader, &mut out),
                 "failed to load file: {input}"
             )?;
-            file_ctx.parse_into(&mut out);
+            decl_files.extend(decl_file);
         }
 
         if self.errors > 0 {
             cu::bail!("found {} errors while parsing input files", self.errors);
         }
 
-        Ok(out)
+        Ok((out, decl_files))
+    }
+
+    /// Read one input file and add the interfaces to the output.
+    /// For Rust inputs, the declaration file is returned
+    pub fn load_input(
+        &mut self,
+        input: &str,
+        reader: &dyn InputReader,
+        out: &mut BTreeMap<String, ir::Interface>,
+    ) -> cu::Result<Option<ir::DeclFile>> {
+        let source = reader.read_string(Path::new(input))?;
+        if input.ends_with(".rs") {
+            let file_ctx = rust::RustFileContext::try_new(self, input, source)?;
+            return Ok(Some(file_ctx.parse_into(out)));
+        }
+        let file_ctx = FileContext::try_new(self, input, source)?;
+        file_ctx.parse_into(out);
+        Ok(None)
     }
 }
 
@@ -75,67 +118,69 @@ pub struct FileContext<'a> {
     /// Name of the file. Used to generate import statements
     filename: String,
 
+    /// Path of the file, as given to the CLI
+    path: String,
+
     /// SWC store for comments, so documentation comments can be preserved
     /// in the generated code
     pub comments: SingleThreadedComments,
 }
 
 impl<'a> FileContext<'a> {
-    /// Create a new file parsing context and load the file
-    pub fn try_new(ctx: &'a mut Context, path: &str) -> cu::Result<Self> {
-        let path = Path::new(path);
+    /// Create a new file parsing context with the content of the file
+    pub fn try_new(ctx: &'a mut Context, path_str: &str, source: String) -> cu::Result<Self> {
+        let path = Path::new(path_str);
         let filename = path.file_name_str()?;
 
         if filename.ends_with(".bus.ts") {
             cu::bail!(".bus.ts is a reserved file extension for generated files.");
         }
 
+        let source_file = ctx
+            .source_map
+            .new_source_file(Lrc::new(FileName::Real(path.to_path_buf())), source);
         let comments = SingleThreadedComments::default();
-
-        let source_file = cu::check!(
-            ctx.source_map.load_file(path),
-            "failed to load input file into parser"
-        )?;
-
         Ok(Self {
             ctx,
             source_file,
             filename: filename.to_string(),
+            path: path_str.to_string(),
             comments,
         })
     }
 
     /// Parses the interfaces in the file and adds them to the output
     pub fn parse_into(mut self, out: &mut BTreeMap<String, ir::Interface>) {
-        let lexer = Lexer::new(
-            Syntax::Typescript(Default::default()),
-            EsVersion::EsNext,
-            StringInput::from(&*self.source_file),
-            Some(&self.comments),
-        );
-
-        let mut has_error = false;
-        let mut parser = Parser::new_from(lexer);
-        for e in parser.take_errors() {
-            e.into_diagnostic(&self.handler).emit();
-            has_error = true;
-        }
+        let (result, errors) = {
+            let lexer = Lexer::new(
+                Syntax::Typescript(Default::default()),
+                EsVersion::EsNext,
+                StringInput::from(&*self.source_file),
+                Some(&self.comments),
+            );
+            let mut parser = Parser::new_from(lexer);
+            let mut errors = parser.take_errors();
+            let result = parser.parse_module();
+            errors.extend(parser.take_errors());
+            (result, errors)
+        };
 
-        let result = parser.parse_module();
-        for e in parser.take_errors() {
-            e.into_diagnostic(&self.handler).emit();
-            has_error = true;
+        let has_error = !errors.is_empty();
+        for e in errors {
+            self.emit_syntax_error(e);
         }
 
         let module = match result {
             Ok(module) => {
-                if has_error {
+                // the parser recovers from some errors, which is only useful for
+                // the language server to show the other diagnostics
+                if has_error && self.diagnostics.is_none() {
                     return;
                 }
                 module
             }
             Err(e) => {
-                e.into_diagnostic(&self.handler).emit();
+                self.emit_syntax_error(e);
                 return;
             }
         };
@@ -165,10 +210,11 @@ impl<'a> FileContext<'a> {
             let filename = self.filename.clone();
             let interface_ctx = InterfaceContext::new(&mut self, imports.clone());
 
+            #[allow(clippy::collapsible_if)]
             if let Some(interface) = interface_ctx.parse(filename, &item, comments) {
                 if let Some(old) = out.insert(interface.name.clone(), interface) {
                     self.emit_error(
-                        item.span,
+                        item.span, diag::Code::Duplicate,
                         format!("duplicate interface name: {}. Interface names must be unique across all input files", old.name),
                     );
                 }
@@ -199,11 +245,19 @@ impl<'a, 'b> InterfaceContext<'a, 'b> {
         comments: ir::CommentBlock,
     ) -> Option<ir::Interface> {
         if item.type_params.is_some() {
-            self.emit_error(item.span, "interface type parameters are not supported");
+            self.emit_error(
+                item.span,
+                diag::Code::UnsupportedGenerics,
+                "interface type parameters are not supported",
+            );
             return None;
         }
         if !item.extends.is_empty() {
-            self.emit_error(item.span, "interface inheritance is not supported");
+            self.emit_error(
+                item.span,
+                diag::Code::UnsupportedInheritance,
+                "interface inheritance is not supported",
+            );
             return None;
         }
 
@@ -211,6 +265,7 @@ impl<'a, 'b> InterfaceContext<'a, 'b> {
         if name.starts_with("_wx") {
             self.emit_error(
                 item.span,
+                diag::Code::InvalidName,
                 "interface names cannot start with `_wx` to avoid conflict with generated code",
             );
             return None;
@@ -218,17 +273,19 @@ impl<'a, 'b> InterfaceContext<'a, 'b> {
 
         // disallow empty interface - they should use the builtin stub instead
         if item.body.body.is_empty() {
-            self.emit_error(item.span, "empty interfaces are not allowed. If you want a one-direction connection, simply omit the --link option for your interface.");
+            self.emit_error(item.span, diag::Code::EmptyInterface, "empty interfaces are not allowed. If you want a one-direction connection, simply omit the --link option for your interface.");
             return None;
         }
 
         let mut functions = BTreeMap::new();
 
         for member in &item.body.body {
+            #[allow(clippy::collapsible_if)]
             if let Some(f) = self.parse_function(member) {
                 if let Some(old) = functions.insert(f.name.clone(), f) {
                     self.emit_error(
                         member.span(),
+                        diag::Code::Duplicate,
                         format!(
                             "duplicate function name in interface {}: {}",
                             name, old.name
@@ -238,9 +295,13 @@ impl<'a, 'b> InterfaceContext<'a, 'b> {
             }
         }
 
+        let path = self.path.clone();
+        let span = self.to_diag_span(item.id.span);
         Some(ir::Interface::new(
             name,
             filename,
+            path,
+            Some(span),
             comments,
             self.imports,
             functions.into_values().collect(),
@@ -1,12 +1,44 @@
 use std::collections::BTreeMap;
 
-use crate::ir;
 use crate::parse::contexts;
+use crate::{InputReader, diag, ir};
 
-/// Parses the input TS files, and load the interface declarations from them.
+/// Parses the input TS or Rust files, and load the interface declarations from them.
+///
+/// The TypeScript declarations to generate for the Rust inputs are also returned
 pub fn load_interfaces_from_inputs(
     inputs: &[String],
-) -> cu::Result<BTreeMap<String, ir::Interface>> {
+    reader: &dyn InputReader,
+) -> cu::Result<(BTreeMap<String, ir::Interface>, Vec<ir::DeclFile>)> {
     let ctx = contexts::Context::default();
-    ctx.parse(inputs)
+    ctx.parse(inputs, reader)
+}
+
+/// Parses the input files for the language server, without stopping at errors.
+///
+/// The interfaces and the declarations for the Rust inputs are returned
+/// with all diagnostics found while parsing
+pub fn check_inputs(
+    inputs: &[String],
+    reader: &dyn InputReader,
+) -> (
+    BTreeMap<String, ir::Interface>,
+    Vec<ir::DeclFile>,
+    Vec<diag::Diagnostic>,
+) {
+    let mut ctx = contexts::Context {
+        diagnostics: Some(vec![]),
+        ..Default::default()
+    };
+    let mut out = BTreeMap::new();
+    let mut decl_files = vec![];
+    for input in inputs {
+        match ctx.load_input(input, reader, &mut out) {
+            Ok(decl_file) => decl_files.extend(decl_file),
+            Err(e) => ctx.report(
+                diag::Diagnostic::new(diag::Code::InvalidInput, format!("{e:#}")).with_file(input),
+            ),
+        }
+    }
+    (out, decl_files, ctx.diagnostics.unwrap_or_default())
 }
@@ -1,9 +1,12 @@
 use swc_common::{Span, Spanned as _};
-use swc_core::ecma::ast::{Expr, TsEntityName, TsFnParam, TsType, TsTypeAnn, TsTypeElement};
+use swc_core::ecma::ast::{
+    Expr, TsEntityName, TsFnOrConstructorType, TsFnParam, TsKeywordTypeKind, TsPropertySignature,
+    TsType, TsTypeAnn, TsTypeElement, TsUnionOrIntersectionType,
+};
 
 use super::contexts::InterfaceContext;
 
-use crate::ir;
+use crate::{diag, ir};
 
 impl InterfaceContext<'_, '_> {
     /// Parse a function in the interface into IR
@@ -11,38 +14,69 @@ impl InterfaceContext<'_, '_> {
         // filter out unsupported syntax
         let method = match item {
             TsTypeElement::TsCallSignatureDecl(x) => {
-                self.emit_error(x.span, "function: call signature not supported");
+                self.emit_error(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: call signature not supported",
+                );
                 return None;
             }
             TsTypeElement::TsConstructSignatureDecl(x) => {
-                self.emit_error(x.span, "function: construct signature is not supported.");
+                self.emit_error(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: construct signature is not supported.",
+                );
                 return None;
             }
             TsTypeElement::TsGetterSignature(x) => {
-                self.emit_error(x.span, "function: getter signature is not supported.");
+                self.emit_error(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: getter signature is not supported.",
+                );
                 return None;
             }
             TsTypeElement::TsSetterSignature(x) => {
-                self.emit_error(x.span, "function: setter signature is not supported.");
+                self.emit_error(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: setter signature is not supported.",
+                );
                 return None;
             }
             TsTypeElement::TsIndexSignature(x) => {
-                self.emit_error(x.span, "function: index signature is not supported.");
+                self.emit_error(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: index signature is not supported.",
+                );
                 return None;
             }
             TsTypeElement::TsPropertySignature(x) => {
-                self.emit_error(x.span, "function: property signature is currently not supported. Please change to method declaration");
+                let fix = self.property_to_method(x);
+                self.emit_error_with_fix(
+                    x.span,
+                    diag::Code::UnsupportedMember,
+                    "function: property signature is currently not supported. Please change to method declaration",
+                    fix,
+                );
                 return None;
             }
             TsTypeElement::TsMethodSignature(method) => method,
         };
         if method.optional {
-            self.emit_error(method.span, "functin: optional methods are not supported");
+            self.emit_error(
+                method.span,
+                diag::Code::UnsupportedMember,
+                "functin: optional methods are not supported",
+            );
             return None;
         }
         if method.type_params.is_some() {
             self.emit_error(
                 method.span,
+                diag::Code::UnsupportedGenerics,
                 "function: method type parameters are not supported",
             );
             return None;
@@ -51,36 +85,157 @@ impl InterfaceContext<'_, '_> {
         let name = match method.key.as_ref() {
             Expr::Ident(x) => x.sym.to_string(),
             _ => {
-                self.emit_error(method.span, "function: method name must be an identifier");
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidName,
+                    "function: method name must be an identifier",
+                );
                 return None;
             }
         };
 
-        let retty_ann = self.parse_function_retty_ann(method.span, method.type_ann.as_deref())?;
+        let (retty_ann, retty_kind) =
+            self.parse_function_retty_ann(method.span, method.type_ann.as_deref())?;
 
         let args = method
             .params
             .iter()
             .filter_map(|arg| self.parse_function_arg(arg))
-            .collect();
+            .collect::<Vec<_>>();
+
+        if let Some(i) = args.iter().position(|arg| arg.signal)
+            && i != args.len() - 1
+        {
+            self.emit_error(
+                method.span,
+                diag::Code::InvalidArgument,
+                format!(
+                    "function: AbortSignal argument `{}` must be the last argument",
+                    args[i].ident
+                ),
+            );
+            return None;
+        }
+
+        let comment = self.parse_doc_comments_at_pos(method.span.lo());
+        let transfer = match ir::Transfer::parse_all(&comment, &args, &retty_ann) {
+            Ok(transfer) => transfer,
+            Err(e) => {
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidDocTag,
+                    format!("function: {e}"),
+                );
+                return None;
+            }
+        };
+        let timeout = match ir::Function::parse_timeout(&comment) {
+            Ok(timeout) => timeout,
+            Err(e) => {
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidDocTag,
+                    format!("function: {e}"),
+                );
+                return None;
+            }
+        };
+
+        let oneway_marker = match &retty_kind {
+            RettyKind::OneWay(ident) => Some(ident.as_str()),
+            _ => None,
+        };
+        let oneway = match ir::Function::parse_oneway(&comment, &retty_ann, timeout, oneway_marker)
+        {
+            Ok(oneway) => oneway,
+            Err(e) => {
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidDocTag,
+                    format!("function: {e}"),
+                );
+                return None;
+            }
+        };
+
+        let keep_alive = match ir::Function::parse_keep_alive(&comment, &args, oneway.is_some()) {
+            Ok(keep_alive) => keep_alive,
+            Err(e) => {
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidDocTag,
+                    format!("function: {e}"),
+                );
+                return None;
+            }
+        };
+
+        if oneway.is_some()
+            && let Some(arg) = args.iter().find(|arg| arg.signal)
+        {
+            self.emit_error(
+                method.span, diag::Code::InvalidDocTag,
+                format!(
+                    "function: @oneway: one-way functions cannot be cancelled, remove the AbortSignal argument `{}`",
+                    arg.ident
+                ),
+            );
+            return None;
+        }
+
+        let stream = match retty_kind {
+            RettyKind::Stream(ident) => Some(ident),
+            _ => None,
+        };
+        if stream.is_some() {
+            if oneway.is_some() {
+                self.emit_error(
+                    method.span,
+                    diag::Code::InvalidDocTag,
+                    "function: @oneway: functions returning a stream cannot be one-way",
+                );
+                return None;
+            }
+            if transfer.iter().any(|t| t.arg.is_none()) {
+                self.emit_error(
+                    method.span, diag::Code::InvalidDocTag,
+                    "function: @transfer: transferring the return value is not supported for streams",
+                );
+                return None;
+            }
+        }
 
         Some(ir::Function {
             name,
-            comment: self.parse_doc_comments_at_pos(method.span.lo()),
+            span: Some(self.to_diag_span(method.key.span())),
+            comment,
             retty_ann,
             args,
+            transfer,
+            timeout,
+            oneway,
+            stream,
+            keep_alive,
+            rust_name: None,
+            rust_retty: None,
         })
     }
 
-    /// Parse the function's return type. Returns the inner type inside WxPromise<T>, with the
-    /// angle brackets included.
+    /// Parse the function's return type. Returns the inner type inside WxPromise<T>
+    /// or WxStream<T>, with the angle brackets included.
+    ///
+    /// If the return type is `WxOneWay`, the inner type is `void`
     fn parse_function_retty_ann(
         &mut self,
         span: Span,
         type_ann: Option<&TsTypeAnn>,
-    ) -> Option<String> {
+    ) -> Option<(String, RettyKind)> {
         let Some(type_ann) = type_ann else {
-            self.emit_error(span, "function: missing return type annotation.");
+            self.emit_error(
+                span,
+                diag::Code::InvalidReturnType,
+                "function: missing return type annotation.",
+            );
             return None;
         };
 
@@ -89,13 +244,40 @@ impl InterfaceContext<'_, '_> {
             return None;
         };
 
-        // outer type must be WxPromise
-        if !matches!(&type_ref.type_name, TsEntityName::Ident(x) if x.sym.as_str() == self.imports.ident_wxpromise)
+        if let TsEntityName::Ident(x) = &type_ref.type_name
+            && let Some(ident_wxoneway) = &self.imports.ident_wxoneway
+            && x.sym.as_str() == ident_wxoneway
         {
-            self.emit_invalid_retty_error(type_ref.span);
-            return None;
+            if type_ref.type_params.is_some() {
+                self.emit_error(
+                    type_ref.span,
+                    diag::Code::InvalidReturnType,
+                    "function: WxOneWay does not take type parameters",
+                );
+                return None;
+            }
+            return Some((
+                "<void>".to_string(),
+                RettyKind::OneWay(ident_wxoneway.clone()),
+            ));
         }
 
+        // outer type must be WxPromise or WxStream
+        let kind = match &type_ref.type_name {
+            TsEntityName::Ident(x) if x.sym.as_str() == self.imports.ident_wxpromise => {
+                RettyKind::Promise
+            }
+            TsEntityName::Ident(x)
+                if self.imports.ident_wxstream.as_deref() == Some(x.sym.as_str()) =>
+            {
+                RettyKind::Stream(x.sym.to_string())
+            }
+            _ => {
+                self.emit_invalid_retty_error(type_ref.span);
+                return None;
+            }
+        };
+
         let Some(inner_type) = &type_ref.type_params else {
             self.emit_invalid_retty_error(type_ref.span);
             return None;
@@ -107,13 +289,44 @@ impl InterfaceContext<'_, '_> {
             self.emit_invalid_retty_error(inner_type.span);
             return None;
         }
-        Some(retty_ann)
+        Some((retty_ann, kind))
+    }
+
+    /// Make the edit to change a property with a function type to a method declaration,
+    /// for example `foo: (a: string) => WxPromise<void>` to `foo(a: string): WxPromise<void>`
+    fn property_to_method(
+        &mut self,
+        property: &TsPropertySignature,
+    ) -> Option<(Span, &'static str, String)> {
+        if property.computed || property.optional {
+            return None;
+        }
+        let Expr::Ident(key) = property.key.as_ref() else {
+            return None;
+        };
+        let type_ann = property.type_ann.as_ref()?;
+        let TsType::TsFnOrConstructorType(TsFnOrConstructorType::TsFnType(fn_type)) =
+            type_ann.type_ann.as_ref()
+        else {
+            return None;
+        };
+        if fn_type.type_params.is_some() {
+            return None;
+        }
+        let mut params = vec![];
+        for param in &fn_type.params {
+            params.push(self.raw_source(param.span())?);
+        }
+        let retty = self.raw_source(fn_type.type_ann.type_ann.span())?;
+        let span = Span::new(property.span.lo, type_ann.span.hi);
+        let replacement = format!("{}({}): {retty}", key.sym, params.join(", "));
+        Some((span, "change to a method declaration", replacement))
     }
 
     fn emit_invalid_retty_error(&mut self, span: Span) {
         self.emit_error(
-            span,
-            "function: return type must be a WxPromise<T>. You might need to import it from \"@pistonite/workex\". The import can be renamed, but type alias is otherwise not supported.",
+            span, diag::Code::InvalidReturnType,
+            "function: return type must be a WxPromise<T>, WxStream<T> or WxOneWay. You might need to import it from \"@pistonite/workex\". The import can be renamed, but type alias is otherwise not supported.",
         );
     }
 
@@ -124,6 +337,7 @@ impl InterfaceContext<'_, '_> {
             TsFnParam::Array(x) => {
                 self.emit_error(
                     x.span,
+                    diag::Code::InvalidArgument,
                     "function argument: array destructuring is not supported",
                 );
                 return None;
@@ -131,6 +345,7 @@ impl InterfaceContext<'_, '_> {
             TsFnParam::Object(x) => {
                 self.emit_error(
                     x.span,
+                    diag::Code::InvalidArgument,
                     "function argument: object destructuring is not supported",
                 );
                 return None;
@@ -138,15 +353,25 @@ impl InterfaceContext<'_, '_> {
             TsFnParam::Rest(x) => {
                 self.emit_error(
                     x.span,
+                    diag::Code::InvalidArgument,
                     "function argument: rest object destructuring is not supported",
                 );
                 return None;
             }
         };
-        let typ = match &ident.type_ann {
-            Some(x) => self.raw_source(x.type_ann.span())?,
+        let (typ, callback, signal) = match &ident.type_ann {
+            Some(x) => {
+                let typ = self.raw_source(x.type_ann.span())?;
+                let callback = self.parse_callback_type(&x.type_ann)?;
+                let signal = self.parse_signal_type(&x.type_ann, &typ)?;
+                (typ, callback, signal)
+            }
             None => {
-                self.emit_error(ident.span, "missing type annotation for function argument");
+                self.emit_error(
+                    ident.span,
+                    diag::Code::InvalidArgument,
+                    "missing type annotation for function argument",
+                );
                 return None;
             }
         };
@@ -154,6 +379,120 @@ impl InterfaceContext<'_, '_> {
             ident: ident.id.sym.to_string(),
             optional: ident.id.optional,
             typ,
+            callback,
+            signal,
+            rust_typ: None,
         })
     }
+
+    /// Check if the type of an argument is `AbortSignal` or `AbortSignal | undefined`, which
+    /// cancels the call instead of being sent to the other side. Emits error if `AbortSignal`
+    /// is used in the type in other ways, since it cannot be sent
+    fn parse_signal_type(&mut self, typ: &TsType, source: &str) -> Option<bool> {
+        // the type is not resolved, so only the global AbortSignal is recognized
+        let is_signal = |typ: &TsType| {
+            matches!(unwrap_parens(typ), TsType::TsTypeRef(x)
+                if x.type_params.is_none()
+                    && matches!(&x.type_name, TsEntityName::Ident(x) if x.sym.as_str() == "AbortSignal"))
+        };
+        let is_undefined = |typ: &TsType| {
+            matches!(unwrap_parens(typ), TsType::TsKeywordType(x)
+                if x.kind == TsKeywordTypeKind::TsUndefinedKeyword)
+        };
+        let signal = match unwrap_parens(typ) {
+            TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsUnionType(x)) => {
+                x.types.iter().any(|x| is_signal(x))
+                    && x.types.iter().all(|x| is_signal(x) || is_undefined(x))
+            }
+            x => is_signal(x),
+        };
+        if !signal && contains_ident(source, "AbortSignal") {
+            self.emit_error(
+                typ.span(),
+                diag::Code::InvalidArgument,
+                "function argument: AbortSignal cannot be sent to the other side. To cancel the call, the type of the last argument should be `AbortSignal` or `AbortSignal | undefined`",
+            );
+            return None;
+        }
+        Some(signal)
+    }
+
+    /// Check if the type of an argument is a function type, which is passed to the
+    /// other side as a callback. Emits error if the function cannot be called remotely
+    fn parse_callback_type(&mut self, typ: &TsType) -> Option<bool> {
+        let fn_type = match unwrap_parens(typ) {
+            TsType::TsFnOrConstructorType(TsFnOrConstructorType::TsFnType(x)) => x,
+            TsType::TsFnOrConstructorType(TsFnOrConstructorType::TsConstructorType(x)) => {
+                self.emit_error(
+                    x.span,
+                    diag::Code::InvalidArgument,
+                    "function argument: constructor types cannot be sent to the other side",
+                );
+                return None;
+            }
+            _ => return Some(false),
+        };
+        // the callback is called on the other side, so it can't return a value synchronously
+        let is_valid_retty = match fn_type.type_ann.type_ann.as_ref() {
+            TsType::TsKeywordType(x) => x.kind == TsKeywordTypeKind::TsVoidKeyword,
+            TsType::TsTypeRef(x) => {
+                matches!(&x.type_name, TsEntityName::Ident(x) if x.sym.as_str() == self.imports.ident_wxpromise)
+            }
+            _ => false,
+        };
+        if !is_valid_retty {
+            self.emit_error(
+                fn_type.type_ann.span, diag::Code::InvalidArgument,
+                "function argument: callbacks must return void or WxPromise<T>, since they are called from the other side",
+            );
+            return None;
+        }
+        for param in &fn_type.params {
+            if let TsFnParam::Ident(x) = param
+                && let Some(type_ann) = &x.type_ann
+                && matches!(
+                    unwrap_parens(&type_ann.type_ann),
+                    TsType::TsFnOrConstructorType(_)
+                )
+            {
+                self.emit_error(
+                    x.span,
+                    diag::Code::InvalidArgument,
+                    "function argument: callbacks cannot take other functions as arguments",
+                );
+                return None;
+            }
+        }
+        Some(true)
+    }
+}
+
+/// Check if the identifier appears in the source as a whole word
+fn contains_ident(source: &str, ident: &str) -> bool {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    source.match_indices(ident).any(|(i, _)| {
+        !source[..i].chars().next_back().is_some_and(is_ident_char)
+            && !source[i + ident.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_ident_char)
+    })
+}
+
+/// Get the type inside the parentheses, like `number | string` in `(number | string)`
+fn unwrap_parens(mut typ: &TsType) -> &TsType {
+    while let TsType::TsParenthesizedType(x) = typ {
+        typ = &x.type_ann;
+    }
+    typ
+}
+
+/// The outer type of a function's return type
+enum RettyKind {
+    /// `WxPromise<T>`
+    Promise,
+    /// `WxOneWay`, with the identifier used in the source
+    OneWay(String),
+    /// `WxStream<T>`, with the identifier used in the source
+    Stream(String),
 }
@@ -2,7 +2,7 @@ use swc_core::ecma::ast::{ImportDecl, ImportSpecifier, ModuleDecl, ModuleExportN
 
 use super::contexts::FileContext;
 
-use crate::ir;
+use crate::{diag, ir};
 
 impl FileContext<'_> {
     /// Parse the supported import statements from the module
@@ -54,7 +54,11 @@ impl FileContext<'_> {
         }
 
         let Some(import_src) = import.src.value.as_str() else {
-            self.emit_error(import.src.span, "import source string is not utf-8");
+            self.emit_error(
+                import.src.span,
+                diag::Code::InvalidImport,
+                "import source string is not utf-8",
+            );
             return None;
         };
 
@@ -5,4 +5,5 @@ mod comment;
 mod contexts;
 mod function;
 mod import;
+mod rust;
 mod util;
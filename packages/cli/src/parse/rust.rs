@@ -0,0 +1,1217 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use cu::pre::*;
+use proc_macro2::Span;
+use quote::ToTokens as _;
+use syn::ext::IdentExt as _;
+use syn::spanned::Spanned as _;
+use syn::{
+    Attribute, Expr, Fields, FnArg, GenericArgument, Item, ItemEnum, ItemStruct, ItemTrait, Lit,
+    Meta, Pat, PathArguments, ReceiverKind, ReturnType, TraitItem, Type,
+};
+
+use super::contexts::Context;
+
+use crate::{diag, ir};
+
+/// Parsing context state for a single Rust input file
+#[derive(Deref, DerefMut)]
+pub struct RustFileContext<'a> {
+    /// Inner context
+    #[deref]
+    #[deref_mut]
+    ctx: &'a mut Context,
+
+    /// Path to the file, for error messages
+    path: String,
+
+    /// Name of the Rust file
+    filename: String,
+
+    /// Name of the TypeScript declaration file to generate
+    decl_filename: String,
+
+    /// Content of the file
+    source: String,
+}
+
+impl<'a> RustFileContext<'a> {
+    /// Create a new file parsing context with the content of the file
+    pub fn try_new(ctx: &'a mut Context, path: &str, source: String) -> cu::Result<Self> {
+        let filename = Path::new(path).file_name_str()?.to_string();
+        let decl_filename = rust_decl_filename(&filename);
+        Ok(Self {
+            ctx,
+            path: path.to_string(),
+            filename,
+            decl_filename,
+            source,
+        })
+    }
+
+    /// Emit an error message with the location in the Rust file
+    fn emit_rust_error<T: std::fmt::Display>(&mut self, span: Span, code: diag::Code, msg: T) {
+        let msg = if self.diagnostics.is_some() || diag::is_json() {
+            msg.to_string()
+        } else {
+            format!("[workex] {msg}")
+        };
+        let diagnostic = diag::Diagnostic::new(code, msg)
+            .with_file(&self.path)
+            .with_span(Some(to_diag_span(span)));
+        self.report(diagnostic);
+        self.errors += 1;
+    }
+
+    /// Parses the traits marked with `#[workex::interface]` and serializable types in the file.
+    /// The interfaces are added to the output, and the declarations are returned
+    pub fn parse_into(mut self, out: &mut BTreeMap<String, ir::Interface>) -> ir::DeclFile {
+        let mut decl = ir::DeclFile {
+            filename: self.decl_filename.clone(),
+            source: self.filename.clone(),
+            types: vec![],
+            interfaces: vec![],
+        };
+        let file = match syn::parse_file(&self.source) {
+            Ok(file) => file,
+            Err(e) => {
+                self.emit_rust_error(e.span(), diag::Code::Syntax, e);
+                return decl;
+            }
+        };
+
+        let mut interfaces = vec![];
+        for item in &file.items {
+            match item {
+                Item::Trait(item) if is_workex_interface(&item.attrs) => {
+                    if let Some(interface) = self.parse_trait(item) {
+                        interfaces.push((item.ident.span(), interface));
+                    }
+                }
+                Item::Struct(item) if derives_serde(&item.attrs) => {
+                    if let Some(t) = self.parse_struct(item) {
+                        decl.types.push(t);
+                    }
+                }
+                Item::Enum(item) if derives_serde(&item.attrs) => {
+                    if let Some(t) = self.parse_enum(item) {
+                        decl.types.push(t);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let type_names = decl
+            .types
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>();
+
+        for (span, (name, comment, functions)) in interfaces {
+            // import the declared types used by the functions
+            // into the generated implementation
+            let mut used = BTreeSet::new();
+            for f in &functions {
+                let types = f
+                    .args
+                    .iter()
+                    .map(|arg| arg.typ.as_str())
+                    .chain(std::iter::once(f.retty_ann.as_str()));
+                for typ in types {
+                    for word in typ.split(|c: char| !c.is_alphanumeric() && c != '_') {
+                        if type_names.contains(&word) {
+                            used.insert(word.to_string());
+                        }
+                    }
+                }
+            }
+            let mut statements = vec![];
+            if !used.is_empty() {
+                statements.push(ir::Import::Import {
+                    is_type: true,
+                    idents: used
+                        .into_iter()
+                        .map(|ident| ir::ImportIdent {
+                            is_type: false,
+                            ident,
+                            rename: None,
+                        })
+                        .collect(),
+                    from: format!("./{}", self.decl_filename),
+                });
+            }
+
+            decl.interfaces.push(name.clone());
+            let interface = ir::Interface::new(
+                name,
+                self.decl_filename.clone(),
+                self.path.clone(),
+                Some(to_diag_span(span)),
+                comment,
+                ir::Imports::new(statements),
+                functions,
+            );
+            if let Some(old) = out.insert(interface.name.clone(), interface) {
+                self.emit_rust_error(
+                    span, diag::Code::Duplicate,
+                    format!("duplicate interface name: {}. Interface names must be unique across all input files", old.name),
+                );
+            }
+        }
+
+        decl
+    }
+
+    /// Parse a trait marked with `#[workex::interface]`
+    fn parse_trait(
+        &mut self,
+        item: &ItemTrait,
+    ) -> Option<(String, ir::CommentBlock, Vec<ir::Function>)> {
+        let span = item.ident.span();
+        if !item.generics.params.is_empty() {
+            self.emit_rust_error(
+                span,
+                diag::Code::UnsupportedGenerics,
+                "trait type parameters are not supported",
+            );
+            return None;
+        }
+        if !item.supertraits.is_empty() {
+            self.emit_rust_error(
+                span,
+                diag::Code::UnsupportedInheritance,
+                "supertraits are not supported",
+            );
+            return None;
+        }
+        let name = item.ident.to_string();
+        if name.starts_with("_wx") {
+            self.emit_rust_error(
+                span,
+                diag::Code::InvalidName,
+                "interface names cannot start with `_wx` to avoid conflict with generated code",
+            );
+            return None;
+        }
+        if item.items.is_empty() {
+            self.emit_rust_error(span, diag::Code::EmptyInterface, "empty interfaces are not allowed. If you want a one-direction connection, simply omit the --link option for your interface.");
+            return None;
+        }
+
+        let mut functions = BTreeMap::new();
+        for member in &item.items {
+            let TraitItem::Fn(member) = member else {
+                self.emit_rust_error(
+                    member.span(),
+                    diag::Code::UnsupportedMember,
+                    "only functions are supported in workex interfaces",
+                );
+                continue;
+            };
+            if let Some(f) = self.parse_trait_fn(member)
+                && let Some(old) = functions.insert(f.name.clone(), f)
+            {
+                self.emit_rust_error(
+                    member.sig.ident.span(),
+                    diag::Code::Duplicate,
+                    format!(
+                        "duplicate function name in interface {}: {}",
+                        name, old.name
+                    ),
+                );
+            }
+        }
+
+        Some((
+            name,
+            parse_doc_comments(&item.attrs),
+            functions.into_values().collect(),
+        ))
+    }
+
+    /// Parse a function in the trait into IR
+    fn parse_trait_fn(&mut self, item: &syn::TraitItemFn) -> Option<ir::Function> {
+        let sig = &item.sig;
+        let span = sig.ident.span();
+        if sig.asyncness.is_none() {
+            self.emit_rust_error(
+                span,
+                diag::Code::InvalidReturnType,
+                "function: functions in workex interfaces must be async",
+            );
+            return None;
+        }
+        if !sig.generics.params.is_empty() {
+            self.emit_rust_error(
+                span,
+                diag::Code::UnsupportedGenerics,
+                "function: type parameters are not supported",
+            );
+            return None;
+        }
+        if sig.variadic.is_some() {
+            self.emit_rust_error(
+                span,
+                diag::Code::InvalidArgument,
+                "function: variadic arguments are not supported",
+            );
+            return None;
+        }
+
+        let mut inputs = sig.inputs.iter();
+        match inputs.next() {
+            Some(FnArg::Receiver(receiver))
+                if receiver.mutability.is_none()
+                    && matches!(receiver.kind, ReceiverKind::Reference(_, _, None)) => {}
+            _ => {
+                self.emit_rust_error(
+                    span,
+                    diag::Code::InvalidArgument,
+                    "function: the first argument must be `&self`",
+                );
+                return None;
+            }
+        }
+
+        let mut args = vec![];
+        let mut has_error = false;
+        for input in inputs {
+            let FnArg::Typed(input) = input else {
+                continue;
+            };
+            let Pat::Ident(ident) = &*input.pat else {
+                self.emit_rust_error(
+                    input.pat.span(),
+                    diag::Code::InvalidArgument,
+                    "function argument: patterns are not supported, use an identifier",
+                );
+                has_error = true;
+                continue;
+            };
+            let typ = match rust_to_ts(&input.ty) {
+                Ok(typ) => typ,
+                Err(e) => {
+                    self.emit_rust_error(e.span(), diag::Code::UnsupportedType, e);
+                    has_error = true;
+                    continue;
+                }
+            };
+            args.push(ir::Arg {
+                ident: to_camel_case(&ident.ident.unraw().to_string()),
+                optional: false,
+                typ,
+                callback: false,
+                signal: false,
+                rust_typ: Some(type_to_string(&input.ty)),
+            });
+        }
+        if has_error {
+            return None;
+        }
+        // trailing Option arguments can be omitted in TypeScript
+        for (arg, input) in args.iter_mut().zip(sig.inputs.iter().skip(1)).rev() {
+            let FnArg::Typed(input) = input else {
+                break;
+            };
+            let Some(inner) = option_inner(&input.ty) else {
+                break;
+            };
+            arg.optional = true;
+            arg.typ = rust_to_ts(inner).ok()?;
+        }
+
+        let retty = match &sig.output {
+            ReturnType::Type(_, ty) => wx_result_inner(ty),
+            ReturnType::Default => None,
+        };
+        let Some(retty) = retty else {
+            self.emit_rust_error(
+                sig.output.span(),
+                diag::Code::InvalidReturnType,
+                "function: return type must be WxResult<T>",
+            );
+            return None;
+        };
+        let retty_ts = if is_unit(retty) {
+            "void".to_string()
+        } else {
+            match rust_to_ts(retty) {
+                Ok(typ) => typ,
+                Err(e) => {
+                    self.emit_rust_error(e.span(), diag::Code::UnsupportedType, e);
+                    return None;
+                }
+            }
+        };
+
+        let retty_ann = format!("<{retty_ts}>");
+        let mut comment = parse_doc_comments(&item.attrs);
+        if !self.parse_deprecated_attr(&item.attrs, &mut comment) {
+            return None;
+        }
+        let transfer = match ir::Transfer::parse_all(&comment, &args, &retty_ann) {
+            Ok(transfer) => transfer,
+            Err(e) => {
+                self.emit_rust_error(span, diag::Code::InvalidDocTag, format!("function: {e}"));
+                return None;
+            }
+        };
+        let timeout = match ir::Function::parse_timeout(&comment) {
+            Ok(timeout) => timeout,
+            Err(e) => {
+                self.emit_rust_error(span, diag::Code::InvalidDocTag, format!("function: {e}"));
+                return None;
+            }
+        };
+
+        let oneway = match ir::Function::parse_oneway(&comment, &retty_ann, timeout, None) {
+            Ok(oneway) => oneway,
+            Err(e) => {
+                self.emit_rust_error(span, diag::Code::InvalidDocTag, format!("function: {e}"));
+                return None;
+            }
+        };
+
+        let rust_name = sig.ident.to_string();
+        Some(ir::Function {
+            name: to_camel_case(sig.ident.unraw().to_string().as_str()),
+            span: Some(to_diag_span(sig.ident.span())),
+            comment,
+            args,
+            retty_ann,
+            transfer,
+            timeout,
+            oneway,
+            stream: None,
+            keep_alive: vec![],
+            rust_name: Some(rust_name),
+            rust_retty: Some(type_to_string(retty)),
+        })
+    }
+
+    /// Parse a serializable struct into a TypeScript type declaration
+    fn parse_struct(&mut self, item: &ItemStruct) -> Option<ir::TypeDecl> {
+        let serde = self.parse_serde_attrs(&item.attrs)?;
+        let kind = match &item.fields {
+            Fields::Named(fields) => {
+                let mut out = vec![];
+                for field in &fields.named {
+                    let field_serde = self.parse_serde_attrs(&field.attrs)?;
+                    if field_serde.skip {
+                        continue;
+                    }
+                    if field_serde.flatten {
+                        self.emit_rust_error(
+                            field.span(),
+                            diag::Code::UnsupportedType,
+                            "#[serde(flatten)] is not supported",
+                        );
+                        return None;
+                    }
+                    let name = field.ident.as_ref()?.unraw().to_string();
+                    let name = match field_serde.rename {
+                        Some(rename) => rename,
+                        None => match &serde.rename_all {
+                            Some(rule) => self.apply_rename_rule(field.span(), rule, &name)?,
+                            None => name,
+                        },
+                    };
+                    let (optional, typ) = match option_inner(&field.ty) {
+                        Some(inner) => (true, rust_to_ts(inner).map(|x| format!("{x} | null"))),
+                        None => (field_serde.default, rust_to_ts(&field.ty)),
+                    };
+                    let typ = match typ {
+                        Ok(typ) => typ,
+                        Err(e) => {
+                            self.emit_rust_error(e.span(), diag::Code::UnsupportedType, e);
+                            return None;
+                        }
+                    };
+                    out.push(ir::FieldDecl {
+                        comment: parse_doc_comments(&field.attrs),
+                        arg: ir::Arg {
+                            ident: ts_property_name(&name),
+                            optional,
+                            typ,
+                            callback: false,
+                            signal: false,
+                            rust_typ: Some(type_to_string(&field.ty)),
+                        },
+                    });
+                }
+                ir::TypeDeclKind::Interface(out)
+            }
+            Fields::Unnamed(fields) => {
+                let mut types = vec![];
+                for field in &fields.unnamed {
+                    match rust_to_ts(&field.ty) {
+                        Ok(typ) => types.push(typ),
+                        Err(e) => {
+                            self.emit_rust_error(e.span(), diag::Code::UnsupportedType, e);
+                            return None;
+                        }
+                    }
+                }
+                if types.len() == 1 {
+                    // newtype structs are serialized as the inner value
+                    ir::TypeDeclKind::Alias(types.pop().unwrap_or_default())
+                } else {
+                    ir::TypeDeclKind::Alias(format!("[{}]", types.join(", ")))
+                }
+            }
+            Fields::Unit => ir::TypeDeclKind::Alias("null".to_string()),
+        };
+        Some(ir::TypeDecl {
+            name: item.ident.to_string(),
+            comment: parse_doc_comments(&item.attrs),
+            generics: item
+                .generics
+                .type_params()
+                .map(|x| x.ident.to_string())
+                .collect(),
+            kind,
+        })
+    }
+
+    /// Parse a serializable enum into a TypeScript type declaration.
+    /// Only enums with unit variants are supported, which are serialized as strings
+    fn parse_enum(&mut self, item: &ItemEnum) -> Option<ir::TypeDecl> {
+        let serde = self.parse_serde_attrs(&item.attrs)?;
+        let mut variants = vec![];
+        for variant in &item.variants {
+            if !matches!(variant.fields, Fields::Unit) {
+                self.emit_rust_error(
+                    variant.span(),
+                    diag::Code::UnsupportedType,
+                    "only enums with unit variants are supported",
+                );
+                return None;
+            }
+            let variant_serde = self.parse_serde_attrs(&variant.attrs)?;
+            if variant_serde.skip {
+                continue;
+            }
+            let name = variant.ident.unraw().to_string();
+            let name = match variant_serde.rename {
+                Some(rename) => rename,
+                None => match &serde.rename_all {
+                    Some(rule) => self.apply_rename_rule(variant.span(), rule, &name)?,
+                    None => name,
+                },
+            };
+            variants.push(crate::emit::quoted(&name));
+        }
+        if variants.is_empty() {
+            self.emit_rust_error(
+                item.ident.span(),
+                diag::Code::UnsupportedType,
+                "enums must have at least one variant",
+            );
+            return None;
+        }
+        Some(ir::TypeDecl {
+            name: item.ident.to_string(),
+            comment: parse_doc_comments(&item.attrs),
+            generics: vec![],
+            kind: ir::TypeDeclKind::Alias(variants.join(" | ")),
+        })
+    }
+
+    /// Add `@deprecated` (and `@since`) to the comment for a `#[deprecated]` attribute,
+    /// if the comment doesn't have the tag already. Returns false if the attribute is invalid
+    fn parse_deprecated_attr(
+        &mut self,
+        attrs: &[Attribute],
+        comment: &mut ir::CommentBlock,
+    ) -> bool {
+        let Some(attr) = attrs.iter().find(|x| x.path().is_ident("deprecated")) else {
+            return true;
+        };
+        let mut note = String::new();
+        let mut since = None;
+        match &attr.meta {
+            Meta::Path(_) => {}
+            Meta::NameValue(meta) => {
+                if let Expr::Lit(lit) = &meta.value
+                    && let Lit::Str(s) = &lit.lit
+                {
+                    note = s.value();
+                }
+            }
+            Meta::List(_) => {
+                let result = attr.parse_nested_meta(|meta| {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    if meta.path.is_ident("note") {
+                        note = value.value();
+                    } else if meta.path.is_ident("since") {
+                        since = Some(value.value());
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    self.emit_rust_error(e.span(), diag::Code::Syntax, e);
+                    return false;
+                }
+            }
+        }
+        if comment.deprecated().is_none() {
+            comment.push_tag("deprecated", note.trim());
+            if let Some(since) = since {
+                comment.push_tag("since", since.trim());
+            }
+        }
+        true
+    }
+
+    /// Parse the `#[serde(...)]` attributes that affect the shape of the serialized data
+    fn parse_serde_attrs(&mut self, attrs: &[Attribute]) -> Option<SerdeAttrs> {
+        let mut out = SerdeAttrs::default();
+        for attr in attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if meta.input.peek(syn::Token![=]) {
+                        let value: syn::LitStr = meta.value()?.parse()?;
+                        out.rename = Some(value.value());
+                        return Ok(());
+                    }
+                    return Err(meta.error("only #[serde(rename = \"...\")] is supported"));
+                }
+                if meta.path.is_ident("rename_all") {
+                    if meta.input.peek(syn::Token![=]) {
+                        let value: syn::LitStr = meta.value()?.parse()?;
+                        out.rename_all = Some(value.value());
+                        return Ok(());
+                    }
+                    return Err(meta.error("only #[serde(rename_all = \"...\")] is supported"));
+                }
+                if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else if meta.path.is_ident("default") || meta.path.is_ident("skip_serializing_if")
+                {
+                    out.default = true;
+                }
+                // ignore the value of other attributes
+                if meta.input.peek(syn::Token![=]) {
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    content.parse::<proc_macro2::TokenStream>()?;
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                self.emit_rust_error(e.span(), diag::Code::Syntax, e);
+                return None;
+            }
+        }
+        Some(out)
+    }
+
+    /// Apply a serde `rename_all` rule to a field or variant name
+    fn apply_rename_rule(&mut self, span: Span, rule: &str, name: &str) -> Option<String> {
+        // split the snake_case field names or PascalCase variant names into words
+        let mut words: Vec<String> = vec![];
+        for part in name.split('_').filter(|x| !x.is_empty()) {
+            let mut word = String::new();
+            for c in part.chars() {
+                if c.is_ascii_uppercase() && !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                word.push(c.to_ascii_lowercase());
+            }
+            if !word.is_empty() {
+                words.push(word);
+            }
+        }
+        let capitalize = |w: &String| {
+            let mut c = w.chars();
+            c.next()
+                .map(|x| x.to_ascii_uppercase().to_string() + c.as_str())
+                .unwrap_or_default()
+        };
+        let out = match rule {
+            "lowercase" => words.concat(),
+            "UPPERCASE" => words.concat().to_ascii_uppercase(),
+            "PascalCase" => words.iter().map(capitalize).collect(),
+            "camelCase" => words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+                .collect(),
+            "snake_case" => words.join("_"),
+            "SCREAMING_SNAKE_CASE" => words.join("_").to_ascii_uppercase(),
+            "kebab-case" => words.join("-"),
+            "SCREAMING-KEBAB-CASE" => words.join("-").to_ascii_uppercase(),
+            _ => {
+                self.emit_rust_error(
+                    span,
+                    diag::Code::UnsupportedType,
+                    format!("unknown rename_all rule: {rule}"),
+                );
+                return None;
+            }
+        };
+        Some(out)
+    }
+}
+
+/// The `#[serde(...)]` attributes that affect the shape of the serialized data
+#[derive(Debug, Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    flatten: bool,
+    default: bool,
+}
+
+/// Get the name of the generated TypeScript declaration file for a Rust input file
+pub fn rust_decl_filename(filename: &str) -> String {
+    let stem = filename.strip_suffix(".rs").unwrap_or(filename);
+    format!("{stem}.ts")
+}
+
+/// Check if the attributes contain `#[workex::interface]`.
+///
+/// `#[workex_runtime::interface]` and an imported `#[interface]` are also accepted
+fn is_workex_interface(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let segments = &attr.path().segments;
+        match segments.len() {
+            1 => segments[0].ident == "interface",
+            2 => {
+                (segments[0].ident == "workex" || segments[0].ident == "workex_runtime")
+                    && segments[1].ident == "interface"
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Check if the attributes contain `#[derive(Serialize)]` or `#[derive(Deserialize)]`
+fn derives_serde(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        if !attr.path().is_ident("derive") {
+            return false;
+        }
+        let Ok(paths) = attr.parse_args_with(
+            syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+        ) else {
+            return false;
+        };
+        paths.iter().any(|path| {
+            path.segments
+                .last()
+                .is_some_and(|x| x.ident == "Serialize" || x.ident == "Deserialize")
+        })
+    })
+}
+
+/// Convert `///` doc comments (`#[doc = "..."]` attributes) into a comment block
+fn parse_doc_comments(attrs: &[Attribute]) -> ir::CommentBlock {
+    let mut lines = vec![];
+    for attr in attrs {
+        let Meta::NameValue(meta) = &attr.meta else {
+            continue;
+        };
+        if !meta.path.is_ident("doc") {
+            continue;
+        }
+        let Expr::Lit(lit) = &meta.value else {
+            continue;
+        };
+        let Lit::Str(s) = &lit.lit else {
+            continue;
+        };
+        for line in s.value().split('\n') {
+            let line = line.strip_prefix(' ').unwrap_or(line);
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    while lines.last().is_some_and(|x| x.is_empty()) {
+        lines.pop();
+    }
+    let start = lines.iter().take_while(|x| x.is_empty()).count();
+    lines.drain(..start);
+    ir::CommentBlock {
+        style: ir::CommentStyle::JsDoc,
+        lines,
+    }
+}
+
+/// Convert a Rust type to the TypeScript type of its serialized JSON value
+fn rust_to_ts(ty: &Type) -> syn::Result<String> {
+    match ty {
+        Type::Paren(x) => rust_to_ts(&x.elem),
+        Type::Group(x) => rust_to_ts(&x.elem),
+        Type::Tuple(x) => {
+            if x.elems.is_empty() {
+                return Ok("null".to_string());
+            }
+            let elems = x
+                .elems
+                .iter()
+                .map(rust_to_ts)
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(format!("[{}]", elems.join(", ")))
+        }
+        Type::Array(x) => Ok(ts_array(rust_to_ts(&x.elem)?)),
+        Type::Reference(_) => Err(syn::Error::new(
+            ty.span(),
+            "references are not supported, use owned types instead",
+        )),
+        Type::Path(x) if x.qself.is_none() => {
+            let Some(segment) = x.path.segments.last() else {
+                return Err(syn::Error::new(ty.span(), "unsupported type"));
+            };
+            let args = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args
+                    .args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        GenericArgument::Type(ty) => Some(rust_to_ts(ty)),
+                        _ => None,
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?,
+                PathArguments::None => vec![],
+                PathArguments::Parenthesized(_) => {
+                    return Err(syn::Error::new(
+                        ty.span(),
+                        "function types are not supported",
+                    ));
+                }
+            };
+            let ident = segment.ident.to_string();
+            let typ = match (ident.as_str(), args.as_slice()) {
+                ("String" | "str" | "char", []) => "string".to_string(),
+                ("bool", []) => "boolean".to_string(),
+                (
+                    "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+                    | "i128" | "isize" | "f32" | "f64",
+                    [],
+                ) => "number".to_string(),
+                ("Value", []) => "unknown".to_string(),
+                ("Option", [inner]) => format!("{inner} | null"),
+                (
+                    "Vec" | "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet" | "IndexSet",
+                    [inner],
+                ) => ts_array(inner.clone()),
+                ("HashMap" | "BTreeMap" | "IndexMap", [k, v]) => format!("Record<{k}, {v}>"),
+                ("Box" | "Rc" | "Arc" | "Cow", [inner]) => inner.clone(),
+                ("Result" | "WxResult", _) => {
+                    return Err(syn::Error::new(
+                        ty.span(),
+                        "nested results are not supported",
+                    ));
+                }
+                (_, []) => ident,
+                (_, args) => format!("{ident}<{}>", args.join(", ")),
+            };
+            Ok(typ)
+        }
+        _ => Err(syn::Error::new(ty.span(), "unsupported type")),
+    }
+}
+
+fn ts_array(inner: String) -> String {
+    if inner.contains('|') {
+        format!("({inner})[]")
+    } else {
+        format!("{inner}[]")
+    }
+}
+
+/// Get `T` from `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    single_type_arg(ty, "Option")
+}
+
+/// Get `T` from `WxResult<T>`
+fn wx_result_inner(ty: &Type) -> Option<&Type> {
+    single_type_arg(ty, "WxResult")
+}
+
+fn single_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let Type::Path(x) = ty else {
+        return None;
+    };
+    let segment = x.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    if args.args.len() != 1 {
+        return None;
+    }
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(x) if x.elems.is_empty())
+}
+
+/// Render the type as Rust source code
+fn type_to_string(ty: &Type) -> String {
+    let mut out = ty.to_token_stream().to_string();
+    for (from, to) in [
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ::", "::"),
+        (":: ", "::"),
+        (" ,", ","),
+        (" ;", ";"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+    ] {
+        out = out.replace(from, to);
+    }
+    out
+}
+
+/// Convert a snake_case identifier to camelCase
+fn to_camel_case(ident: &str) -> String {
+    let mut out = String::with_capacity(ident.len());
+    // keep leading underscores
+    let trimmed = ident.trim_start_matches('_');
+    out.push_str(&ident[..ident.len() - trimmed.len()]);
+    let mut upper = false;
+    for c in trimmed.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Quote the property name if it's not a valid identifier
+fn ts_property_name(name: &str) -> String {
+    let is_ident = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_ident {
+        name.to_string()
+    } else {
+        crate::emit::quoted(name)
+    }
+}
+
+/// Get the lines and columns of the span
+fn to_diag_span(span: Span) -> diag::Span {
+    let start = span.start();
+    let end = span.end();
+    diag::Span {
+        line_start: start.line,
+        column_start: start.column + 1,
+        line_end: end.line,
+        column_end: end.column + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the source as a Rust input, and return the interfaces, the declarations
+    /// and the messages of the diagnostics
+    fn parse(source: &str) -> (BTreeMap<String, ir::Interface>, ir::DeclFile, Vec<String>) {
+        let mut ctx = Context {
+            diagnostics: Some(vec![]),
+            ..Default::default()
+        };
+        let mut out = BTreeMap::new();
+        let decl = RustFileContext::try_new(&mut ctx, "src/api.rs", source.to_string())
+            .unwrap()
+            .parse_into(&mut out);
+        let messages = ctx
+            .diagnostics
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.message)
+            .collect();
+        (out, decl, messages)
+    }
+
+    /// Parse the source and return the first diagnostic
+    fn parse_error(source: &str) -> String {
+        let (_, _, messages) = parse(source);
+        assert_eq!(messages.len(), 1, "{messages:?}");
+        messages.into_iter().next().unwrap_or_default()
+    }
+
+    fn to_ts(ty: &str) -> Result<String, String> {
+        let ty: Type = syn::parse_str(ty).unwrap();
+        rust_to_ts(&ty).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_rust_to_ts() {
+        assert_eq!(to_ts("String"), Ok("string".to_string()));
+        assert_eq!(to_ts("u64"), Ok("number".to_string()));
+        assert_eq!(to_ts("bool"), Ok("boolean".to_string()));
+        assert_eq!(to_ts("serde_json::Value"), Ok("unknown".to_string()));
+        assert_eq!(to_ts("()"), Ok("null".to_string()));
+        assert_eq!(to_ts("(String, f64)"), Ok("[string, number]".to_string()));
+        assert_eq!(
+            to_ts("Vec<Option<u8>>"),
+            Ok("(number | null)[]".to_string())
+        );
+        assert_eq!(to_ts("[i32; 4]"), Ok("number[]".to_string()));
+        assert_eq!(
+            to_ts("std::collections::BTreeMap<String, Vec<Point>>"),
+            Ok("Record<string, Point[]>".to_string())
+        );
+        assert_eq!(to_ts("Arc<Box<str>>"), Ok("string".to_string()));
+        assert_eq!(to_ts("Page<Point>"), Ok("Page<Point>".to_string()));
+    }
+
+    #[test]
+    fn test_rust_to_ts_errors() {
+        assert!(
+            to_ts("&str")
+                .unwrap_err()
+                .contains("references are not supported")
+        );
+        assert!(
+            to_ts("Box<dyn Fn(u32)>")
+                .unwrap_err()
+                .contains("unsupported type")
+        );
+        assert!(
+            to_ts("Vec<Result<u32, String>>")
+                .unwrap_err()
+                .contains("nested results")
+        );
+    }
+
+    #[test]
+    fn test_parse_trait() {
+        let source = r#"
+/// Interface doc
+#[workex::interface]
+pub trait Files {
+    /// Read the file
+    ///
+    /// @timeout 5s
+    async fn read_file(&self, r#type: String, max_size: Option<u32>) -> WxResult<Vec<u8>>;
+    #[deprecated(since = "0.2", note = "use read_file")]
+    async fn read(&self, path: String) -> WxResult<()>;
+}
+
+#[workex_runtime::interface]
+trait Other {
+    async fn run(&self) -> WxResult<()>;
+}
+
+// not an interface
+trait Ignored {
+    fn run(&self);
+}
+"#;
+        let (out, decl, messages) = parse(source);
+        assert_eq!(messages, Vec::<String>::new());
+        assert_eq!(decl.filename, "api.ts");
+        assert_eq!(decl.interfaces, ["Files", "Other"]);
+        assert_eq!(out.keys().collect::<Vec<_>>(), ["Files", "Other"]);
+
+        let files = &out["Files"];
+        assert_eq!(files.comment.lines, ["Interface doc"]);
+        // sorted by name
+        let read = &files.functions[0];
+        assert_eq!(read.name, "read");
+        assert_eq!(read.retty_ann, "<void>");
+        assert_eq!(read.comment.deprecated(), Some("use read_file".to_string()));
+        assert!(read.comment.lines.iter().any(|x| x == "@since 0.2"));
+
+        let read_file = &files.functions[1];
+        assert_eq!(read_file.name, "readFile");
+        assert_eq!(read_file.rust_name.as_deref(), Some("read_file"));
+        assert_eq!(read_file.retty_ann, "<number[]>");
+        assert_eq!(read_file.rust_retty.as_deref(), Some("Vec<u8>"));
+        assert_eq!(read_file.timeout, Some(5000));
+        let args = read_file
+            .args
+            .iter()
+            .map(|x| (x.ident.as_str(), x.optional, x.typ.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            [("type", false, "string"), ("maxSize", true, "number")]
+        );
+        assert_eq!(read_file.args[1].rust_typ.as_deref(), Some("Option<u32>"));
+    }
+
+    #[test]
+    fn test_parse_serde_types() {
+        let source = r#"
+/// A point
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Point {
+    pos_x: f64,
+    #[serde(rename = "y-pos")]
+    pos_y: f64,
+    label: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(skip)]
+    cache: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct Id(u64);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Kind {
+    FileType,
+    #[serde(rename = "dir")]
+    Directory,
+}
+
+// not serializable
+pub struct Internal(u32);
+"#;
+        let (_, decl, messages) = parse(source);
+        assert_eq!(messages, Vec::<String>::new());
+        let names = decl
+            .types
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Point", "Id", "Kind"]);
+
+        let ir::TypeDeclKind::Interface(fields) = &decl.types[0].kind else {
+            panic!("expected an interface");
+        };
+        assert_eq!(decl.types[0].comment.lines, ["A point"]);
+        let fields = fields
+            .iter()
+            .map(|x| (x.arg.ident.as_str(), x.arg.optional, x.arg.typ.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("posX", false, "number"),
+                ("\"y-pos\"", false, "number"),
+                ("label", true, "string | null"),
+                ("tags", true, "string[]"),
+            ]
+        );
+        assert!(matches!(&decl.types[1].kind, ir::TypeDeclKind::Alias(x) if x == "number"));
+        assert!(
+            matches!(&decl.types[2].kind, ir::TypeDeclKind::Alias(x) if x == "\"FILE_TYPE\" | \"dir\"")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error =
+            parse_error("#[workex::interface] trait A<T> { async fn f(&self) -> WxResult<T>; }");
+        assert!(error.contains("trait type parameters are not supported"));
+        let error = parse_error(
+            "#[workex::interface] trait A: Send { async fn f(&self) -> WxResult<()>; }",
+        );
+        assert!(error.contains("supertraits are not supported"));
+        let error = parse_error("#[workex::interface] trait A {}");
+        assert!(error.contains("empty interfaces are not allowed"));
+        let error = parse_error("#[workex::interface] trait A { fn f(&self) -> WxResult<()>; }");
+        assert!(error.contains("must be async"));
+        let error =
+            parse_error("#[workex::interface] trait A { async fn f(self) -> WxResult<()>; }");
+        assert!(error.contains("the first argument must be `&self`"));
+        let error = parse_error("#[workex::interface] trait A { async fn f(&self) -> u32; }");
+        assert!(error.contains("return type must be WxResult<T>"));
+        let error = parse_error(
+            "#[workex::interface] trait A { async fn f(&self, x: &str) -> WxResult<()>; }",
+        );
+        assert!(error.contains("references are not supported"));
+        let error = parse_error(
+            "#[workex::interface] trait A { async fn f(&self, (a, b): (u32, u32)) -> WxResult<()>; }",
+        );
+        assert!(error.contains("patterns are not supported"));
+        let error = parse_error("#[workex::interface] trait A { type T; }");
+        assert!(error.contains("only functions are supported"));
+        let error = parse_error(
+            "#[workex::interface] trait A { async fn f(&self) -> WxResult<()>; async fn f(&self) -> WxResult<()>; }",
+        );
+        assert!(error.contains("duplicate function name in interface A: f"));
+        let error = parse_error("#[derive(Serialize)] struct A { #[serde(flatten)] b: B }");
+        assert!(error.contains("#[serde(flatten)] is not supported"));
+        let error = parse_error(
+            "#[derive(Serialize)] #[serde(rename_all = \"Title Case\")] struct A { b: u32 }",
+        );
+        assert!(error.contains("unknown rename_all rule: Title Case"));
+        let error = parse_error("#[derive(Serialize)] enum A { B(u32) }");
+        assert!(error.contains("only enums with unit variants are supported"));
+        let error = parse_error("#[derive(Serialize)] enum A {}");
+        assert!(error.contains("enums must have at least one variant"));
+        let error = parse_error("trait A {");
+        assert!(!error.is_empty());
+    }
+
+    #[test]
+    fn test_apply_rename_rule() {
+        let mut ctx = Context::default();
+        let mut file = RustFileContext::try_new(&mut ctx, "a.rs", String::new()).unwrap();
+        let mut rename =
+            |rule: &str, name: &str| file.apply_rename_rule(Span::call_site(), rule, name);
+        assert_eq!(
+            rename("camelCase", "file_path"),
+            Some("filePath".to_string())
+        );
+        assert_eq!(
+            rename("PascalCase", "file_path"),
+            Some("FilePath".to_string())
+        );
+        assert_eq!(
+            rename("snake_case", "FilePath"),
+            Some("file_path".to_string())
+        );
+        assert_eq!(
+            rename("kebab-case", "FilePath"),
+            Some("file-path".to_string())
+        );
+        assert_eq!(
+            rename("SCREAMING-KEBAB-CASE", "file_path"),
+            Some("FILE-PATH".to_string())
+        );
+        assert_eq!(
+            rename("lowercase", "FilePath"),
+            Some("filepath".to_string())
+        );
+        assert_eq!(
+            rename("UPPERCASE", "file_path"),
+            Some("FILEPATH".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_camel_case() {
+        assert_eq!(to_camel_case("read_file_async"), "readFileAsync");
+        assert_eq!(to_camel_case("_private_name"), "_privateName");
+        assert_eq!(to_camel_case("already"), "already");
+    }
+}
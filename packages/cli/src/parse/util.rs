@@ -1,15 +1,103 @@
-use swc_common::Span;
+use swc_common::errors::DiagnosticId;
+use swc_common::{Span, Spanned as _};
 
 use super::contexts::Context;
 
+use crate::diag;
+
 impl Context {
     /// Emit an error message, which will be shown to the user after formatted
-    /// by SWC
-    pub fn emit_error<T: std::fmt::Display>(&mut self, span: Span, msg: T) {
-        self.handler
-            .struct_span_err(span, &format!("[workex] {msg}"))
-            .emit();
+    /// by SWC, or reported as JSON with `--message-format json`
+    pub fn emit_error<T: std::fmt::Display>(&mut self, span: Span, code: diag::Code, msg: T) {
+        self.emit_error_with_fix(span, code, msg, None);
+    }
+
+    /// Emit an error message with a suggested edit that fixes it,
+    /// as `(span, title, replacement)`
+    pub fn emit_error_with_fix<T: std::fmt::Display>(
+        &mut self,
+        span: Span,
+        code: diag::Code,
+        msg: T,
+        fix: Option<(Span, &str, String)>,
+    ) {
+        self.errors += 1;
+        if self.diagnostics.is_none() && !diag::is_json() {
+            let msg = format!("[workex] {msg}");
+            let mut builder = self.handler.struct_span_err_with_code(
+                span,
+                &msg,
+                DiagnosticId::Error(code.as_str().to_string()),
+            );
+            if let Some((fix_span, title, replacement)) = fix {
+                builder.span_suggestion(fix_span, title, replacement);
+            }
+            builder.emit();
+            diag::count(diag::Severity::Error);
+            return;
+        }
+        let file = self.source_map.span_to_filename(span).to_string();
+        let mut diagnostic = diag::Diagnostic::new(code, msg.to_string())
+            .with_file(file)
+            .with_span(Some(self.to_diag_span(span)));
+        if let Some((fix_span, title, replacement)) = fix {
+            diagnostic = diagnostic.with_fix(diag::Fix {
+                title: title.to_string(),
+                span: self.to_diag_span(fix_span),
+                replacement,
+            });
+        }
+        self.report(diagnostic);
+    }
+
+    /// Emit an error from the TypeScript parser
+    pub fn emit_syntax_error(&mut self, error: swc_ecma_parser::error::Error) {
         self.errors += 1;
+        if self.diagnostics.is_none() && !diag::is_json() {
+            error.into_diagnostic(&self.handler).emit();
+            diag::count(diag::Severity::Error);
+            return;
+        }
+        let span = error.span();
+        let file = self.source_map.span_to_filename(span).to_string();
+        self.report(
+            diag::Diagnostic::new(diag::Code::Syntax, error.kind().msg())
+                .with_file(file)
+                .with_span(Some(self.to_diag_span(span))),
+        );
+    }
+
+    /// Report the diagnostic, or collect it if the diagnostics are being collected
+    pub fn report(&mut self, diagnostic: diag::Diagnostic) {
+        match &mut self.diagnostics {
+            Some(diagnostics) => diagnostics.push(diagnostic),
+            None => diag::report(diagnostic),
+        }
+    }
+
+    /// Get the lines and columns of the span
+    pub fn to_diag_span(&self, span: Span) -> diag::Span {
+        let start = self.source_map.lookup_char_pos(span.lo);
+        let end = self.source_map.lookup_char_pos(span.hi);
+        diag::Span {
+            line_start: start.line,
+            column_start: self.char_column(span.lo),
+            line_end: end.line,
+            column_end: self.char_column(span.hi),
+        }
+    }
+
+    /// Get the 1-based column of the position in characters. The column from SWC
+    /// counts the characters outside of the BMP as 2, like UTF-16, while the columns
+    /// from the Rust parser are in characters
+    fn char_column(&self, pos: swc_common::BytePos) -> usize {
+        let file = self.source_map.lookup_byte_offset(pos);
+        let before = &file.sf.src[..file.pos.0 as usize];
+        let line = match before.rfind('\n') {
+            Some(i) => &before[i + 1..],
+            None => before,
+        };
+        line.chars().count() + 1
     }
     /// Extract the raw source code as String
     pub fn raw_source(&mut self, span: Span) -> Option<String> {
@@ -19,7 +107,11 @@ impl Context {
         match result {
             Ok(s) => Some(s),
             Err(_) => {
-                self.emit_error(span, "failed to extract source code from span");
+                self.emit_error(
+                    span,
+                    diag::Code::InvalidInput,
+                    "failed to extract source code from span",
+                );
                 None
             }
         }
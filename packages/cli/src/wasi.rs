@@ -0,0 +1,98 @@
+//! Entry point for WASI, where the CLI setup from `cu` is not available.
+//!
+//! The flags and the log messages are kept the same as the native binary,
+//! without the colors.
+
+use std::process::ExitCode;
+
+use clap::Parser;
+
+/// Same as the verbosity flags in `cu::cli::Flags`
+#[derive(Debug, Default, Clone, PartialEq, clap::Args)]
+pub struct Flags {
+    /// Verbose. More -v makes it more verbose (opposite of --quiet)
+    #[clap(short = 'v', long, action(clap::ArgAction::Count))]
+    pub verbose: u8,
+    /// Quiet. More -q makes it more quiet (opposite of --verbose)
+    #[clap(short = 'q', long, action(clap::ArgAction::Count))]
+    pub quiet: u8,
+}
+
+impl Flags {
+    fn level_filter(&self) -> log::LevelFilter {
+        match (self.verbose as i8 - self.quiet as i8).clamp(-2, 2) {
+            -2 => log::LevelFilter::Off,
+            -1 => log::LevelFilter::Error,
+            0 => log::LevelFilter::Info,
+            1 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Parse the args, set up the logging and run the main function, like `#[cu::cli]`
+pub fn run<T: Parser>(
+    preprocess: impl FnOnce(&mut T),
+    flags: impl FnOnce(&T) -> &Flags,
+    main: impl FnOnce(T) -> cu::Result<()>,
+) -> ExitCode {
+    let mut args = T::parse();
+    preprocess(&mut args);
+    log::set_max_level(flags(&args).level_filter());
+    // only fails if a logger is already set
+    let _ = log::set_logger(&Logger);
+    if let Err(e) = set_current_dir() {
+        cu::error!("fatal: {e:?}");
+        return ExitCode::FAILURE;
+    }
+    match main(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            cu::error!("fatal: {e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Change to the current directory of the launcher, so the relative paths and the
+/// lookup of `.editorconfig` in the parent directories are the same as the native binary.
+///
+/// The launcher mounts the root directory, but the process always starts in `/`
+fn set_current_dir() -> cu::Result<()> {
+    let Some(dir) = std::env::var_os("WORKEX_WASI_CWD") else {
+        cu::bail!("WORKEX_WASI_CWD is not set, run the CLI with the workex launcher");
+    };
+    if let Err(e) = std::env::set_current_dir(&dir) {
+        cu::bail!("failed to change to the current directory {dir:?}: {e}");
+    }
+    Ok(())
+}
+
+struct Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let prefix = match record.level() {
+            log::Level::Error => 'E',
+            log::Level::Warn => 'W',
+            log::Level::Info => 'I',
+            log::Level::Debug => 'D',
+            log::Level::Trace => 'T',
+        };
+        let message = record.args().to_string();
+        let mut lines = message.lines();
+        println!("{prefix}] {}", lines.next().unwrap_or_default());
+        for line in lines {
+            println!(" | {line}");
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,33 @@
+//! Check that the default style renders the same output as older versions of the CLI.
+//!
+//! The files in `tests/baseline/snapshot` are generated by the CLI before the style options
+//! were added, from `tests/baseline/api.ts` with `-p fixture -l Host,Worker`.
+
+use std::path::Path;
+
+#[test]
+fn test_default_style_matches_baseline() {
+    let options = workex::Options {
+        inputs: vec!["tests/baseline/api.ts".to_string()],
+        protocol: Some("fixture".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        no_editorconfig: true,
+        ..Default::default()
+    };
+    let package = workex::load(&options, &workex::FsReader).unwrap();
+    let output = workex::emit::render(&package).unwrap();
+    assert_eq!(output.files.len(), 7);
+    for (path, actual) in &output.files {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if name == ".gitignore" {
+            assert_eq!(actual, "# workex generated files\n*\n");
+            continue;
+        }
+        let expected = std::fs::read_to_string(Path::new("tests/baseline/snapshot").join(name))
+            .unwrap_or_else(|_| panic!("unexpected file: {}", path.display()));
+        assert!(
+            *actual == expected,
+            "{name} is different from the baseline:\n{actual}"
+        );
+    }
+}
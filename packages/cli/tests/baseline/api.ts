@@ -0,0 +1,26 @@
+import type { WxPromise } from "@pistonite/workex";
+import type { Data, Options } from "./types.ts";
+
+/**
+ * The host side
+ *
+ * It's used by the "worker"
+ */
+export interface Host {
+    /** Get the data at the path */
+    getData(path: string, options?: Options): WxPromise<Data | undefined>;
+    /**
+     * Ping with no arguments
+     */
+    ping(): WxPromise<void>;
+    setAll(items: Record<string, Data[]>, force: boolean, count: number): WxPromise<[string, number]>;
+}
+
+export interface Worker {
+    initialize(): WxPromise<boolean>;
+}
+
+/** Not linked with anything */
+export interface Lonely {
+    hello(name: string): WxPromise<string>;
+}
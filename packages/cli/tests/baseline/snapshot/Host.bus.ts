@@ -0,0 +1,31 @@
+/*
+ * This file is generated by the workex CLI Tool
+ *
+ * Please visit https://workex.pistonite.dev for more information
+ */
+
+import type { WxProtocolBindConfig } from "@pistonite/workex";
+import type { Host } from "../api.ts";
+import type { Worker } from "../api.ts";
+import { _wxRecverImpl } from "./Worker.ts";
+import { _wxSenderImpl } from "./Host.ts";
+
+/**
+ * Create a bind config for the Host interface, under the `fixture` protocol
+ *
+ * When used with a creator function in Workex, an implementation of Host will be returned
+ * to send remote calls to the other side. This side needs to provide an implementation for Worker
+ * to be used when the other side calls this side
+ *
+ * This function is generated by the workex CLI tool
+ */
+export const fixtureHost = (handler: Worker, resolve?: (_: Host) => (void | Promise<void>)): WxProtocolBindConfig<Host> => { return {
+    protocol: "fixture",
+    interfaces: ["Host", "Worker"],
+    recvHandler: _wxRecverImpl(handler),
+    bindSend: (sender) => {
+        const impl = new _wxSenderImpl(sender);
+        resolve?.(impl);
+        return impl;
+    },
+}};
\ No newline at end of file
@@ -0,0 +1,67 @@
+/*
+ * This file is generated by the workex CLI Tool
+ *
+ * Please visit https://workex.pistonite.dev for more information
+ */
+
+import type { Host } from "../api.ts";
+
+import type { WxPromise, WxBusRecvHandler, WxProtocolBoundSender } from "@pistonite/workex";
+import type { Data, Options } from "../types.ts";
+
+/*
+ * These generated implementations are used internally by other generated code.
+ * They should not be used directly!
+ */
+
+/**
+ * The host side
+ * 
+ * It's used by the "worker"
+ */
+export class _wxSenderImpl implements Host {
+    private sender: WxProtocolBoundSender
+
+    constructor(sender: WxProtocolBoundSender) {
+        this.sender = sender
+    }
+
+    /**
+     * Get the data at the path
+     */
+    public getData( path: string, options?: Options ): WxPromise<Data | undefined> {
+        return this.sender.send<Data | undefined>(16 /* Host.getData */, [ path, options ]);
+    }
+
+    /**
+     * Ping with no arguments
+     */
+    public ping( ): WxPromise<void> {
+        return this.sender.sendVoid(17 /* Host.ping */, [ ]);
+    }
+
+    public setAll( items: Record<string, Data[]>, force: boolean, count: number ): WxPromise<[string, number]> {
+        return this.sender.send<[string, number]>(18 /* Host.setAll */, [ items, force, count ]);
+    }
+}
+
+/**
+ * The host side
+ * 
+ * It's used by the "worker"
+ */
+export const _wxRecverImpl = (handler: Host): WxBusRecvHandler => {
+    return ((fId, args: any[]) => { switch (fId) {
+        case 16 /* Host.getData */: {
+            const [ a0, a1 ] = args;
+            return handler.getData( a0, a1 );
+        }
+        case 17 /* Host.ping */: {
+            return handler.ping();
+        }
+        case 18 /* Host.setAll */: {
+            const [ a0, a1, a2 ] = args;
+            return handler.setAll( a0, a1, a2 );
+        }
+    } return Promise.resolve({ err: { code: "UnknownFunction" } }); }) as WxBusRecvHandler;
+};
\ No newline at end of file
@@ -0,0 +1,42 @@
+/*
+ * This file is generated by the workex CLI Tool
+ *
+ * Please visit https://workex.pistonite.dev for more information
+ */
+
+import type { WxProtocolBindConfig } from "@pistonite/workex";
+import type { Lonely } from "../api.ts";
+import { _wxSenderImpl, _wxRecverImpl } from "./Lonely.ts";
+
+/**
+ * Create a bind config for the Lonely interface, under the `fixture` protocol
+ *
+ * When used with a creator function in Workex, if no arguments, or a `resolve` function is provided,
+ * the config will be for the sender side (i.e. the side that calls Lonely).
+ * Otherwise, the config will be for the receiver side, and an implementation of Lonely needs to be provided.
+ *
+ * This interface is not linked to another interface in the protocol. One side should provide an implementation, and the other
+ * side should call with no arguments or a `resolve` function to receive a caller.
+ *
+ * This function is generated by the workex CLI tool
+ */
+export function fixtureLonely(handler: Lonely): WxProtocolBindConfig<Record<string, never>>;
+export function fixtureLonely(resolve?: (_: Lonely) => (void | Promise<void>)): WxProtocolBindConfig<Lonely>;
+export function fixtureLonely(handlerOrResolve?: Lonely | ((_: Lonely) => (void | Promise<void>))): WxProtocolBindConfig<Record<string, never>> | WxProtocolBindConfig<Lonely> {
+    if (!handlerOrResolve || typeof handlerOrResolve === "function") { return {
+        protocol: "fixture",
+        interfaces: ["Lonely", "_wxStub"],
+        recvHandler: () => Promise.resolve({ err: { code: "UnexpectedStubCall" } }),
+        bindSend: (sender) => {
+            const impl = new _wxSenderImpl(sender);
+            handlerOrResolve?.(impl);
+            return impl;
+        },
+    };}
+    return {
+        protocol: "fixture",
+        interfaces: ["_wxStub", "Lonely"],
+        recvHandler: _wxRecverImpl(handlerOrResolve),
+        bindSend: () => ({})
+    };
+};
\ No newline at end of file
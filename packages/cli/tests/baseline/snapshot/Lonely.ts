@@ -0,0 +1,42 @@
+/*
+ * This file is generated by the workex CLI Tool
+ *
+ * Please visit https://workex.pistonite.dev for more information
+ */
+
+import type { Lonely } from "../api.ts";
+
+import type { WxPromise, WxBusRecvHandler, WxProtocolBoundSender } from "@pistonite/workex";
+import type { Data, Options } from "../types.ts";
+
+/*
+ * These generated implementations are used internally by other generated code.
+ * They should not be used directly!
+ */
+
+/**
+ * Not linked with anything
+ */
+export class _wxSenderImpl implements Lonely {
+    private sender: WxProtocolBoundSender
+
+    constructor(sender: WxProtocolBoundSender) {
+        this.sender = sender
+    }
+
+    public hello( name: string ): WxPromise<string> {
+        return this.sender.send<string>(19 /* Lonely.hello */, [ name ]);
+    }
+}
+
+/**
+ * Not linked with anything
+ */
+export const _wxRecverImpl = (handler: Lonely): WxBusRecvHandler => {
+    return ((fId, args: any[]) => { switch (fId) {
+        case 19 /* Lonely.hello */: {
+            const [ a0 ] = args;
+            return handler.hello( a0 );
+        }
+    } return Promise.resolve({ err: { code: "UnknownFunction" } }); }) as WxBusRecvHandler;
+};
\ No newline at end of file
@@ -0,0 +1,35 @@
+/*
+ * This file is generated by the workex CLI Tool
+ *
+ * Please visit https://workex.pistonite.dev for more information
+ */
+
+import type { Worker } from "../api.ts";
+
+import type { WxPromise, WxBusRecvHandler, WxProtocolBoundSender } from "@pistonite/workex";
+import type { Data, Options } from "../types.ts";
+
+/*
+ * These generated implementations are used internally by other generated code.
+ * They should not be used directly!
+ */
+
+export class _wxSenderImpl implements Worker {
+    private sender: WxProtocolBoundSender
+
+    constructor(sender: WxProtocolBoundSender) {
+        this.sender = sender
+    }
+
+    public initialize( ): WxPromise<boolean> {
+        return this.sender.send<boolean>(20 /* Worker.initialize */, [ ]);
+    }
+}
+
+export const _wxRecverImpl = (handler: Worker): WxBusRecvHandler => {
+    return ((fId, args: any[]) => { switch (fId) {
+        case 20 /* Worker.initialize */: {
+            return handler.initialize();
+        }
+    } return Promise.resolve({ err: { code: "UnknownFunction" } }); }) as WxBusRecvHandler;
+};
\ No newline at end of file
@@ -0,0 +1,2 @@
+export type Data = { x: number };
+export type Options = { y?: string };
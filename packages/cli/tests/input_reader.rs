@@ -0,0 +1,76 @@
+//! Check that the generator only reads the files through the `InputReader`, which is
+//! what the WASI build relies on
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const EDITORCONFIG: &str = "root = true\n[*]\nindent_style = tab\n";
+const HEADER: &str = "Source: {source}\nHash: {hash}\n";
+
+/// Render the baseline input in `dir`, with the `.editorconfig` and the header template
+/// next to it. Returns the generated files by the path relative to `dir`
+fn render(dir: &Path, reader: &dyn workex::InputReader) -> BTreeMap<String, String> {
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let options = workex::Options {
+        inputs: vec![path("api.ts")],
+        protocol: Some("fixture".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        header_file: Some(path("header.txt")),
+        index: true,
+        ..Default::default()
+    };
+    let package = workex::load(&options, reader).unwrap();
+    let output = workex::emit::render(&package).unwrap();
+    output
+        .files
+        .into_iter()
+        .map(|(path, content)| {
+            let path = path.strip_prefix(dir).unwrap().to_str().unwrap();
+            (path.replace('\\', "/"), content)
+        })
+        .collect()
+}
+
+/// The files in the input directory, by the name
+fn input_files() -> Vec<(&'static str, String)> {
+    let read =
+        |name: &str| std::fs::read_to_string(Path::new("tests/baseline").join(name)).unwrap();
+    vec![
+        ("api.ts", read("api.ts")),
+        ("types.ts", read("types.ts")),
+        (".editorconfig", EDITORCONFIG.to_string()),
+        ("header.txt", HEADER.to_string()),
+    ]
+}
+
+#[test]
+fn test_memory_reader_matches_fs_reader() {
+    let dir = std::env::temp_dir().join(format!("workex-input-fs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, content) in input_files() {
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+    let dir = dir.canonicalize().unwrap();
+    let expected = render(&dir, &workex::FsReader);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // the directory does not exist, so anything not read through the reader would fail
+    let dir = dir.with_file_name(format!("workex-input-memory-{}", std::process::id()));
+    assert!(!dir.exists());
+    let sources = input_files()
+        .into_iter()
+        .map(|(name, content)| (dir.join(name).to_str().unwrap().to_string(), content))
+        .collect();
+    let reader = workex::MemoryReader {
+        sources: &sources,
+        fallback: &workex::FsReader,
+    };
+    let actual = render(&dir, &reader);
+
+    assert_eq!(actual, expected);
+    // the header and the .editorconfig are used
+    let host = &actual["interfaces/Host.ts"];
+    assert!(host.starts_with("/*\n * Source: api.ts\n * Hash: "));
+    assert!(host.contains("\n\tprivate sender: WxProtocolBoundSender\n"));
+}
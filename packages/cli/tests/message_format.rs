@@ -0,0 +1,107 @@
+//! Check the output of the CLI with `--message-format json`
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde_json::{Value, json};
+
+/// Run the CLI in a new directory with the input file and the extra arguments,
+/// and return the JSON lines in stdout and if the CLI succeeded
+fn run(name: &str, source: &str, args: &[&str]) -> (Vec<Value>, bool) {
+    let dir = std::env::temp_dir().join(format!("workex-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("api.ts"), source).unwrap();
+    let output = Command::new(PathBuf::from(env!("CARGO_BIN_EXE_workex")))
+        .args(["api.ts", "-p", "test", "--message-format", "json"])
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {line}")))
+        .collect();
+    (lines, output.status.success())
+}
+
+#[test]
+fn test_json_warning() {
+    let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    /** @param nme the name */
+    hello(name: string): WxPromise<string>;
+}
+"#;
+    let (lines, success) = run("warning", source, &[]);
+    assert!(success);
+    assert_eq!(
+        lines,
+        [
+            json!({
+                "reason": "diagnostic",
+                "severity": "warning",
+                "code": "W0001",
+                "message": "Api.hello: @param nme does not match any argument",
+                "file": "api.ts",
+                "span": { "line_start": 5, "column_start": 5, "line_end": 5, "column_end": 10 },
+            }),
+            json!({ "reason": "summary", "success": true, "errors": 0, "warnings": 1 }),
+        ]
+    );
+}
+
+#[test]
+fn test_json_error() {
+    let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    get value(): number;
+}
+"#;
+    let (lines, success) = run("error", source, &[]);
+    assert!(!success);
+    assert_eq!(
+        lines,
+        [
+            json!({
+                "reason": "diagnostic",
+                "severity": "error",
+                "code": "E0002",
+                "message": "function: getter signature is not supported.",
+                "file": "api.ts",
+                "span": { "line_start": 4, "column_start": 5, "line_end": 4, "column_end": 25 },
+            }),
+            json!({ "reason": "summary", "success": false, "errors": 1, "warnings": 0 }),
+        ]
+    );
+}
+
+#[test]
+fn test_json_fatal() {
+    // the error that stops the CLI is reported without a location
+    // if nothing else is reported
+    let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface Api { hello(): WxPromise<void>; }
+"#;
+    let (lines, success) = run("fatal", source, &["-l", "Api,Missing"]);
+    assert!(!success);
+    assert_eq!(
+        lines,
+        [
+            json!({
+                "reason": "diagnostic",
+                "severity": "error",
+                "code": "E0100",
+                "message": "Interface not found: Missing",
+                "file": null,
+                "span": null,
+            }),
+            json!({ "reason": "summary", "success": false, "errors": 1, "warnings": 0 }),
+        ]
+    );
+}
@@ -0,0 +1,628 @@
+//! Check the code rendered by the emitters for the options
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The directory of the input file for the test
+fn input_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("workex-render-{name}-{}", std::process::id()))
+}
+
+/// Load the input file in a new directory with the options. Returns the package
+/// and the directory, which is deleted after the package is loaded
+fn load(name: &str, source: &str, options: workex::Options) -> (workex::ir::Package, PathBuf) {
+    let dir = input_dir(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("api.ts");
+    std::fs::write(&input, source).unwrap();
+    let options = workex::Options {
+        inputs: vec![input.to_str().unwrap().to_string()],
+        no_editorconfig: true,
+        ..options
+    };
+    let package = workex::load(&options, &workex::FsReader).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    (package, dir)
+}
+
+/// Render the input file with the options, and return the content
+/// of the generated files by the path relative to the input file
+fn render(name: &str, source: &str, options: workex::Options) -> BTreeMap<String, String> {
+    let (package, dir) = load(name, source, options);
+    let output = workex::emit::render(&package).unwrap();
+    output
+        .files
+        .into_iter()
+        .map(|(path, content)| {
+            let path = path.strip_prefix(&dir).unwrap().to_str().unwrap();
+            (path.replace('\\', "/"), content)
+        })
+        .collect()
+}
+
+const SOURCE: &str = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Host {
+    log(message: string): WxPromise<void>;
+}
+export interface Worker {
+    add(a: number, b: number): WxPromise<number>;
+}
+"#;
+
+#[test]
+fn test_index_protocols() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec![
+            "Host,Worker".to_string(),
+            "Host,Worker@other-1=oth".to_string(),
+        ],
+        index: true,
+        ..Default::default()
+    };
+    let files = render("index-protocols", SOURCE, options);
+    let index = &files["interfaces/index.ts"];
+    assert!(index.contains("export const PROTOCOL = \"test\";\n"));
+    assert!(index.contains("export const PROTOCOLS = [\"test\", \"other-1\"] as const;\n"));
+    assert!(index.contains("export { othHost, testHost } from \"./Host.bus.ts\";"));
+
+    // only the package protocol without links under other protocols
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        index: true,
+        ..Default::default()
+    };
+    let files = render("index-protocol", SOURCE, options);
+    let index = &files["interfaces/index.ts"];
+    assert!(index.contains("export const PROTOCOLS = [\"test\"] as const;\n"));
+}
+
+#[test]
+fn test_stream() {
+    let source = r#"import type { WxStream } from "@pistonite/workex";
+export interface Api {
+    search(query: string): WxStream<string>;
+}
+"#;
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("stream", source, options);
+    let api = &files["interfaces/Api.ts"];
+    assert!(api.contains(
+        "    public search( query: string ): WxStream<string> {\n        return this.sender.sendStream<string>(16 /* Api.search */, [ query ]);\n"
+    ));
+    assert!(api.contains("return Promise.resolve({ stream: handler.search( a0 ) });"));
+}
+
+#[test]
+fn test_callbacks() {
+    let source = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    /** @keepalive onEvent */
+    watch(path: string, onChange: (path: string) => void, onEvent?: (e: number) => WxPromise<boolean>): WxPromise<void>;
+}
+"#;
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("callbacks", source, options);
+    let api = &files["interfaces/Api.ts"];
+    assert!(api.contains(
+        "return this.sender.sendVoid(16 /* Api.watch */, [ path, onChange, onEvent ], { callbacks: [1, 2], keepAlive: [2] });"
+    ));
+    assert!(api.contains("return ((fId, args: any[], context) => { switch (fId) {"));
+    assert!(
+        api.contains("return handler.watch( a0, context.callback(a1), context.callback(a2) );")
+    );
+}
+
+#[test]
+fn test_abort_signal() {
+    let source = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    compute(n: number, signal?: AbortSignal): WxPromise<number>;
+}
+"#;
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("abort-signal", source, options);
+    let api = &files["interfaces/Api.ts"];
+    // the signal is not sent as an argument
+    assert!(api.contains(
+        "return this.sender.send<number>(16 /* Api.compute */, [ n ], { signal: signal });"
+    ));
+    assert!(api.contains("return handler.compute( a0, context.signal() );"));
+}
+
+const DEPRECATED: &str = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Api {
+    /**
+     * Say hello
+     *
+     * @deprecated use greet instead
+     * @since 0.2.0
+     */
+    hello(name: string): WxPromise<string>;
+    greet(name: string): WxPromise<string>;
+}
+"#;
+
+#[test]
+fn test_deprecated() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("deprecated", DEPRECATED, options);
+    let api = &files["interfaces/Api.ts"];
+    // the doc comment with the tag is kept on the sender method
+    assert!(api.contains(
+        "     * @deprecated use greet instead\n     * @since 0.2.0\n     */\n    public hello( name: string ): WxPromise<string> {"
+    ));
+    assert!(!api.contains("wxWarnDeprecatedCall"));
+
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        log_deprecated: true,
+        ..Default::default()
+    };
+    let files = render("log-deprecated", DEPRECATED, options);
+    let api = &files["interfaces/Api.ts"];
+    assert!(api.contains("import { wxWarnDeprecatedCall } from \"@pistonite/workex\";"));
+    assert!(api.contains(
+        "        case 17 /* Api.hello */: {\n            wxWarnDeprecatedCall(\"Api.hello\");\n"
+    ));
+    assert_eq!(api.matches("wxWarnDeprecatedCall(").count(), 1);
+}
+
+#[test]
+fn test_deprecated_functions() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let (package, _) = load("summary", DEPRECATED, options);
+    let deprecated = package
+        .deprecated_functions()
+        .into_iter()
+        .map(|(interface, function, reason)| {
+            (interface.name.as_str(), function.name.as_str(), reason)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        deprecated,
+        [(
+            "Api",
+            "hello",
+            "use greet instead (since 0.2.0)".to_string()
+        )]
+    );
+}
+
+/// Check that the code is plain JavaScript without types
+fn assert_plain_js(path: &str, code: &str) {
+    use swc_common::{FileName, SourceMap, sync::Lrc};
+    use swc_ecma_parser::{EsSyntax, Parser, StringInput, Syntax, lexer::Lexer};
+
+    let source_map: Lrc<SourceMap> = Default::default();
+    let file = source_map.new_source_file(
+        Lrc::new(FileName::Custom(path.to_string())),
+        code.to_string(),
+    );
+    let lexer = Lexer::new(
+        Syntax::Es(EsSyntax::default()),
+        Default::default(),
+        StringInput::from(&*file),
+        None,
+    );
+    let mut parser = Parser::new_from(lexer);
+    if let Err(e) = parser.parse_module() {
+        panic!("{path} is not valid JavaScript: {:?}\n{code}", e.kind());
+    }
+}
+
+#[test]
+fn test_target_js() {
+    let source = SOURCE.replace(
+        "    add(a: number, b: number): WxPromise<number>;",
+        "    stat(path: string): WxPromise<Info>;",
+    );
+    let source = format!("import type {{ Info }} from \"./types.ts\";\n{source}");
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        target: workex::ir::Target::Js,
+        ..Default::default()
+    };
+    let files = render("target-js", &source, options);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [
+            "interfaces/.gitignore",
+            "interfaces/Host.bus.d.ts",
+            "interfaces/Host.bus.js",
+            "interfaces/Host.d.ts",
+            "interfaces/Host.js",
+            "interfaces/Worker.bus.d.ts",
+            "interfaces/Worker.bus.js",
+            "interfaces/Worker.d.ts",
+            "interfaces/Worker.js",
+        ]
+    );
+    for (path, code) in &files {
+        if path.ends_with(".js") {
+            assert_plain_js(path, code);
+            assert!(!code.contains(".ts\""), "{path} imports a .ts file");
+        }
+    }
+    let worker = &files["interfaces/Worker.js"];
+    assert!(worker.contains(
+        "    stat( path ) {\n        return this.sender.send(17 /* Worker.stat */, [ path ]);\n"
+    ));
+    let bus = &files["interfaces/Worker.bus.js"];
+    assert!(bus.contains("import { _wxRecverImpl } from \"./Host.js\";"));
+    assert!(bus.contains("export const testWorker = (handler, resolve) => { return {"));
+
+    // the imports in the declarations are rewritten to .js
+    let dts = &files["interfaces/Worker.d.ts"];
+    assert!(dts.contains("import type { Worker } from \"../api.js\";"));
+    assert!(dts.contains("import type { Info } from \"../types.js\";"));
+    assert!(dts.contains("export declare class _wxSenderImpl implements Worker {"));
+    assert!(dts.contains("    stat( path: string ): WxPromise<Info>;"));
+    let bus_dts = &files["interfaces/Worker.bus.d.ts"];
+    assert!(bus_dts.contains(
+        "export declare const testWorker: (handler: Host, resolve?: (_: Worker) => (void | Promise<void>)) => WxProtocolBindConfig<Worker>;"
+    ));
+}
+
+const ALL_KINDS: &str = r#"import type { WxPromise, WxStream, WxOneWay } from "@pistonite/workex";
+export interface Api {
+    add(a: number, b: number): WxPromise<number>;
+    log(message: string): WxPromise<void>;
+    search(query: string): WxStream<string>;
+    notify(event: string): WxOneWay;
+}
+"#;
+
+#[test]
+fn test_mock() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        mock: true,
+        ..Default::default()
+    };
+    let files = render("mock", ALL_KINDS, options);
+    let mock = &files["interfaces/Api.mock.ts"];
+    assert!(mock.contains("import { WxMock } from \"@pistonite/workex\";"));
+    assert!(mock.contains("export class ApiMock extends WxMock<Api> implements Api {"));
+    assert!(mock.contains(
+        "    public add( a: number, b: number ): WxPromise<number> {\n        return this.wxCall(\"add\", [ a, b ]);\n"
+    ));
+    assert!(mock.contains("        return this.wxCallVoid(\"log\", [ message ]);\n"));
+    assert!(mock.contains("        return this.wxStream(\"search\", [ query ]);\n"));
+    assert!(mock.contains(
+        "    public notify( event: string ): WxOneWay {\n        this.wxOneWay(\"notify\", [ event ]);\n"
+    ));
+
+    // not generated by default
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("no-mock", ALL_KINDS, options);
+    assert!(!files.contains_key("interfaces/Api.mock.ts"));
+}
+
+#[test]
+fn test_loopback() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec![
+            "Host,Worker".to_string(),
+            "Host,Worker@other=oth".to_string(),
+        ],
+        loopback: true,
+        ..Default::default()
+    };
+    let files = render("loopback", SOURCE, options);
+    let loopback = &files["interfaces/loopback.ts"];
+    assert!(loopback.contains("import { wxLoopback } from \"@pistonite/workex\";"));
+    assert!(loopback.contains("import { othHost, testHost } from \"./Host.bus.ts\";"));
+    assert!(loopback.contains("import { othWorker, testWorker } from \"./Worker.bus.ts\";"));
+    assert!(loopback.contains(
+        "export const testHostWorkerLoopback = async (handlers: { host: Host; worker: Worker }): Promise<WxResult<{ host: Host; worker: Worker; connection: WxCloseController }>> => {"
+    ));
+    // one function for each link
+    assert!(loopback.contains("export const othHostWorkerLoopback = async ("));
+    // each side receives the calls to the handler of the other side
+    assert!(loopback.contains("        creatorA({ api: testWorker(handlers.host) }),\n"));
+    assert!(loopback.contains("        creatorB({ api: testHost(handlers.worker) }),\n"));
+    assert!(loopback.contains(
+        "    return { val: { host: b.val.protocols.api, worker: a.val.protocols.api, connection: a.val.connection } };\n"
+    ));
+
+    // not generated by default
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        ..Default::default()
+    };
+    let files = render("no-loopback", SOURCE, options);
+    assert!(!files.contains_key("interfaces/loopback.ts"));
+}
+
+#[test]
+fn test_trace() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        trace: true,
+        ..Default::default()
+    };
+    let files = render("trace", SOURCE, options);
+    let bus = &files["interfaces/Host.bus.ts"];
+    assert!(bus.contains("import { wxTraceRecver, wxTraceSender } from \"@pistonite/workex\";"));
+    // the receiver traces the calls from the other side
+    assert!(bus.contains(
+        "    recvHandler: wxTraceRecver(_wxRecverImpl(handler), \"test\", \"Worker\", { add: 17 }),\n"
+    ));
+    assert!(bus.contains(
+        "        const impl = wxTraceSender(new _wxSenderImpl(sender), \"test\", \"Host\", { log: 16 });\n"
+    ));
+
+    // not traced by default
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        ..Default::default()
+    };
+    let files = render("no-trace", SOURCE, options);
+    let bus = &files["interfaces/Host.bus.ts"];
+    assert!(!bus.contains("wxTrace"));
+    assert!(bus.contains("    recvHandler: _wxRecverImpl(handler),\n"));
+}
+
+#[test]
+fn test_dispatch() {
+    let source = r#"import type { WxPromise } from "@pistonite/workex";
+export interface Host {
+    log(message: string): WxPromise<void>;
+}
+export interface Worker {
+    add(a: number, b: number): WxPromise<number>;
+    sub(a: number, b: number): WxPromise<number>;
+}
+"#;
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        dispatch: workex::ir::Dispatch::Table,
+        ..Default::default()
+    };
+    let files = render("dispatch-table", source, options);
+    let worker = &files["interfaces/Worker.ts"];
+    assert!(worker.contains("    const table: ((args: any[]) => unknown)[] = [\n"));
+    assert!(worker.contains(
+        "        /* 17 Worker.add */ (args) => {\n            return handler.add( args[0], args[1] );\n        },\n"
+    ));
+    assert!(worker.contains("        /* 18 Worker.sub */ (args) => {\n"));
+    // the table starts at the first function ID of the interface
+    assert!(worker.contains("        const f = table[fId - 17];\n"));
+    assert!(worker.contains(
+        "        return f ? f(args) : Promise.resolve({ err: { code: \"UnknownFunction\" } });\n"
+    ));
+    assert!(!worker.contains("switch (fId)"));
+    let host = &files["interfaces/Host.ts"];
+    assert!(host.contains("        const f = table[fId - 16];\n"));
+
+    // switch by default
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        ..Default::default()
+    };
+    let files = render("dispatch-switch", source, options);
+    let worker = &files["interfaces/Worker.ts"];
+    assert!(worker.contains("switch (fId)"));
+    assert!(worker.contains("        case 18 /* Worker.sub */: {\n"));
+    assert!(!worker.contains("const table"));
+
+    // only the interfaces with enough functions use the table
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        dispatch: workex::ir::Dispatch::Auto,
+        dispatch_threshold: 2,
+        ..Default::default()
+    };
+    let files = render("dispatch-auto", source, options);
+    assert!(files["interfaces/Worker.ts"].contains("const table"));
+    assert!(files["interfaces/Host.ts"].contains("switch (fId)"));
+}
+
+#[test]
+fn test_header() {
+    let header_file =
+        std::env::temp_dir().join(format!("workex-render-header-{}.txt", std::process::id()));
+    std::fs::write(&header_file, "Source: {source}\nHash: {hash}\n").unwrap();
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        header_file: Some(header_file.to_str().unwrap().to_string()),
+        license: Some("MIT".to_string()),
+        lint_directive: vec![
+            workex::ir::LintDirective::EslintDisable,
+            workex::ir::LintDirective::TsNocheck,
+        ],
+        ..Default::default()
+    };
+    let files = render("header", ALL_KINDS, options);
+    let _ = std::fs::remove_file(&header_file);
+    let code = &files["interfaces/Api.ts"];
+    let (header, body) = code.split_once("\n\n").unwrap();
+    let (header, hash) = header.split_once(" * Hash: ").unwrap();
+    assert_eq!(
+        header,
+        "// SPDX-License-Identifier: MIT\n/*\n * Source: api.ts\n"
+    );
+    assert_eq!(
+        hash,
+        format!(
+            "{:016x}\n */\n/* eslint-disable */\n// @ts-nocheck",
+            fnv1a(body)
+        )
+    );
+    assert!(body.starts_with("import type { Api } from \"../api.ts\";"));
+
+    // an empty template removes the comment
+    std::fs::write(&header_file, "").unwrap();
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        header_file: Some(header_file.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    let files = render("header-empty", ALL_KINDS, options);
+    let _ = std::fs::remove_file(&header_file);
+    assert!(files["interfaces/Api.ts"].starts_with("import type { Api } from \"../api.ts\";"));
+}
+
+/// 64-bit FNV-1a hash, used for the `{hash}` placeholder in the header
+fn fnv1a(content: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in content.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[test]
+fn test_index() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        index: true,
+        ..Default::default()
+    };
+    let files = render("index", SOURCE, options);
+    let index = &files["interfaces/index.ts"];
+    assert!(index.contains(
+        "export const FUNCTION_IDS = {\n    Host: { log: 16 },\n    Worker: { add: 17 },\n} as const;\n"
+    ));
+    assert!(index.contains("export { testHost } from \"./Host.bus.ts\";\n"));
+    assert!(index.contains("export { testWorker } from \"./Worker.bus.ts\";"));
+    // the internal implementations are not exported
+    assert!(!index.contains("_wx"));
+
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        index: true,
+        target: workex::ir::Target::Js,
+        ..Default::default()
+    };
+    let files = render("index-js", SOURCE, options);
+    assert!(!files.contains_key("interfaces/index.ts"));
+    let index = &files["interfaces/index.js"];
+    assert_plain_js("interfaces/index.js", index);
+    assert!(index.contains("export const PROTOCOL = \"test\";\n"));
+    assert!(index.contains(
+        "export const FUNCTION_IDS = {\n    Host: { log: 16 },\n    Worker: { add: 17 },\n};\n"
+    ));
+    assert!(index.contains("export { testHost } from \"./Host.bus.js\";\n"));
+    let dts = &files["interfaces/index.d.ts"];
+    assert!(dts.contains("export declare const PROTOCOL: \"test\";\n"));
+    assert!(dts.contains(
+        "export declare const FUNCTION_IDS: {\n    readonly Host: { readonly log: 16; };\n    readonly Worker: { readonly add: 17; };\n};\n"
+    ));
+    assert!(dts.contains("export { testWorker } from \"./Worker.bus.js\";"));
+
+    // not generated by default
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        ..Default::default()
+    };
+    let files = render("no-index", SOURCE, options);
+    assert!(!files.contains_key("interfaces/index.ts"));
+}
+
+#[test]
+fn test_single_file() {
+    let options = workex::Options {
+        protocol: Some("test".to_string()),
+        link: vec!["Host,Worker".to_string()],
+        single_file: Some(
+            input_dir("single-file")
+                .join("src/generated/wx.ts")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ),
+        ..Default::default()
+    };
+    let files = render("single-file", SOURCE, options);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        vec!["src/generated/wx.ts"]
+    );
+    let code = &files["src/generated/wx.ts"];
+    // the imports are combined and relative to the file
+    assert!(code.contains("import type { Host, Worker } from \"../../api.ts\";\n"));
+    assert!(code.contains("class _wxHostSenderImpl implements Host {"));
+    assert!(code.contains("const _wxWorkerRecverImpl = (handler: Worker): WxBusRecvHandler => {"));
+    // only the bind functions are exported
+    let exports = code
+        .lines()
+        .filter(|line| line.starts_with("export "))
+        .map(|line| line.split(" = ").next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exports,
+        vec!["export const testHost", "export const testWorker"]
+    );
+    assert!(code.contains("    recvHandler: _wxWorkerRecverImpl(handler),\n"));
+    assert!(code.contains("        const impl = new _wxHostSenderImpl(sender);\n"));
+}
+
+#[test]
+fn test_single_file_conflicts() {
+    #[derive(clap::Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        options: workex::Options,
+    }
+    let parse = |args: &[&str]| {
+        <Cli as clap::Parser>::try_parse_from(
+            ["workex", "api.ts", "-p", "test", "--single-file", "wx.ts"]
+                .iter()
+                .chain(args),
+        )
+    };
+    assert!(parse(&[]).is_ok());
+    for arg in ["--dir=out", "--mock", "--loopback", "--index"] {
+        let error = parse(&[arg]).err().unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    let dir = input_dir("single-file-js");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("api.ts");
+    std::fs::write(&input, SOURCE).unwrap();
+    let options = workex::Options {
+        inputs: vec![input.to_str().unwrap().to_string()],
+        protocol: Some("test".to_string()),
+        single_file: Some(dir.join("wx.js").to_str().unwrap().to_string()),
+        target: workex::ir::Target::Js,
+        no_editorconfig: true,
+        ..Default::default()
+    };
+    let error = workex::load(&options, &workex::FsReader).unwrap_err();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(format!("{error:?}").contains("--single-file is not supported with --target js"));
+}
@@ -0,0 +1,32 @@
+import type { WxPromise } from "@pistonite/workex";
+
+export type FileInfo = {
+    path: string;
+    size: number;
+};
+
+/**
+ * API implemented in Rust
+ */
+export interface Api {
+    /** Add the numbers */
+    add(a: number, b: number): WxPromise<number>;
+
+    /** Arguments with names that are keywords in Rust */
+    echo(self: string, type: string, crate: boolean): WxPromise<[string, string, boolean]>;
+
+    /**
+     * Optional arguments and collections
+     * @timeout 5s
+     */
+    stat(paths: string[], options?: Record<string, string>): WxPromise<FileInfo[]>;
+
+    /**
+     * Does not wait for the other side
+     * @oneway
+     */
+    log(message: string): WxPromise<void>;
+
+    /** The signal is ignored in Rust */
+    wait(ms: number, signal?: AbortSignal): WxPromise<void>;
+}
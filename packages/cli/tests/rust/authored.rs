@@ -0,0 +1,26 @@
+//! Interfaces authored in Rust, with the bindings in a child module
+
+use serde::{Deserialize, Serialize};
+use workex_runtime::WxResult;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+    pub label: Option<String>,
+}
+
+/// Helper implemented in Rust
+#[workex_runtime::interface]
+pub trait Helper {
+    /// Move the point
+    async fn translate(&self, point: Point, dx: f64, dy: f64) -> WxResult<Point>;
+
+    /// Get the labels of the points
+    async fn labels(&self, points: Vec<Point>) -> WxResult<Vec<String>>;
+}
+
+#[rustfmt::skip]
+#[path = "authored_bindings.rs"]
+pub mod bindings;
@@ -0,0 +1,80 @@
+//! This file is generated by the workex CLI Tool
+//!
+//! Please visit https://workex.pistonite.dev for more information
+
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[allow(unused_imports)]
+use super::*;
+
+/// The protocol identifier
+pub const PROTOCOL: &str = "test";
+
+pub use workex_runtime::{WxError, WxResult, WxSender};
+
+fn wx_to_value<T: Serialize>(value: T) -> WxResult<Value> {
+    serde_json::to_value(value).map_err(|e| WxError {
+        code: "Fail".to_string(),
+        message: Some(e.to_string()),
+    })
+}
+
+fn wx_from_value<T: for<'de> Deserialize<'de>>(value: Value) -> WxResult<T> {
+    serde_json::from_value(value).map_err(|e| WxError {
+        code: "InvalidRequestData".to_string(),
+        message: Some(e.to_string()),
+    })
+}
+
+fn wx_args(args: Value) -> WxResult<std::vec::IntoIter<Value>> {
+    match args {
+        Value::Array(args) => Ok(args.into_iter()),
+        _ => Err(WxError::new("InvalidRequestData")),
+    }
+}
+
+/// Client for calling Helper on the other side
+pub struct HelperClient<S: WxSender> {
+    sender: S,
+}
+
+impl<S: WxSender> HelperClient<S> {
+    pub fn new(sender: S) -> Self {
+        Self { sender }
+    }
+}
+
+impl<S: WxSender> Helper for HelperClient<S> {
+    async fn labels(&self, points: Vec<Point>) -> WxResult<Vec<String>> {
+        let args = Value::Array(vec![wx_to_value(points)?]);
+        wx_from_value(self.sender.send(16 /* Helper.labels */, args).await?)
+    }
+
+    async fn translate(&self, point: Point, dx: f64, dy: f64) -> WxResult<Point> {
+        let args = Value::Array(vec![wx_to_value(point)?, wx_to_value(dx)?, wx_to_value(dy)?]);
+        wx_from_value(self.sender.send(17 /* Helper.translate */, args).await?)
+    }
+}
+
+/// Dispatch an incoming call to the Helper implementation
+///
+/// The returned value should be sent back to the caller as the return value
+pub async fn dispatch_helper<H: Helper>(handler: &H, fid: u32, args: Value) -> WxResult<Value> {
+    let mut args = wx_args(args)?;
+    match fid {
+        16 /* Helper.labels */ => {
+            let a0: Vec<Point> = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.labels(a0).await?)
+        }
+        17 /* Helper.translate */ => {
+            let a0: Point = wx_from_value(args.next().unwrap_or_default())?;
+            let a1: f64 = wx_from_value(args.next().unwrap_or_default())?;
+            let a2: f64 = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.translate(a0, a1, a2).await?)
+        }
+        _ => Err(WxError::new("UnknownFunction")),
+    }
+}
\ No newline at end of file
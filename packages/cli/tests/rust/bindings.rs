@@ -0,0 +1,162 @@
+//! This file is generated by the workex CLI Tool
+//!
+//! Please visit https://workex.pistonite.dev for more information
+
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The protocol identifier
+pub const PROTOCOL: &str = "test";
+
+/// Workex error object, containing an error code and optionally a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WxError {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl WxError {
+    pub fn new(code: impl Into<String>) -> Self {
+        Self { code: code.into(), message: None }
+    }
+}
+
+pub type WxResult<T> = Result<T, WxError>;
+
+/// Sender used by generated clients to execute remote calls
+///
+/// The arguments are sent as an array, and the return value is the `val` of the result
+pub trait WxSender {
+    fn send(&self, fid: u32, args: Value) -> impl std::future::Future<Output = WxResult<Value>>;
+
+    /// Same as `send`, but with a timeout for this call instead of the default
+    fn send_with_timeout(&self, fid: u32, args: Value, timeout: std::time::Duration) -> impl std::future::Future<Output = WxResult<Value>> {
+        let _ = timeout;
+        self.send(fid, args)
+    }
+
+    /// Send a call without waiting for the other side. The other side never replies
+    ///
+    /// By default, this is sent as a normal call and the return value is ignored
+    fn send_one_way(&self, fid: u32, args: Value) -> impl std::future::Future<Output = WxResult<()>> {
+        let result = self.send(fid, args);
+        async move { result.await.map(|_| ()) }
+    }
+}
+
+fn wx_to_value<T: Serialize>(value: T) -> WxResult<Value> {
+    serde_json::to_value(value).map_err(|e| WxError {
+        code: "Fail".to_string(),
+        message: Some(e.to_string()),
+    })
+}
+
+fn wx_from_value<T: for<'de> Deserialize<'de>>(value: Value) -> WxResult<T> {
+    serde_json::from_value(value).map_err(|e| WxError {
+        code: "InvalidRequestData".to_string(),
+        message: Some(e.to_string()),
+    })
+}
+
+fn wx_args(args: Value) -> WxResult<std::vec::IntoIter<Value>> {
+    match args {
+        Value::Array(args) => Ok(args.into_iter()),
+        _ => Err(WxError::new("InvalidRequestData")),
+    }
+}
+
+/// API implemented in Rust
+#[allow(async_fn_in_trait)]
+pub trait Api {
+    /// Add the numbers
+    async fn add(&self, a: f64, b: f64) -> WxResult<f64>;
+
+    /// Arguments with names that are keywords in Rust
+    async fn echo(&self, self_: String, r#type: String, crate_: bool) -> WxResult<(String, String, bool)>;
+
+    /// Does not wait for the other side
+    /// @oneway
+    async fn log(&self, message: String) -> WxResult<()>;
+
+    /// Optional arguments and collections
+    /// @timeout 5s
+    async fn stat(&self, paths: Vec<String>, options: Option<std::collections::HashMap<String, String>>) -> WxResult<Vec<crate::FileInfo>>;
+
+    /// The signal is ignored in Rust
+    async fn wait(&self, ms: f64) -> WxResult<()>;
+}
+
+/// Client for calling Api on the other side
+pub struct ApiClient<S: WxSender> {
+    sender: S,
+}
+
+impl<S: WxSender> ApiClient<S> {
+    pub fn new(sender: S) -> Self {
+        Self { sender }
+    }
+}
+
+impl<S: WxSender> Api for ApiClient<S> {
+    async fn add(&self, a: f64, b: f64) -> WxResult<f64> {
+        let args = Value::Array(vec![wx_to_value(a)?, wx_to_value(b)?]);
+        wx_from_value(self.sender.send(16 /* Api.add */, args).await?)
+    }
+
+    async fn echo(&self, self_: String, r#type: String, crate_: bool) -> WxResult<(String, String, bool)> {
+        let args = Value::Array(vec![wx_to_value(self_)?, wx_to_value(r#type)?, wx_to_value(crate_)?]);
+        wx_from_value(self.sender.send(17 /* Api.echo */, args).await?)
+    }
+
+    async fn log(&self, message: String) -> WxResult<()> {
+        let args = Value::Array(vec![wx_to_value(message)?]);
+        self.sender.send_one_way(18 /* Api.log */, args).await
+    }
+
+    async fn stat(&self, paths: Vec<String>, options: Option<std::collections::HashMap<String, String>>) -> WxResult<Vec<crate::FileInfo>> {
+        let args = Value::Array(vec![wx_to_value(paths)?, wx_to_value(options)?]);
+        wx_from_value(self.sender.send_with_timeout(19 /* Api.stat */, args, std::time::Duration::from_millis(5000)).await?)
+    }
+
+    async fn wait(&self, ms: f64) -> WxResult<()> {
+        let args = Value::Array(vec![wx_to_value(ms)?]);
+        wx_from_value(self.sender.send(20 /* Api.wait */, args).await?)
+    }
+}
+
+/// Dispatch an incoming call to the Api implementation
+///
+/// The returned value should be sent back to the caller as the return value
+pub async fn dispatch_api<H: Api>(handler: &H, fid: u32, args: Value) -> WxResult<Value> {
+    let mut args = wx_args(args)?;
+    match fid {
+        16 /* Api.add */ => {
+            let a0: f64 = wx_from_value(args.next().unwrap_or_default())?;
+            let a1: f64 = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.add(a0, a1).await?)
+        }
+        17 /* Api.echo */ => {
+            let a0: String = wx_from_value(args.next().unwrap_or_default())?;
+            let a1: String = wx_from_value(args.next().unwrap_or_default())?;
+            let a2: bool = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.echo(a0, a1, a2).await?)
+        }
+        18 /* Api.log */ => {
+            let a0: String = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.log(a0).await?)
+        }
+        19 /* Api.stat */ => {
+            let a0: Vec<String> = wx_from_value(args.next().unwrap_or_default())?;
+            let a1: Option<std::collections::HashMap<String, String>> = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.stat(a0, a1).await?)
+        }
+        20 /* Api.wait */ => {
+            let a0: f64 = wx_from_value(args.next().unwrap_or_default())?;
+            wx_to_value(handler.wait(a0).await?)
+        }
+        _ => Err(WxError::new("UnknownFunction")),
+    }
+}
\ No newline at end of file
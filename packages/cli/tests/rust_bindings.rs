@@ -0,0 +1,265 @@
+//! Check that the generated Rust bindings compile and work with the runtime.
+//!
+//! The bindings are generated into `tests/rust/` and checked in, so they are compiled
+//! as modules of this test, which are not formatted. Run the tests with
+//! `WORKEX_UPDATE_SNAPSHOTS=1` to update them.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use workex_runtime::{BusBuilder, ProtocolBinding};
+
+#[rustfmt::skip]
+#[allow(clippy::all)]
+#[path = "rust/bindings.rs"]
+mod bindings;
+
+#[rustfmt::skip]
+#[allow(clippy::all)]
+#[path = "rust/runtime_bindings.rs"]
+mod runtime_bindings;
+
+#[rustfmt::skip]
+#[allow(clippy::all)]
+#[path = "rust/authored.rs"]
+mod authored;
+
+/// Mapped from `FileInfo` in `tests/rust/api.ts`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileInfo {
+    path: String,
+    size: f64,
+}
+
+/// Generate the Rust bindings for the input, and compare them with the checked-in file
+fn check_bindings(input: &str, rust: &str, rust_runtime: Option<&str>) {
+    let options = workex::Options {
+        inputs: vec![input.to_string()],
+        protocol: Some("test".to_string()),
+        rust: Some(rust.to_string()),
+        rust_type: vec!["FileInfo=crate::FileInfo".to_string()],
+        rust_runtime: rust_runtime.map(|x| x.to_string()),
+        no_editorconfig: true,
+        ..Default::default()
+    };
+    let package = workex::load(&options, &workex::FsReader).unwrap();
+    let output = workex::emit::render(&package).unwrap();
+    let actual = &output.files[std::path::Path::new(rust)];
+    if std::env::var_os("WORKEX_UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(rust, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(rust).unwrap();
+    assert!(
+        *actual == expected,
+        "{rust} is outdated, run the tests with WORKEX_UPDATE_SNAPSHOTS=1 to update it"
+    );
+}
+
+#[test]
+fn test_bindings_up_to_date() {
+    check_bindings("tests/rust/api.ts", "tests/rust/bindings.rs", None);
+    check_bindings(
+        "tests/rust/api.ts",
+        "tests/rust/runtime_bindings.rs",
+        Some("workex_runtime"),
+    );
+    check_bindings(
+        "tests/rust/authored.rs",
+        "tests/rust/authored_bindings.rs",
+        Some("workex_runtime"),
+    );
+}
+
+#[test]
+fn test_rust_inputs_require_runtime() {
+    let options = workex::Options {
+        inputs: vec!["tests/rust/authored.rs".to_string()],
+        protocol: Some("test".to_string()),
+        rust: Some("tests/rust/authored_bindings.rs".to_string()),
+        no_editorconfig: true,
+        ..Default::default()
+    };
+    let error = workex::load(&options, &workex::FsReader).unwrap_err();
+    assert!(error.to_string().contains("--rust-runtime is required"));
+}
+
+#[test]
+fn test_map_is_rejected() {
+    let source = r#"
+import type { WxPromise } from "@pistonite/workex";
+export interface Api { get(): WxPromise<Map<string, number>>; }
+"#;
+    let sources = BTreeMap::from([("tests/rust/map.ts".to_string(), source.to_string())]);
+    let reader = workex::MemoryReader {
+        sources: &sources,
+        fallback: &workex::FsReader,
+    };
+    let options = workex::Options {
+        inputs: vec!["tests/rust/map.ts".to_string()],
+        protocol: Some("test".to_string()),
+        rust: Some("tests/rust/map.rs".to_string()),
+        no_editorconfig: true,
+        ..Default::default()
+    };
+    let package = workex::load(&options, &reader).unwrap();
+    let error = workex::emit::render(&package).unwrap_err();
+    assert!(format!("{error:#}").contains("`Map<string, number>` cannot be sent to Rust"));
+}
+
+#[derive(Default)]
+struct ApiImpl {
+    logs: std::sync::Mutex<Vec<String>>,
+}
+
+/// Implement the `Api` trait in both modules, which use different `WxResult` types
+macro_rules! impl_api {
+    ($module:ident) => {
+        impl $module::Api for ApiImpl {
+            async fn add(&self, a: f64, b: f64) -> $module::WxResult<f64> {
+                Ok(a + b)
+            }
+
+            async fn echo(
+                &self,
+                self_: String,
+                r#type: String,
+                crate_: bool,
+            ) -> $module::WxResult<(String, String, bool)> {
+                Ok((self_, r#type, crate_))
+            }
+
+            async fn stat(
+                &self,
+                paths: Vec<String>,
+                options: Option<std::collections::HashMap<String, String>>,
+            ) -> $module::WxResult<Vec<FileInfo>> {
+                let size = options.map(|x| x.len()).unwrap_or_default() as f64;
+                Ok(paths
+                    .into_iter()
+                    .map(|path| FileInfo { path, size })
+                    .collect())
+            }
+
+            async fn log(&self, message: String) -> $module::WxResult<()> {
+                self.logs.lock().unwrap().push(message);
+                Ok(())
+            }
+
+            async fn wait(&self, ms: f64) -> $module::WxResult<()> {
+                tokio::time::sleep(Duration::from_millis(ms as u64)).await;
+                Ok(())
+            }
+        }
+    };
+}
+impl_api!(bindings);
+impl_api!(runtime_bindings);
+
+/// Sender that calls the dispatcher directly
+struct DirectSender(Arc<ApiImpl>);
+
+impl bindings::WxSender for DirectSender {
+    fn send(&self, fid: u32, args: Value) -> impl Future<Output = bindings::WxResult<Value>> {
+        bindings::dispatch_api(&*self.0, fid, args)
+    }
+}
+
+#[tokio::test]
+async fn test_standalone_round_trip() {
+    use bindings::Api as _;
+
+    let handler = Arc::new(ApiImpl::default());
+    let client = bindings::ApiClient::new(DirectSender(Arc::clone(&handler)));
+    assert_eq!(client.add(1.0, 2.0).await, Ok(3.0));
+    assert_eq!(
+        client.echo("a".to_string(), "b".to_string(), true).await,
+        Ok(("a".to_string(), "b".to_string(), true))
+    );
+    let options = std::collections::HashMap::from([("k".to_string(), "v".to_string())]);
+    assert_eq!(
+        client.stat(vec!["x".to_string()], Some(options)).await,
+        Ok(vec![FileInfo {
+            path: "x".to_string(),
+            size: 1.0
+        }])
+    );
+    // one-way calls are sent as normal calls by default
+    assert_eq!(client.log("hello".to_string()).await, Ok(()));
+    assert_eq!(*handler.logs.lock().unwrap(), ["hello"]);
+    assert_eq!(
+        bindings::dispatch_api(&ApiImpl::default(), 99, Value::Array(vec![])).await,
+        Err(bindings::WxError::new("UnknownFunction"))
+    );
+}
+
+#[tokio::test]
+async fn test_runtime_round_trip() {
+    use runtime_bindings::Api as _;
+
+    let (a, b) = workex_runtime::transport::channel();
+    let handler = Arc::new(ApiImpl::default());
+    let handler_ref = Arc::clone(&handler);
+    let passive = BusBuilder::passive()
+        .bind(ProtocolBinding::receiver(
+            "test",
+            "Api",
+            move |fid, args| {
+                let handler = Arc::clone(&handler_ref);
+                async move { runtime_bindings::dispatch_api(&*handler, fid, args).await }
+            },
+        ))
+        .connect(b);
+    let active = BusBuilder::active()
+        .bind(ProtocolBinding::sender("test", "Api"))
+        .connect(a);
+    let (active, _passive) = tokio::join!(active, passive);
+    let client = runtime_bindings::ApiClient::new(active.unwrap().sender("test").unwrap());
+
+    assert_eq!(client.add(1.0, 2.0).await, Ok(3.0));
+    assert_eq!(client.wait(1.0).await, Ok(()));
+    assert_eq!(client.log("hello".to_string()).await, Ok(()));
+    // the one-way call is not waited for
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(*handler.logs.lock().unwrap(), ["hello"]);
+}
+
+struct HelperImpl;
+
+impl authored::Helper for HelperImpl {
+    async fn translate(
+        &self,
+        point: authored::Point,
+        dx: f64,
+        dy: f64,
+    ) -> workex_runtime::WxResult<authored::Point> {
+        Ok(authored::Point {
+            x: point.x + dx,
+            y: point.y + dy,
+            ..point
+        })
+    }
+
+    async fn labels(&self, points: Vec<authored::Point>) -> workex_runtime::WxResult<Vec<String>> {
+        Ok(points.into_iter().filter_map(|x| x.label).collect())
+    }
+}
+
+#[tokio::test]
+async fn test_authored_dispatch() {
+    // the function IDs are in the order of the names
+    let point = serde_json::json!({ "x": 1.0, "y": 2.0, "label": "a" });
+    let args = serde_json::json!([[point, { "x": 0.0, "y": 0.0 }]]);
+    assert_eq!(
+        authored::bindings::dispatch_helper(&HelperImpl, 16, args).await,
+        Ok(serde_json::json!(["a"]))
+    );
+    let args = serde_json::json!([point, 1.0, 1.0]);
+    assert_eq!(
+        authored::bindings::dispatch_helper(&HelperImpl, 17, args).await,
+        Ok(serde_json::json!({ "x": 2.0, "y": 3.0, "label": "a" }))
+    );
+}
@@ -0,0 +1,21 @@
+//! Attribute macros for Workex. Use these through the `workex-runtime` crate.
+
+use proc_macro::TokenStream;
+
+/// Mark a trait as a Workex interface.
+///
+/// The workex CLI reads traits with this attribute from `.rs` input files,
+/// and generates the TypeScript declarations and bindings for them.
+/// The trait itself is kept as-is, except that the `async_fn_in_trait`
+/// lint is allowed, since all functions in the interface are `async`.
+#[proc_macro_attribute]
+pub fn interface(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return r##"compile_error!("#[workex::interface] does not take any arguments");"##
+            .parse()
+            .unwrap();
+    }
+    let mut out: TokenStream = "#[allow(async_fn_in_trait)]".parse().unwrap();
+    out.extend(item);
+    out
+}
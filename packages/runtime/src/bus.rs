@@ -0,0 +1,610 @@
+//! Bidirectional Unicall System (BUS)
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::end::End;
+use crate::transport::Transport;
+use crate::{
+    FUNC_CALLBACK, FUNC_CALLBACK_DISPOSE, FUNC_CANCEL, FUNC_HANDSHAKE, FUNC_PROTOCOL, FUNC_RETURN,
+    FUNC_RETURN_ERROR, FUNC_STREAM_CANCEL, FUNC_STREAM_CHUNK, FUNC_STREAM_END, FUNC_STREAM_ERROR,
+    FUNC_STREAM_PULL, ONE_WAY_MESSAGE_ID, PROTOCOL_MSG_AGREE, PROTOCOL_MSG_DISAGREE,
+    PROTOCOL_MSG_QUERY, WxError, WxMessage, WxResult, WxSender,
+};
+
+/// Future returned by a [`RecvHandler`]
+pub type RecvFuture = Pin<Box<dyn Future<Output = WxResult<Value>> + Send>>;
+
+/// Handler registered on the bus for handling RPC calls from the other side.
+///
+/// This is usually a wrapper around the `dispatch_*` function generated by the
+/// workex CLI with the `--rust` flag
+pub type RecvHandler = Arc<dyn Fn(u32, Value) -> RecvFuture + Send + Sync>;
+
+/// Interface name used for the unimplemented side of an unlinked interface
+const STUB_INTERFACE: &str = "_wxStub";
+
+/// Binding of a protocol on the bus, same as `WxProtocolBindConfig`
+/// in the TypeScript SDK
+pub struct ProtocolBinding {
+    protocol: String,
+    /// The interface called by this side, and the interface implemented by this side
+    interfaces: [String; 2],
+    handler: RecvHandler,
+}
+
+impl ProtocolBinding {
+    /// Bind a pair of linked interfaces. Calls to `send_interface` are sent
+    /// to the other side, and calls to `recv_interface` from the other side
+    /// are handled by the handler
+    pub fn new<F, Fut>(
+        protocol: impl Into<String>,
+        send_interface: impl Into<String>,
+        recv_interface: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(u32, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = WxResult<Value>> + Send + 'static,
+    {
+        Self {
+            protocol: protocol.into(),
+            interfaces: [send_interface.into(), recv_interface.into()],
+            handler: Arc::new(move |fid, args| Box::pin(handler(fid, args))),
+        }
+    }
+
+    /// Bind an unlinked interface on the side that calls it
+    pub fn sender(protocol: impl Into<String>, interface: impl Into<String>) -> Self {
+        Self {
+            protocol: protocol.into(),
+            interfaces: [interface.into(), STUB_INTERFACE.to_string()],
+            handler: Arc::new(|_, _| Box::pin(async { Err(WxError::new("UnexpectedStubCall")) })),
+        }
+    }
+
+    /// Bind an unlinked interface on the side that implements it
+    pub fn receiver<F, Fut>(
+        protocol: impl Into<String>,
+        interface: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(u32, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = WxResult<Value>> + Send + 'static,
+    {
+        Self::new(protocol, STUB_INTERFACE, interface, handler)
+    }
+}
+
+/// Builder for creating a [`Bus`] over a [`Transport`]
+pub struct BusBuilder {
+    is_active: bool,
+    timeout: Duration,
+    bindings: Vec<ProtocolBinding>,
+}
+
+impl BusBuilder {
+    /// Create a bus on the active side, which initiates the handshake and protocol query.
+    ///
+    /// In the TypeScript SDK, this is the side that uses `wxWorkerGlobal`,
+    /// `wxWindowOwner` or `wxWebSocket`
+    pub fn active() -> Self {
+        Self::new(true)
+    }
+
+    /// Create a bus on the passive side, which waits for the other side to initiate
+    /// the connection
+    pub fn passive() -> Self {
+        Self::new(false)
+    }
+
+    fn new(is_active: bool) -> Self {
+        Self {
+            is_active,
+            timeout: Duration::from_secs(60),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Set the timeout for initialization and messaging, default is 60s
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add a protocol binding to the bus
+    pub fn bind(mut self, binding: ProtocolBinding) -> Self {
+        self.bindings.push(binding);
+        self
+    }
+
+    /// Establish the connection over the transport and agree on the protocols.
+    ///
+    /// This must be called within a tokio runtime
+    pub async fn connect<T: Transport>(self, transport: T) -> WxResult<Bus> {
+        let mut protocols = BTreeSet::new();
+        let mut protocol_query = Vec::with_capacity(self.bindings.len());
+        let mut handlers = HashMap::with_capacity(self.bindings.len());
+        for binding in self.bindings {
+            let protocol = binding.protocol;
+            if !protocols.insert(protocol.clone()) {
+                return Err(WxError::with_message(
+                    "DuplicateProtocol",
+                    format!("Duplicate protocol: {protocol}"),
+                ));
+            }
+            // format query as PASSIVE->ACTIVE, where
+            // PASSIVE/ACTIVE are the side that implements the interface
+            let [send, recv] = binding.interfaces;
+            if self.is_active {
+                protocol_query.push(format!("{protocol}:{send}->{recv}"));
+            } else {
+                protocol_query.push(format!("{protocol}:{recv}->{send}"));
+            }
+            handlers.insert(protocol, binding.handler);
+        }
+        protocol_query.sort();
+        log::debug!("protocol query is {}", protocol_query.join(","));
+
+        let (end, mut inbox) = End::connect(transport, self.is_active, self.timeout).await?;
+
+        if self.is_active {
+            end.send(WxMessage::internal(
+                PROTOCOL_MSG_QUERY,
+                FUNC_PROTOCOL,
+                protocol_query.clone().into(),
+            ))?;
+        }
+
+        let agreement = async {
+            while let Some(message) = inbox.recv().await {
+                // ignore hello messages on established channel
+                if message.is_internal() && message.f == FUNC_HANDSHAKE {
+                    continue;
+                }
+                if !message.is_internal() || message.f != FUNC_PROTOCOL {
+                    log::warn!("bus received message before protocol agreement");
+                    continue;
+                }
+                let received: Vec<String> = serde_json::from_value(message.d).unwrap_or_default();
+                match message.m {
+                    PROTOCOL_MSG_QUERY => {
+                        let agree = received == protocol_query;
+                        log::debug!("replying to protocol query: agreed={agree}");
+                        end.send(WxMessage::internal(
+                            if agree {
+                                PROTOCOL_MSG_AGREE
+                            } else {
+                                PROTOCOL_MSG_DISAGREE
+                            },
+                            FUNC_PROTOCOL,
+                            protocol_query.clone().into(),
+                        ))?;
+                        if agree {
+                            return Ok(());
+                        }
+                    }
+                    PROTOCOL_MSG_AGREE => {
+                        log::debug!("received protocol agreement");
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+                let message = format!(
+                    "received: {}, expected: {}",
+                    received.join(", "),
+                    protocol_query.join(", ")
+                );
+                log::error!("disagreed on protocol: {message}");
+                return Err(WxError::with_message("ProtocolDisagree", message));
+            }
+            Err(WxError::new("Closed"))
+        };
+
+        let result = match tokio::time::timeout(self.timeout, agreement).await {
+            Ok(result) => result,
+            Err(_) => Err(WxError::new("Timeout")),
+        };
+        if let Err(e) = result {
+            log::error!("bus failed to agree on protocols, communication not established!");
+            end.close();
+            return Err(e);
+        }
+
+        let inner = Arc::new(BusInner {
+            end,
+            timeout: self.timeout,
+            pending: Mutex::new(HashMap::new()),
+            next_message_id: Mutex::new(100),
+            active: Mutex::new(HashMap::new()),
+        });
+        tokio::spawn(route_messages(Arc::clone(&inner), inbox, handlers));
+
+        Ok(Bus { inner, protocols })
+    }
+}
+
+/// A connection with protocols agreed with the other side
+#[derive(Clone)]
+pub struct Bus {
+    inner: Arc<BusInner>,
+    protocols: BTreeSet<String>,
+}
+
+impl Bus {
+    /// Get the sender to call the other side under the protocol.
+    ///
+    /// Returns `None` if the protocol is not bound on this bus
+    pub fn sender(&self, protocol: &str) -> Option<BoundSender> {
+        if !self.protocols.contains(protocol) {
+            return None;
+        }
+        Some(BoundSender {
+            inner: Arc::clone(&self.inner),
+            protocol: protocol.into(),
+        })
+    }
+
+    /// Close the connection
+    pub fn close(&self) {
+        self.inner.end.close();
+    }
+
+    /// Check if the connection is closed
+    pub fn is_closed(&self) -> bool {
+        self.inner.end.is_closed()
+    }
+
+    /// Wait until the connection is closed
+    pub async fn closed(&self) {
+        self.inner.end.closed().await
+    }
+}
+
+struct BusInner {
+    end: End,
+    timeout: Duration,
+    pending: Mutex<HashMap<u32, oneshot::Sender<WxResult<Value>>>>,
+    next_message_id: Mutex<u32>,
+    /// Calls from the other side being handled, by message id, so they can be cancelled
+    active: Mutex<HashMap<u32, tokio::task::AbortHandle>>,
+}
+
+/// Sender bound to a specific protocol, used by generated clients to execute RPC calls
+#[derive(Clone)]
+pub struct BoundSender {
+    inner: Arc<BusInner>,
+    protocol: Arc<str>,
+}
+
+impl WxSender for BoundSender {
+    async fn send(&self, fid: u32, args: Value) -> WxResult<Value> {
+        self.send_with_timeout(fid, args, self.inner.timeout).await
+    }
+
+    async fn send_with_timeout(&self, fid: u32, args: Value, timeout: Duration) -> WxResult<Value> {
+        let inner = &self.inner;
+        let (resolve, response) = oneshot::channel();
+        let message_id = {
+            let mut pending = inner.pending.lock().unwrap();
+            let mut next = inner.next_message_id.lock().unwrap();
+            let initial = *next;
+            // handle message id collision
+            while pending.contains_key(&*next) {
+                *next = increment_message_id(*next);
+                if *next == initial {
+                    return Err(WxError::fail("No available message id"));
+                }
+            }
+            let message_id = *next;
+            *next = increment_message_id(*next);
+            pending.insert(message_id, resolve);
+            message_id
+        };
+
+        let result = match inner
+            .end
+            .send(WxMessage::new(&*self.protocol, message_id, fid, args))
+        {
+            Err(e) => Err(e),
+            Ok(()) => match tokio::time::timeout(timeout, response).await {
+                Ok(Ok(result)) => result,
+                // pending messages are dropped when the connection closes
+                Ok(Err(_)) => Err(WxError::new("Closed")),
+                Err(_) => Err(WxError::new("Timeout")),
+            },
+        };
+        inner.pending.lock().unwrap().remove(&message_id);
+        result
+    }
+
+    fn send_one_way(&self, fid: u32, args: Value) -> impl Future<Output = WxResult<()>> + Send {
+        std::future::ready(self.inner.end.send(WxMessage::new(
+            &*self.protocol,
+            ONE_WAY_MESSAGE_ID,
+            fid,
+            args,
+        )))
+    }
+}
+
+/// Make sure the message id is representable in 32-bit signed integer
+/// for max compatibility with different runtimes
+fn increment_message_id(id: u32) -> u32 {
+    if id >= 0x7fffffff { 100 } else { id + 1 }
+}
+
+/// If the function ID is a response or a control message, which should not be replied to
+fn is_no_reply_message(f: u32) -> bool {
+    matches!(
+        f,
+        FUNC_RETURN
+            | FUNC_RETURN_ERROR
+            | FUNC_STREAM_CHUNK
+            | FUNC_STREAM_END
+            | FUNC_STREAM_ERROR
+            | FUNC_STREAM_PULL
+            | FUNC_STREAM_CANCEL
+            | FUNC_CALLBACK_DISPOSE
+            | FUNC_CANCEL
+    )
+}
+
+/// Route incoming messages after protocol agreement:
+/// - routes return messages to pending calls
+/// - routes incoming requests to the correct handler
+/// - aborts the handlers of the calls cancelled by the other side
+async fn route_messages(
+    inner: Arc<BusInner>,
+    mut inbox: mpsc::UnboundedReceiver<WxMessage>,
+    handlers: HashMap<String, RecvHandler>,
+) {
+    while let Some(WxMessage { p, m, f, d, .. }) = inbox.recv().await {
+        if p == crate::INTERNAL_PROTOCOL {
+            if f != FUNC_HANDSHAKE && f != FUNC_PROTOCOL {
+                log::warn!("bus received unknown workex internal message: {f}");
+            }
+            continue;
+        }
+
+        let Some(handler) = handlers.get(&p) else {
+            if m == ONE_WAY_MESSAGE_ID {
+                log::warn!("bus received one-way call for unknown protocol: {p}");
+            } else if !is_no_reply_message(f) {
+                // unknown protocol from incoming requests, return an error
+                let error = WxError::new("UnknownProtocol");
+                let _ = inner.end.send(WxMessage::internal(
+                    m,
+                    FUNC_RETURN_ERROR,
+                    serde_json::to_value(error).unwrap_or_default(),
+                ));
+            } else {
+                log::warn!("bus received unknown protocol for a response message: {p}");
+            }
+            continue;
+        };
+
+        // other side returning a value
+        if f == FUNC_RETURN || f == FUNC_RETURN_ERROR {
+            let Some(pending) = inner.pending.lock().unwrap().remove(&m) else {
+                log::warn!("bus received response for unknown message id: {m}");
+                continue;
+            };
+            let result = if f == FUNC_RETURN {
+                Ok(d)
+            } else {
+                Err(serde_json::from_value(d).unwrap_or_else(|_| WxError::new("Fail")))
+            };
+            let _ = pending.send(result);
+            continue;
+        }
+
+        // other side cancelling a call, or a stream returned by this side
+        if f == FUNC_CANCEL || f == FUNC_STREAM_CANCEL {
+            if let Some(call) = inner.active.lock().unwrap().remove(&m) {
+                call.abort();
+            }
+            continue;
+        }
+
+        if matches!(
+            f,
+            FUNC_STREAM_CHUNK | FUNC_STREAM_END | FUNC_STREAM_ERROR | FUNC_STREAM_PULL
+        ) {
+            log::warn!("bus received stream message {f}, but streams are not supported");
+            continue;
+        }
+
+        // callbacks are never passed from this side
+        if f == FUNC_CALLBACK_DISPOSE {
+            log::warn!("bus received callback dispose, but callbacks are not supported");
+            continue;
+        }
+        if f == FUNC_CALLBACK {
+            log::warn!("bus received callback call, but callbacks are not supported");
+            let error = WxError::new("CallbackDisposed");
+            let _ = inner.end.send(WxMessage::new(
+                p,
+                m,
+                FUNC_RETURN_ERROR,
+                serde_json::to_value(error).unwrap_or_default(),
+            ));
+            continue;
+        }
+
+        // other side sending a request
+        let call = if d.is_array() {
+            let call = tokio::spawn(handler(f, d));
+            if m != ONE_WAY_MESSAGE_ID {
+                inner.active.lock().unwrap().insert(m, call.abort_handle());
+            }
+            Some(call)
+        } else {
+            None
+        };
+        let inner = Arc::clone(&inner);
+        tokio::spawn(async move {
+            let result = match call {
+                Some(call) => {
+                    let result = call.await;
+                    if m != ONE_WAY_MESSAGE_ID {
+                        inner.active.lock().unwrap().remove(&m);
+                    }
+                    match result {
+                        Ok(result) => result,
+                        // the other side does not wait for the reply of a cancelled call
+                        Err(e) if e.is_cancelled() => return,
+                        Err(e) => Err(WxError::with_message("Catch", e.to_string())),
+                    }
+                }
+                None => {
+                    log::warn!("bus received invalid data for a request");
+                    Err(WxError::new("InvalidRequestData"))
+                }
+            };
+            // one-way calls are never replied to
+            if m == ONE_WAY_MESSAGE_ID {
+                if let Err(e) = result {
+                    log::warn!("one-way call to function {f} failed: {e}");
+                }
+                return;
+            }
+            let response = match result {
+                Ok(value) => WxMessage::new(p, m, FUNC_RETURN, value),
+                Err(error) => WxMessage::new(
+                    p,
+                    m,
+                    FUNC_RETURN_ERROR,
+                    serde_json::to_value(error).unwrap_or_default(),
+                ),
+            };
+            if inner.end.send(response).is_err() {
+                log::warn!("bus failed to send response because the end is closed");
+            }
+        });
+    }
+
+    // drop all pending calls, which resolves them with the Closed error
+    inner.pending.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::transport::channel;
+
+    /// Connect 2 buses over an in-memory channel. The passive side implements
+    /// `Api` under the `test` protocol, which doubles the number or sleeps for
+    /// the number of milliseconds. One-way calls and finished sleeps are sent to `calls`
+    async fn connect(timeout: Duration) -> (Bus, Bus, mpsc::UnboundedReceiver<Value>) {
+        let (a, b) = channel();
+        let (send_call, calls) = mpsc::unbounded_channel();
+        let handler = move |fid, args: Value| {
+            let send_call = send_call.clone();
+            async move {
+                let n = args[0].as_u64().unwrap_or_default();
+                match fid {
+                    16 => Ok(json!(n * 2)),
+                    17 => {
+                        tokio::time::sleep(Duration::from_millis(n)).await;
+                        let _ = send_call.send(args);
+                        Ok(Value::Null)
+                    }
+                    18 => {
+                        let _ = send_call.send(args);
+                        Ok(Value::Null)
+                    }
+                    _ => Err(WxError::new("UnknownFunction")),
+                }
+            }
+        };
+        let active = BusBuilder::active()
+            .timeout(timeout)
+            .bind(ProtocolBinding::sender("test", "Api"))
+            .connect(a);
+        let passive = BusBuilder::passive()
+            .timeout(timeout)
+            .bind(ProtocolBinding::receiver("test", "Api", handler))
+            .connect(b);
+        let (active, passive) = tokio::join!(active, passive);
+        (active.unwrap(), passive.unwrap(), calls)
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let (active, _passive, _) = connect(Duration::from_secs(5)).await;
+        assert!(active.sender("other").is_none());
+        let sender = active.sender("test").unwrap();
+        assert_eq!(sender.send(16, json!([21])).await, Ok(json!(42)));
+        assert_eq!(
+            sender.send(99, json!([])).await,
+            Err(WxError::new("UnknownFunction"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_one_way() {
+        let (active, _passive, mut calls) = connect(Duration::from_secs(5)).await;
+        let sender = active.sender("test").unwrap();
+        assert_eq!(sender.send_one_way(18, json!([7])).await, Ok(()));
+        assert_eq!(calls.recv().await, Some(json!([7])));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (active, _passive, _) = connect(Duration::from_secs(5)).await;
+        let sender = active.sender("test").unwrap();
+        let result = sender
+            .send_with_timeout(17, json!([1000]), Duration::from_millis(10))
+            .await;
+        assert_eq!(result, Err(WxError::new("Timeout")));
+        assert_eq!(sender.send(17, json!([1])).await, Ok(Value::Null));
+    }
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let (active, _passive, mut calls) = connect(Duration::from_secs(5)).await;
+        let sender = active.sender("test").unwrap();
+        let message_id = *active.inner.next_message_id.lock().unwrap();
+        let call = tokio::spawn(async move {
+            sender
+                .send_with_timeout(17, json!([200]), Duration::from_millis(400))
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // same as the message sent by the TypeScript SDK when the signal is aborted
+        let cancel = WxMessage::new("test", message_id, FUNC_CANCEL, Value::Null);
+        active.inner.end.send(cancel).unwrap();
+        // the handler is aborted and the call is not replied to
+        assert_eq!(call.await.unwrap(), Err(WxError::new("Timeout")));
+        assert!(calls.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_callback() {
+        let (active, _passive, _) = connect(Duration::from_secs(5)).await;
+        let sender = active.sender("test").unwrap();
+        assert_eq!(
+            sender.send(FUNC_CALLBACK, json!([1])).await,
+            Err(WxError::new("CallbackDisposed"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let (active, passive, _) = connect(Duration::from_secs(5)).await;
+        let sender = active.sender("test").unwrap();
+        passive.close();
+        active.closed().await;
+        assert!(active.is_closed());
+        assert!(sender.send(16, json!([1])).await.is_err());
+    }
+}
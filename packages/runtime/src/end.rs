@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::{mpsc, watch};
+
+use crate::transport::{Transport, TransportRecv, TransportSend};
+use crate::{FUNC_CLOSE, FUNC_HANDSHAKE, HANDSHAKE_MSG_HELLO, WxError, WxMessage, WxResult};
+
+/// One end of an established messaging channel, same as `WxEnd` in the TypeScript SDK.
+///
+/// The transport is driven by a writer task and a reader task. Messages received
+/// after the handshake are delivered to the inbox returned by [`End::connect`].
+#[derive(Clone)]
+pub(crate) struct End {
+    /// Outgoing messages. `None` tells the writer to close the transport
+    out: mpsc::UnboundedSender<Option<WxMessage>>,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl End {
+    /// Spawn the tasks to drive the transport, and execute the handshake.
+    ///
+    /// The active side sends hello messages on an interval until a hello is received
+    /// from the other side. The passive side responds with hello when it receives one.
+    pub async fn connect<T: Transport>(
+        transport: T,
+        is_active: bool,
+        timeout: Duration,
+    ) -> WxResult<(Self, mpsc::UnboundedReceiver<WxMessage>)> {
+        let (mut send, mut recv) = transport.split();
+        let (out, mut out_recv) = mpsc::unbounded_channel::<Option<WxMessage>>();
+        let (closed, _) = watch::channel(false);
+        let end = Self {
+            out,
+            closed: Arc::new(closed),
+        };
+
+        // writer task
+        {
+            let end = end.clone();
+            tokio::spawn(async move {
+                while let Some(Some(message)) = out_recv.recv().await {
+                    if let Err(e) = send.send(message).await {
+                        log::error!("failed to send message: {e}");
+                        end.close_from_remote();
+                        break;
+                    }
+                }
+                send.close().await;
+            });
+        }
+
+        // reader task
+        let (inbox_send, mut inbox) = mpsc::unbounded_channel();
+        {
+            let end = end.clone();
+            tokio::spawn(async move {
+                loop {
+                    let message = tokio::select! {
+                        message = recv.recv() => message,
+                        _ = end.closed() => break,
+                    };
+                    let message = match message {
+                        Ok(Some(message)) => message,
+                        Ok(None) => {
+                            log::debug!("transport closed by the other side");
+                            break;
+                        }
+                        Err(e) => {
+                            log::error!("failed to receive message: {e}");
+                            break;
+                        }
+                    };
+                    if !message.is_valid() {
+                        continue;
+                    }
+                    if message.is_internal() && message.f == FUNC_CLOSE {
+                        break;
+                    }
+                    if inbox_send.send(message).is_err() {
+                        break;
+                    }
+                }
+                end.close_from_remote();
+            });
+        }
+
+        let handshake = async {
+            let mut interval = tokio::time::interval(Duration::from_millis(50));
+            let mut count = 0;
+            loop {
+                let message = if is_active {
+                    tokio::select! {
+                        message = inbox.recv() => message,
+                        _ = interval.tick() => {
+                            count += 1;
+                            if count == 20 {
+                                interval = tokio::time::interval(Duration::from_secs(1));
+                                interval.tick().await;
+                            }
+                            end.send(hello())?;
+                            continue;
+                        }
+                    }
+                } else {
+                    inbox.recv().await
+                };
+                let Some(message) = message else {
+                    return Err(WxError::new("Closed"));
+                };
+                if !message.is_internal() || message.f != FUNC_HANDSHAKE {
+                    continue;
+                }
+                if message.m != HANDSHAKE_MSG_HELLO {
+                    log::warn!("unknown handshake message with mID {}", message.m);
+                    continue;
+                }
+                if !is_active {
+                    end.send(hello())?;
+                }
+                return Ok(());
+            }
+        };
+
+        let result = match tokio::time::timeout(timeout, handshake).await {
+            Ok(result) => result,
+            Err(_) => Err(WxError::new("Timeout")),
+        };
+        if let Err(e) = result {
+            end.close();
+            return Err(e);
+        }
+
+        Ok((end, inbox))
+    }
+
+    /// Send a message to the other end
+    pub fn send(&self, message: WxMessage) -> WxResult<()> {
+        if self.is_closed() {
+            return Err(WxError::new("Closed"));
+        }
+        self.out
+            .send(Some(message))
+            .map_err(|_| WxError::new("Closed"))
+    }
+
+    /// Close the channel, and notify the other side
+    pub fn close(&self) {
+        if self.is_closed() {
+            return;
+        }
+        let _ = self
+            .out
+            .send(Some(WxMessage::internal(1, FUNC_CLOSE, "close".into())));
+        self.close_from_remote();
+    }
+
+    /// Close the channel without notifying the other side
+    pub fn close_from_remote(&self) {
+        if self.closed.send_replace(true) {
+            return;
+        }
+        let _ = self.out.send(None);
+    }
+
+    /// Check if the channel is closed
+    pub fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    /// Wait until the channel is closed
+    pub async fn closed(&self) {
+        let mut closed = self.closed.subscribe();
+        let _ = closed.wait_for(|closed| *closed).await;
+    }
+}
+
+fn hello() -> WxMessage {
+    WxMessage::internal(
+        HANDSHAKE_MSG_HELLO,
+        FUNC_HANDSHAKE,
+        Value::String("hello".to_string()),
+    )
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// Workex error object, containing an error code and optionally a message.
+///
+/// The codes are the same as `WxEc` in the TypeScript SDK, for example
+/// `"Timeout"`, `"Closed"` or `"UnknownFunction"`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WxError {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl WxError {
+    /// Create an error with only the code
+    pub fn new(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: None,
+        }
+    }
+
+    /// Create an error with the code and message
+    pub fn with_message(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: Some(message.into()),
+        }
+    }
+
+    /// Create a generic `Fail` error with a message
+    pub fn fail(message: impl Into<String>) -> Self {
+        Self::with_message("Fail", message)
+    }
+}
+
+impl std::fmt::Display for WxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.code, message),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for WxError {}
+
+pub type WxResult<T> = Result<T, WxError>;
@@ -0,0 +1,80 @@
+//! Native runtime for Workex.
+//!
+//! This crate speaks the same wire protocol as the `@pistonite/workex` TypeScript SDK:
+//! the handshake, the protocol agreement and the routing of calls and returns.
+//! Messages are carried over a pluggable [`Transport`](transport::Transport),
+//! and stdio, WebSocket and in-memory channel transports are included.
+//!
+//! Use the `--rust` flag of the workex CLI with `--rust-runtime workex_runtime`
+//! to generate bindings that work with this crate. For example, a native helper
+//! process can serve an interface to a web page through a local server:
+//!
+//! ```ignore
+//! let ws = tokio_tungstenite::accept_async(tcp_stream).await?;
+//! let handler = Arc::new(MyHelper::default());
+//! let bus = BusBuilder::passive()
+//!     .bind(ProtocolBinding::receiver("myproto", "Helper", move |fid, args| {
+//!         let handler = Arc::clone(&handler);
+//!         async move { proto::dispatch_helper(&*handler, fid, args).await }
+//!     }))
+//!     .connect(WebSocketTransport::new(ws))
+//!     .await?;
+//! bus.closed().await;
+//! ```
+//!
+//! The web page then connects to the helper with `wxWebSocket` from the TypeScript SDK.
+//!
+//! Interfaces can also be authored in Rust, as traits marked with [`interface`].
+//! Pass the `.rs` file as input to the workex CLI to generate the TypeScript side.
+//! To write the attribute as `#[workex::interface]`, rename the dependency:
+//!
+//! ```toml
+//! [dependencies]
+//! workex = { package = "workex-runtime", version = "0.1.0" }
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::Value;
+
+mod bus;
+pub use bus::*;
+mod end;
+mod error;
+pub use error::*;
+mod message;
+pub use message::*;
+pub mod transport;
+
+#[cfg(feature = "macros")]
+pub use workex_macros::interface;
+
+/// Sender used by generated clients to execute remote calls
+///
+/// The arguments are sent as an array, and the return value is the `val` of the result
+pub trait WxSender {
+    fn send(&self, fid: u32, args: Value) -> impl Future<Output = WxResult<Value>> + Send;
+
+    /// Same as [`send`](Self::send), but with a timeout for this call instead of
+    /// the default. Used for functions annotated with `@timeout`
+    fn send_with_timeout(
+        &self,
+        fid: u32,
+        args: Value,
+        timeout: Duration,
+    ) -> impl Future<Output = WxResult<Value>> + Send {
+        let _ = timeout;
+        self.send(fid, args)
+    }
+
+    /// Send a one-way call without waiting for a response. Used for functions
+    /// annotated with `@oneway`. Errors are only returned if the call cannot be sent
+    ///
+    /// By default, this is sent as a normal call and the return value is ignored,
+    /// so senders that don't support one-way calls still work
+    fn send_one_way(&self, fid: u32, args: Value) -> impl Future<Output = WxResult<()>> + Send {
+        let result = self.send(fid, args);
+        async move { result.await.map(|_| ()) }
+    }
+}
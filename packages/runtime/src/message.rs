@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Internal protocol used for implementation of lower-level
+/// communication before control is passed to user-defined interfaces
+pub const INTERNAL_PROTOCOL: &str = "workex";
+
+/// Func ID for return value
+pub const FUNC_RETURN: u32 = 0;
+
+/// Func ID for return exception
+pub const FUNC_RETURN_ERROR: u32 = 1;
+
+/// Func ID used for handshake. The message ID determines the message type
+pub const FUNC_HANDSHAKE: u32 = 2;
+/// Hello handshake message
+pub const HANDSHAKE_MSG_HELLO: u32 = 1;
+
+/// Func ID used to request closing the connection
+pub const FUNC_CLOSE: u32 = 3;
+
+/// Func ID used to register and bind buses.
+///
+/// Active side sends this with message ID [`PROTOCOL_MSG_QUERY`],
+/// and the passive side returns [`PROTOCOL_MSG_AGREE`] or [`PROTOCOL_MSG_DISAGREE`].
+/// The data is the array of protocol names
+pub const FUNC_PROTOCOL: u32 = 4;
+/// Protocol query message
+pub const PROTOCOL_MSG_QUERY: u32 = 0;
+/// Protocol agree message
+pub const PROTOCOL_MSG_AGREE: u32 = 1;
+/// Protocol disagree message
+pub const PROTOCOL_MSG_DISAGREE: u32 = 2;
+
+/// Func IDs used for streaming return values, with the message ID of the call
+/// that returned the stream. Streams are not supported by the native runtime,
+/// so these are only recognized to not be handled as calls
+pub const FUNC_STREAM_CHUNK: u32 = 5;
+/// See [`FUNC_STREAM_CHUNK`]
+pub const FUNC_STREAM_END: u32 = 6;
+/// See [`FUNC_STREAM_CHUNK`]
+pub const FUNC_STREAM_ERROR: u32 = 7;
+/// See [`FUNC_STREAM_CHUNK`]
+pub const FUNC_STREAM_PULL: u32 = 8;
+/// See [`FUNC_STREAM_CHUNK`]
+pub const FUNC_STREAM_CANCEL: u32 = 9;
+
+/// Func ID used to call a callback passed in as an argument.
+/// The data is the handle of the callback, followed by the arguments
+pub const FUNC_CALLBACK: u32 = 10;
+
+/// Func ID used to dispose a callback passed in as an argument.
+/// The data is the handle of the callback
+pub const FUNC_CALLBACK_DISPOSE: u32 = 11;
+
+/// Func ID used to cancel a call, when the signal passed to the call is aborted
+/// on the other side. The message ID is the one of the call to cancel
+pub const FUNC_CANCEL: u32 = 12;
+
+/// Message ID for one-way calls. The receiver never replies to these calls,
+/// so the sender does not wait for a response
+pub const ONE_WAY_MESSAGE_ID: u32 = 0;
+
+/// Message object sent over a transport, same as `WxMessage` in the TypeScript SDK
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WxMessage {
+    /// Always "workex" to not be confused with messages from other libraries
+    pub s: String,
+    /// The protocol identifier. The protocol "workex" is reserved for internal communication
+    pub p: String,
+    /// Message identifier, used to route responses to the pending call.
+    ///
+    /// Messages 0-99 are reserved for internal use. Regular RPC calls start from 100.
+    pub m: u32,
+    /// Function identifier. For response messages, this indicates if the response
+    /// is a return (0) or catch (1)
+    pub f: u32,
+    /// Data payload. Note that `undefined` on the JS side is omitted when serialized to JSON
+    #[serde(default)]
+    pub d: Value,
+}
+
+impl WxMessage {
+    /// Create a new message
+    pub fn new(p: impl Into<String>, m: u32, f: u32, d: Value) -> Self {
+        Self {
+            s: INTERNAL_PROTOCOL.to_string(),
+            p: p.into(),
+            m,
+            f,
+            d,
+        }
+    }
+
+    /// Create a new message in the internal protocol
+    pub fn internal(m: u32, f: u32, d: Value) -> Self {
+        Self::new(INTERNAL_PROTOCOL, m, f, d)
+    }
+
+    /// Check the message is a valid workex message
+    pub fn is_valid(&self) -> bool {
+        self.s == INTERNAL_PROTOCOL && !self.p.is_empty()
+    }
+
+    /// Check the message is in the internal protocol
+    pub fn is_internal(&self) -> bool {
+        self.p == INTERNAL_PROTOCOL
+    }
+}
@@ -0,0 +1,63 @@
+use tokio::sync::mpsc;
+
+use crate::WxMessage;
+
+use super::{Transport, TransportRecv, TransportSend};
+
+/// One end of an in-memory channel, created with [`channel`]
+pub struct ChannelTransport {
+    send: mpsc::UnboundedSender<WxMessage>,
+    recv: mpsc::UnboundedReceiver<WxMessage>,
+}
+
+/// Create an in-memory channel with 2 ends that can communicate with each other
+/// in the same process. Dropping or closing either end closes the channel.
+pub fn channel() -> (ChannelTransport, ChannelTransport) {
+    let (send_a, recv_b) = mpsc::unbounded_channel();
+    let (send_b, recv_a) = mpsc::unbounded_channel();
+    (
+        ChannelTransport {
+            send: send_a,
+            recv: recv_a,
+        },
+        ChannelTransport {
+            send: send_b,
+            recv: recv_b,
+        },
+    )
+}
+
+impl Transport for ChannelTransport {
+    type Send = ChannelSend;
+    type Recv = ChannelRecv;
+
+    fn split(self) -> (Self::Send, Self::Recv) {
+        (ChannelSend(Some(self.send)), ChannelRecv(self.recv))
+    }
+}
+
+/// Sending half of a [`ChannelTransport`]
+pub struct ChannelSend(Option<mpsc::UnboundedSender<WxMessage>>);
+
+impl TransportSend for ChannelSend {
+    async fn send(&mut self, message: WxMessage) -> std::io::Result<()> {
+        let Some(send) = &self.0 else {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        };
+        send.send(message)
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    async fn close(&mut self) {
+        self.0 = None;
+    }
+}
+
+/// Receiving half of a [`ChannelTransport`]
+pub struct ChannelRecv(mpsc::UnboundedReceiver<WxMessage>);
+
+impl TransportRecv for ChannelRecv {
+    async fn recv(&mut self) -> std::io::Result<Option<WxMessage>> {
+        Ok(self.0.recv().await)
+    }
+}
@@ -0,0 +1,48 @@
+use std::future::Future;
+
+use crate::WxMessage;
+
+mod channel;
+pub use channel::*;
+#[cfg(feature = "stdio")]
+mod stdio;
+#[cfg(feature = "stdio")]
+pub use stdio::*;
+#[cfg(feature = "websocket")]
+mod websocket;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
+
+/// A messaging channel that can carry [`WxMessage`]s to the other side.
+///
+/// The transport is split into a sending half and a receiving half,
+/// which are driven by separate tasks on the bus. This means the receiving
+/// half does not need to be cancel-safe.
+pub trait Transport {
+    type Send: TransportSend;
+    type Recv: TransportRecv;
+
+    /// Split the transport into the sending and receiving halves
+    fn split(self) -> (Self::Send, Self::Recv);
+}
+
+/// Sending half of a [`Transport`]
+pub trait TransportSend: Send + 'static {
+    /// Send a message to the other side
+    fn send(&mut self, message: WxMessage) -> impl Future<Output = std::io::Result<()>> + Send;
+
+    /// Close the transport. No more messages will be sent after this is called
+    fn close(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+/// Receiving half of a [`Transport`]
+pub trait TransportRecv: Send + 'static {
+    /// Receive the next message from the other side.
+    ///
+    /// Returns `Ok(None)` if the other side closed the channel.
+    /// Data that cannot be parsed as a message should be skipped
+    /// instead of returning an error.
+    fn recv(&mut self) -> impl Future<Output = std::io::Result<Option<WxMessage>>> + Send;
+}
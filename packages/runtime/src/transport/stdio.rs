@@ -0,0 +1,76 @@
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+
+use crate::WxMessage;
+
+use super::{Transport, TransportRecv, TransportSend};
+
+/// Transport that sends one JSON message per line over a reader and a writer,
+/// for example the stdin and stdout of a process. See [`stdio`]
+pub struct LineTransport<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R, W> LineTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+}
+
+/// Create a transport over the stdin and stdout of the current process.
+///
+/// Nothing else should be printed to stdout when this transport is used.
+pub fn stdio() -> LineTransport<tokio::io::Stdin, tokio::io::Stdout> {
+    LineTransport::new(tokio::io::stdin(), tokio::io::stdout())
+}
+
+impl<R, W> Transport for LineTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    type Send = LineSend<W>;
+    type Recv = LineRecv<R>;
+
+    fn split(self) -> (Self::Send, Self::Recv) {
+        (
+            LineSend(self.writer),
+            LineRecv(BufReader::new(self.reader).lines()),
+        )
+    }
+}
+
+/// Sending half of a [`LineTransport`]
+pub struct LineSend<W>(W);
+
+impl<W: AsyncWrite + Unpin + Send + 'static> TransportSend for LineSend<W> {
+    async fn send(&mut self, message: WxMessage) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(&message)?;
+        line.push(b'\n');
+        self.0.write_all(&line).await?;
+        self.0.flush().await
+    }
+
+    async fn close(&mut self) {
+        let _ = self.0.shutdown().await;
+    }
+}
+
+/// Receiving half of a [`LineTransport`]
+pub struct LineRecv<R>(Lines<BufReader<R>>);
+
+impl<R: AsyncRead + Unpin + Send + 'static> TransportRecv for LineRecv<R> {
+    async fn recv(&mut self) -> std::io::Result<Option<WxMessage>> {
+        while let Some(line) = self.0.next_line().await? {
+            match serde_json::from_str(&line) {
+                Ok(message) => return Ok(Some(message)),
+                Err(e) => log::debug!("ignoring invalid message line: {e}"),
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,85 @@
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::WxMessage;
+
+use super::{Transport, TransportRecv, TransportSend};
+
+/// Transport that sends one JSON message per text frame over an established WebSocket.
+///
+/// This is compatible with `wxWebSocket` in the TypeScript SDK. For example,
+/// a native helper process can accept the connection from a web page with
+/// `tokio_tungstenite::accept_async`, then create a passive bus with this transport.
+pub struct WebSocketTransport<S> {
+    stream: WebSocketStream<S>,
+}
+
+impl<S> WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    pub fn new(stream: WebSocketStream<S>) -> Self {
+        Self { stream }
+    }
+}
+
+impl<S> Transport for WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Send = WebSocketSend<S>;
+    type Recv = WebSocketRecv<S>;
+
+    fn split(self) -> (Self::Send, Self::Recv) {
+        let (sink, stream) = self.stream.split();
+        (WebSocketSend(sink), WebSocketRecv(stream))
+    }
+}
+
+/// Sending half of a [`WebSocketTransport`]
+pub struct WebSocketSend<S>(SplitSink<WebSocketStream<S>, Message>);
+
+impl<S> TransportSend for WebSocketSend<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn send(&mut self, message: WxMessage) -> std::io::Result<()> {
+        let text = serde_json::to_string(&message)?;
+        self.0
+            .send(Message::text(text))
+            .await
+            .map_err(std::io::Error::other)
+    }
+
+    async fn close(&mut self) {
+        let _ = self.0.close().await;
+    }
+}
+
+/// Receiving half of a [`WebSocketTransport`]
+pub struct WebSocketRecv<S>(SplitStream<WebSocketStream<S>>);
+
+impl<S> TransportRecv for WebSocketRecv<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    async fn recv(&mut self) -> std::io::Result<Option<WxMessage>> {
+        while let Some(frame) = self.0.next().await {
+            let text = match frame.map_err(std::io::Error::other)? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Ok(None),
+                // ping/pong are handled by tungstenite, and binary frames
+                // are not used by workex
+                _ => continue,
+            };
+            match serde_json::from_str(&text) {
+                Ok(message) => return Ok(Some(message)),
+                Err(e) => log::debug!("ignoring invalid message frame: {e}"),
+            }
+        }
+        Ok(None)
+    }
+}
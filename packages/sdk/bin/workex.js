@@ -0,0 +1,29 @@
+#!/usr/bin/env node
+// Run the workex CLI compiled to WASI (`task cli:build-wasi`), so it can be used
+// without installing the native binary.
+//
+// The root of the file system is mounted as the root directory, and the CLI
+// changes to the current directory from WORKEX_WASI_CWD, so the paths and the
+// lookup of .editorconfig in the parent directories are the same as natively.
+// On Windows, only the drive of the current directory is accessible.
+import { readFile } from "node:fs/promises";
+import path from "node:path";
+import { WASI } from "node:wasi";
+
+const cwd = process.cwd();
+const root = path.parse(cwd).root;
+const wasi = new WASI({
+    version: "preview1",
+    args: ["workex", ...process.argv.slice(2)],
+    env: {
+        ...process.env,
+        WORKEX_WASI_CWD: "/" + path.relative(root, cwd).split(path.sep).join("/"),
+    },
+    preopens: { "/": root },
+    returnOnExit: true,
+});
+const wasm = await WebAssembly.compile(
+    await readFile(new URL("./workex.wasm", import.meta.url)),
+);
+const instance = await WebAssembly.instantiate(wasm, wasi.getImportObject());
+process.exitCode = wasi.start(instance);
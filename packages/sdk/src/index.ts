@@ -7,7 +7,15 @@
  */
 
 // creator functions
-export { wxWorker, wxWorkerGlobal, wxPopup, wxFrame, wxWindowOwner } from "./wx_create.ts";
+export {
+    wxWorker,
+    wxWorkerGlobal,
+    wxPopup,
+    wxFrame,
+    wxWindowOwner,
+    wxWebSocket,
+    wxLoopback,
+} from "./wx_create.ts";
 export type { WxWorkerCreateOptions } from "./wx_create.ts";
 
 // types used in public APIs
@@ -16,16 +24,27 @@ export type {
     WxProtocolConfig,
     WxProtocolBindConfig,
     WxBusRecvHandler,
+    WxTransferResult,
     WxProtocolOutput,
     WxBusCreator,
     WxProtocolBoundSender,
+    WxSendOptions,
     WxCreateBusOutput,
 } from "./wx_bus.ts";
 export type { WxFrameLinkOptions, WxWindowOpenOptions, WxWindow } from "./wx_window.ts";
 
-export type { WxEc, WxError, WxResult, WxVoid, WxPromise } from "./wx_error.ts";
+export type { WxEc, WxError, WxResult, WxVoid, WxPromise, WxOneWay } from "./wx_error.ts";
+export type { WxStream, WxStreamResult } from "./wx_stream.ts";
+export type { WxBusRecvContext, WxCallbackFn } from "./wx_callback.ts";
+export { wxDisposeCallback } from "./wx_callback.ts";
+
+export type { WxMockKey, WxMockArgs, WxMockResult, WxMockCall } from "./wx_mock.ts";
+export { WxMock } from "./wx_mock.ts";
+
+export type { WxTraceEvent, WxTraceHook } from "./wx_trace.ts";
+export { wxSetTraceHook, wxTraceSender, wxTraceRecver } from "./wx_trace.ts";
 
 export type { WxPromiseWrapper } from "./wx_util.ts";
 export { wxMakePromise, wxWrapHandler } from "./wx_util.ts";
 
-export { log as wxLogger } from "./wx_log.ts";
+export { log as wxLogger, wxWarnDeprecatedCall } from "./wx_log.ts";
@@ -9,10 +9,15 @@
  * @module
  */
 export { wxFail } from "./wx_error.ts";
-export type { WindowLike, IFrameLike, WorkerLike } from "./wx_util.ts";
+export type { WindowLike, IFrameLike, WorkerLike, WebSocketLike } from "./wx_util.ts";
 
 export type { WxEnd, WxEndOptions } from "./wx_end.ts";
-export { wxMakeWorkerEnd, wxMakeWorkerGlobalEnd, wxMakeChannel } from "./wx_end.ts";
+export {
+    wxMakeWorkerEnd,
+    wxMakeWorkerGlobalEnd,
+    wxMakeWebSocketEnd,
+    wxMakeChannel,
+} from "./wx_end.ts";
 
 export type {
     WxPayload,
@@ -23,14 +28,27 @@ export type {
 } from "./wx_message.ts";
 export {
     wxInternalProtocol,
+    wxOneWayMessageId,
     wxFuncReturn,
     wxFuncReturnError,
     wxFuncHandshake,
     wxHandshakeMsgHello,
     wxFuncClose,
     wxFuncProtocol,
+    wxFuncStreamChunk,
+    wxFuncStreamEnd,
+    wxFuncStreamError,
+    wxFuncStreamPull,
+    wxFuncStreamCancel,
+    wxFuncCallback,
+    wxFuncCallbackDispose,
+    wxFuncCancel,
     isWxMessageEvent,
     wxMakeMessageController,
 } from "./wx_message.ts";
 
 export { wxWindow } from "./wx_window.ts";
+
+export type { WxStreamHandle, WxStreamOpenFn } from "./wx_stream.ts";
+export { WxCallbackRegistry, wxMakeCallbackStub } from "./wx_callback.ts";
+export { WxStreamReader, wxPumpStream, wxStreamWindow } from "./wx_stream.ts";
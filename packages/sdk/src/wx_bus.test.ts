@@ -0,0 +1,97 @@
+import { describe, expect, test } from "vitest";
+
+import type { WxBusRecvHandler, WxProtocolBoundSender } from "./wx_bus.ts";
+import { wxLoopback } from "./wx_create.ts";
+
+/** Connect a sender to the handler through a loopback bus */
+const connect = async (recvHandler: WxBusRecvHandler) => {
+    const [passive, active] = wxLoopback({ timeout: 1000 });
+    const bindSend = (sender: WxProtocolBoundSender) => sender;
+    const [handlerSide, callerSide] = await Promise.all([
+        passive({ test: { protocol: "test", interfaces: ["Caller", "Api"], recvHandler, bindSend } }),
+        active({
+            test: {
+                protocol: "test",
+                interfaces: ["Api", "Caller"],
+                recvHandler: () => Promise.resolve({ err: { code: "UnknownFunction" } }),
+                bindSend,
+            },
+        }),
+    ]);
+    if (handlerSide.err || callerSide.err) {
+        throw new Error("failed to connect");
+    }
+    return { sender: callerSide.val.protocols.test, connection: callerSide.val.connection };
+};
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+/** Handler that waits until the call is cancelled, and records if the signal is aborted */
+const waitForCancel = (state: { called: boolean; aborted: boolean }): WxBusRecvHandler => {
+    return (_, __, context) => {
+        state.called = true;
+        const signal = context.signal();
+        return new Promise((resolve) => {
+            signal.addEventListener("abort", () => {
+                state.aborted = true;
+                resolve({ err: { code: "Aborted" } });
+            });
+        });
+    };
+};
+
+describe("bus", () => {
+    test("round trip", async () => {
+        const { sender } = await connect((fId, data) => {
+            if (fId !== 16) {
+                return Promise.resolve({ err: { code: "UnknownFunction" } });
+            }
+            return Promise.resolve({ val: (data[0] as number) + (data[1] as number) });
+        });
+        expect(await sender.send<number>(16, [1, 2])).toEqual({ val: 3 });
+        expect(await sender.sendVoid(16, [1, 2])).toEqual({});
+        expect(await sender.send<number>(17, [])).toEqual({
+            err: { code: "UnknownFunction" },
+        });
+    });
+
+    test("cancel with AbortSignal", async () => {
+        const state = { called: false, aborted: false };
+        const { sender } = await connect(waitForCancel(state));
+        const controller = new AbortController();
+        const result = sender.send<void>(16, [], { signal: controller.signal });
+        await sleep(10);
+        expect(state.called).toBe(true);
+        controller.abort();
+        expect(await result).toEqual({ err: { code: "Cancelled" } });
+        await sleep(10);
+        expect(state.aborted).toBe(true);
+    });
+
+    test("cancel before sending", async () => {
+        const state = { called: false, aborted: false };
+        const { sender } = await connect(waitForCancel(state));
+        const controller = new AbortController();
+        controller.abort();
+        const result = await sender.send<void>(16, [], { signal: controller.signal });
+        expect(result).toEqual({ err: { code: "Cancelled" } });
+        await sleep(10);
+        expect(state.called).toBe(false);
+    });
+
+    test("cancel when the connection is closed", async () => {
+        const state = { called: false, aborted: false };
+        const { sender, connection } = await connect(waitForCancel(state));
+        void sender.send<void>(16, []);
+        await sleep(10);
+        connection.close();
+        expect(state.aborted).toBe(true);
+    });
+
+    test("timeout", async () => {
+        const state = { called: false, aborted: false };
+        const { sender } = await connect(waitForCancel(state));
+        const result = await sender.send<void>(16, [], { timeout: 10 });
+        expect(result).toEqual({ err: { code: "Timeout" } });
+    });
+});
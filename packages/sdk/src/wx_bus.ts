@@ -10,12 +10,32 @@ import {
     wxFuncReturn,
     wxFuncReturnError,
     wxInternalProtocol,
+    wxOneWayMessageId,
+    wxFuncStreamChunk,
+    wxFuncStreamEnd,
+    wxFuncStreamError,
+    wxFuncStreamPull,
+    wxFuncStreamCancel,
+    wxFuncCallback,
+    wxFuncCallbackDispose,
+    wxFuncCancel,
     type WxCloseController,
     type WxOnRecvFn,
     type WxPayload,
 } from "./wx_message.ts";
 import { wxMakePromise } from "./wx_util.ts";
 import { log } from "./wx_log.ts";
+import {
+    WxCallbackRegistry,
+    wxMakeCallbackStub,
+    type WxBusRecvContext,
+} from "./wx_callback.ts";
+import {
+    WxStreamReader,
+    wxPumpStream,
+    type WxStream,
+    type WxStreamResult,
+} from "./wx_stream.ts";
 
 /**
  * Base type shape for the config object passed into the bus creation function.
@@ -54,7 +74,23 @@ export type WxBusRecvHandler = (
     fId: number,
     // eslint-disable-next-line @typescript-eslint/no-explicit-any
     data: any[],
-) => WxPromise<unknown> | WxPromise<void>;
+    context: WxBusRecvContext,
+) =>
+    | WxPromise<unknown>
+    | WxPromise<void>
+    | Promise<WxTransferResult>
+    | Promise<WxStreamResult>;
+
+/**
+ * Result returned by a {@link WxBusRecvHandler} when the return value
+ * is annotated with `@transfer`. The values in `transfer` are transferred
+ * instead of cloned when sending the return value
+ */
+export interface WxTransferResult {
+    val: unknown;
+    transfer: unknown[];
+    err?: undefined;
+}
 
 /**
  * Type for creator functions that takes in a configuration object built from
@@ -116,6 +152,45 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
     // - routes incoming requests to the correct handler
 
     const pendingMessages: Map<number, (value: WxResult<unknown>) => void> = new Map();
+    // streams returned from the other side, receiving the chunks
+    const pendingStreams: Map<number, (f: number, d: unknown) => void> = new Map();
+    // streams returned from handlers on this side, receiving pull/cancel messages
+    const activeStreams: Map<number, (f: number, d: unknown) => void> = new Map();
+    // callbacks passed to the other side
+    const callbacks = new WxCallbackRegistry();
+    // calls being handled on this side, that can be cancelled by the other side
+    const activeCalls: Map<number, ActiveCall> = new Map();
+    const cancelCall = (m: number) => {
+        const call = activeCalls.get(m);
+        if (call) {
+            call.cancelled = true;
+            call.controller?.abort();
+        }
+    };
+
+    // context for handlers to create stubs for callbacks passed from the other side,
+    // and the signal for cancellation. One-way calls cannot be cancelled
+    const makeRecvContext = (p: string, call: ActiveCall | undefined): WxBusRecvContext => ({
+        callback: (handle) =>
+            wxMakeCallbackStub(
+                handle,
+                (h, args) => sender.sendCallback(p, h, args),
+                (h) => sender.sendCallbackDispose(p, h),
+            ),
+        signal: () => {
+            if (!call) {
+                return new AbortController().signal;
+            }
+            // the controller is only created if the handler needs it
+            if (!call.controller) {
+                call.controller = new AbortController();
+                if (call.cancelled) {
+                    call.controller.abort();
+                }
+            }
+            return call.controller.signal;
+        },
+    });
 
     const { promise: protocolPromise, resolve: resolveProtocol } = wxMakePromise<WxVoid>();
 
@@ -168,7 +243,10 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
         // check if it's a known protocol
         const handler = protocolToHandler[p];
         if (!handler) {
-            if (f !== wxFuncReturn && f !== wxFuncReturnError) {
+            if (m === wxOneWayMessageId) {
+                // one-way calls are never replied to
+                log.warn(`bus received one-way call for unknown protocol: ${p}`);
+            } else if (!isNoReplyMessage(f)) {
                 // unknown protocol from incoming requests, return an error
                 const res = end.send({
                     s: wxInternalProtocol,
@@ -190,10 +268,81 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
             return;
         }
 
+        // other side sending a chunk of a stream
+        if (f === wxFuncStreamChunk || f === wxFuncStreamEnd || f === wxFuncStreamError) {
+            const pending = pendingStreams.get(m);
+            if (!pending) {
+                // the stream could be cancelled by this side already
+                log.debug(`bus received stream message for unknown message id: ${m}`);
+                return;
+            }
+            pending(f, d);
+            return;
+        }
+
+        // other side consuming or cancelling a stream returned by this side
+        if (f === wxFuncStreamPull || f === wxFuncStreamCancel) {
+            if (f === wxFuncStreamCancel) {
+                cancelCall(m);
+            }
+            activeStreams.get(m)?.(f, d);
+            return;
+        }
+
+        // other side cancelling a call
+        if (f === wxFuncCancel) {
+            cancelCall(m);
+            return;
+        }
+
+        // other side calling a callback passed from this side
+        if (f === wxFuncCallback) {
+            const [handle, ...args] = Array.isArray(d) ? d : [];
+            const callback = callbacks.get(handle);
+            let result: WxResult<unknown>;
+            if (!callback) {
+                result = { err: { code: "CallbackDisposed" } };
+            } else {
+                try {
+                    const value = await callback(...args);
+                    // callbacks return either nothing or a WxResult
+                    const isResult =
+                        value && typeof value === "object" && ("val" in value || "err" in value);
+                    result = isResult ? value : {};
+                } catch (e) {
+                    result = { err: { code: "Catch", message: errstr(e) } };
+                }
+            }
+            const res = end.send({
+                s: wxInternalProtocol,
+                p,
+                m,
+                f: result.err ? wxFuncReturnError : wxFuncReturn,
+                d: result.err ? result.err : result.val,
+            });
+            if (res.err?.code === "Closed") {
+                log.warn(`bus failed to send callback response because the end is closed`);
+                end.close();
+            }
+            return;
+        }
+
+        // other side disposing a callback passed from this side
+        if (f === wxFuncCallbackDispose) {
+            callbacks.dispose(d);
+            return;
+        }
+
         // other side returning a value
         if (f === wxFuncReturn || f === wxFuncReturnError) {
             const pending = pendingMessages.get(m);
             if (!pending) {
+                // calls to streaming functions can fail before the stream starts
+                const pendingStream = pendingStreams.get(m);
+                if (pendingStream) {
+                    pendingStream(f, d);
+                    return;
+                }
                 log.warn(`bus received response for unknown message id: ${m}`);
                 return;
             }
@@ -205,6 +354,23 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
             return;
         }
 
+        // other side sending a one-way request, which is never replied to
+        if (m === wxOneWayMessageId) {
+            if (!d || !Array.isArray(d)) {
+                log.warn(`bus received invalid data for a one-way request`);
+                return;
+            }
+            try {
+                const result = await handler(f, d, makeRecvContext(p, undefined));
+                if (result?.err) {
+                    log.warn(`one-way call to function ${f} failed: ${result.err.code}`);
+                }
+            } catch (e) {
+                log.error(`one-way call to function ${f} failed: ${errstr(e)}`);
+            }
+            return;
+        }
+
         // other side sending a request
         let sendResult: WxResult<unknown>;
         if (!d || !Array.isArray(d)) {
@@ -219,8 +385,14 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
                 } satisfies WxError,
             });
         } else {
+            const call: ActiveCall = { cancelled: false };
+            activeCalls.set(m, call);
             try {
-                const result = await handler(f, d);
+                const result = await handler(f, d, makeRecvContext(p, call));
+                if (call.cancelled) {
+                    // the other side is no longer waiting for the result
+                    return;
+                }
                 if (!result) {
                     sendResult = end.send({
                         s: wxInternalProtocol,
@@ -239,16 +411,34 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
                         f: wxFuncReturnError,
                         d: result.err,
                     });
+                } else if ("stream" in result) {
+                    sendResult = await wxPumpStream(
+                        result.stream,
+                        (f, d) => end.send({ s: wxInternalProtocol, p, m, f, d }),
+                        (control) => {
+                            if (control) {
+                                activeStreams.set(m, control);
+                            } else {
+                                activeStreams.delete(m);
+                            }
+                        },
+                    );
                 } else {
-                    sendResult = end.send({
-                        s: wxInternalProtocol,
-                        p,
-                        m,
-                        f: wxFuncReturn,
-                        d: result.val,
-                    });
+                    sendResult = end.send(
+                        {
+                            s: wxInternalProtocol,
+                            p,
+                            m,
+                            f: wxFuncReturn,
+                            d: result.val,
+                        },
+                        "transfer" in result ? wxTransferList(result.transfer) : undefined,
+                    );
                 }
             } catch (e) {
+                if (call.cancelled) {
+                    return;
+                }
                 sendResult = end.send({
                     s: wxInternalProtocol,
                     p,
@@ -259,6 +449,8 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
                         message: errstr(e),
                     } satisfies WxError,
                 });
+            } finally {
+                activeCalls.delete(m);
             }
         }
 
@@ -275,10 +467,24 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
         return endResult;
     }
     const end = endResult.val;
+    const sender = new WxBusSender(end, pendingMessages, pendingStreams, callbacks, timeout);
 
     const removeProtocolSubscriber = end.onClose(() => {
         resolveProtocol({ err: { code: "Closed" } });
     });
+    // streams don't time out as a whole, so they need to be ended when closed
+    void end.onClose(() => {
+        for (const pending of pendingStreams.values()) {
+            pending(wxFuncStreamError, { code: "Closed" } satisfies WxError);
+        }
+        for (const control of activeStreams.values()) {
+            control(wxFuncStreamCancel, undefined);
+        }
+        callbacks.clear();
+        for (const m of activeCalls.keys()) {
+            cancelCall(m);
+        }
+    });
 
     if (isActiveSide) {
         // agree on protocols with the other end
@@ -311,8 +517,7 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
         return protocolRes;
     }
 
-    // create the sender and link to the protocols
-    const sender = new WxBusSender(end, pendingMessages, timeout);
+    // link the sender to the protocols
 
     const keyToSender: Record<string, unknown> = {};
     for (const protocol in protocolToBindSender) {
@@ -336,9 +541,51 @@ export const wxCreateBus = async <TConfig extends WxProtocolConfig>(
  */
 export interface WxProtocolBoundSender {
     /** Send a call and ignore the returned value */
-    sendVoid(fId: number, data: unknown[]): WxPromise<void>;
+    sendVoid(fId: number, data: unknown[], options?: WxSendOptions): WxPromise<void>;
     /** Send a call */
-    send<TReturn>(fId: number, data: unknown[]): WxPromise<TReturn>;
+    send<TReturn>(fId: number, data: unknown[], options?: WxSendOptions): WxPromise<TReturn>;
+    /**
+     * Send a one-way call without waiting for the other side. An error
+     * is only returned if the call cannot be sent
+     */
+    sendOneWay(fId: number, data: unknown[], options?: WxSendOptions): WxVoid;
+    /**
+     * Create a stream that sends the call when iterated. The timeout applies
+     * to waiting for each chunk
+     */
+    sendStream<TReturn>(fId: number, data: unknown[], options?: WxSendOptions): WxStream<TReturn>;
+}
+
+/**
+ * Options for a single call, generated from the annotations on the function
+ */
+export interface WxSendOptions {
+    /**
+     * Values to transfer instead of clone. `undefined`
+     * and `null` values in the list are ignored
+     */
+    transfer?: unknown[];
+    /**
+     * Timeout for this call in milliseconds, instead of the timeout
+     * of the bus
+     */
+    timeout?: number;
+    /**
+     * Indices of the arguments that are callbacks. The callbacks are
+     * kept on this side, and the other side receives stubs to call them.
+     * They are disposed when the call completes
+     */
+    callbacks?: number[];
+    /**
+     * Indices of the callbacks that are not disposed when the call completes.
+     * They need to be disposed with {@link wxDisposeCallback}
+     */
+    keepAlive?: number[];
+    /**
+     * Signal to cancel the call. When aborted, the other side is notified,
+     * and the call returns a `Cancelled` error without waiting for the other side
+     */
+    signal?: AbortSignal;
 }
 
 class WxProtocolBoundSenderImpl implements WxProtocolBoundSender {
@@ -350,8 +597,8 @@ class WxProtocolBoundSenderImpl implements WxProtocolBoundSender {
         this.protocol = protocol;
     }
 
-    public async sendVoid(fId: number, data: unknown[]): WxPromise<void> {
-        const result = await this.sender.send(this.protocol, fId, data);
+    public async sendVoid(fId: number, data: unknown[], options?: WxSendOptions): WxPromise<void> {
+        const result = await this.sender.send(this.protocol, fId, data, options);
         if (result.err) {
             return result;
         }
@@ -359,14 +606,32 @@ class WxProtocolBoundSenderImpl implements WxProtocolBoundSender {
         return {};
     }
 
-    public async send<TReturn>(fId: number, data: unknown[]): WxPromise<TReturn> {
-        return this.sender.send<TReturn>(this.protocol, fId, data);
+    public async send<TReturn>(
+        fId: number,
+        data: unknown[],
+        options?: WxSendOptions,
+    ): WxPromise<TReturn> {
+        return this.sender.send<TReturn>(this.protocol, fId, data, options);
+    }
+
+    public sendOneWay(fId: number, data: unknown[], options?: WxSendOptions): WxVoid {
+        return this.sender.sendOneWay(this.protocol, fId, data, options);
+    }
+
+    public sendStream<TReturn>(
+        fId: number,
+        data: unknown[],
+        options?: WxSendOptions,
+    ): WxStream<TReturn> {
+        return this.sender.sendStream<TReturn>(this.protocol, fId, data, options);
     }
 }
 
 class WxBusSender {
     private end: WxEnd;
     private pendingMessages: Map<number, (value: WxResult<unknown>) => void>;
+    private pendingStreams: Map<number, (f: number, d: unknown) => void>;
+    private callbacks: WxCallbackRegistry;
     private timeout: number;
 
     private nextMessageId = 100;
@@ -374,27 +639,45 @@ class WxBusSender {
     constructor(
         end: WxEnd,
         pendingMessages: Map<number, (value: WxResult<unknown>) => void>,
+        pendingStreams: Map<number, (f: number, d: unknown) => void>,
+        callbacks: WxCallbackRegistry,
         timeout: number,
     ) {
         this.end = end;
         this.pendingMessages = pendingMessages;
+        this.pendingStreams = pendingStreams;
+        this.callbacks = callbacks;
         this.timeout = timeout;
     }
 
-    public async send<TReturn>(protocol: string, fId: number, data: unknown[]): WxPromise<TReturn> {
+    public async send<TReturn>(
+        protocol: string,
+        fId: number,
+        data: unknown[],
+        options?: WxSendOptions,
+    ): WxPromise<TReturn> {
+        const signal = options?.signal;
+        if (signal?.aborted) {
+            return { err: { code: "Cancelled" } };
+        }
         const mIdRes = this.nextMId();
         if (mIdRes.err) {
             return mIdRes;
         }
         const mId = mIdRes.val;
-        const res = this.end.send({
-            s: wxInternalProtocol,
-            p: protocol,
-            m: mId,
-            f: fId,
-            d: data,
-        });
+        const [args, handles] = this.registerCallbacks(data, options, false);
+        const res = this.end.send(
+            {
+                s: wxInternalProtocol,
+                p: protocol,
+                m: mId,
+                f: fId,
+                d: args,
+            },
+            options?.transfer && wxTransferList(options.transfer),
+        );
         if (res.err) {
+            this.disposeCallbacks(handles);
             return res;
         }
 
@@ -404,20 +687,173 @@ class WxBusSender {
         const timeoutPromise = new Promise<WxResult<unknown>>((resolve) => {
             setTimeout(() => {
                 resolve({ err: { code: "Timeout" } });
-            }, this.timeout);
+            }, options?.timeout ?? this.timeout);
+        });
+        let onAbort: (() => void) | undefined;
+        const cancelPromise = new Promise<WxResult<unknown>>((resolve) => {
+            if (!signal) {
+                return;
+            }
+            onAbort = () => {
+                this.sendCancel(protocol, mId);
+                resolve({ err: { code: "Cancelled" } });
+            };
+            signal.addEventListener("abort", onAbort, { once: true });
         });
 
-        const result = await Promise.race([responsePromise, timeoutPromise]);
+        const result = await Promise.race([responsePromise, timeoutPromise, cancelPromise]);
+        if (onAbort) {
+            signal?.removeEventListener("abort", onAbort);
+        }
         this.pendingMessages.delete(mId);
+        this.disposeCallbacks(handles);
         return result as Awaited<WxPromise<TReturn>>;
     }
 
+    /** Notify the other side that a call is cancelled */
+    private sendCancel(protocol: string, mId: number) {
+        // if the end is closed, the calls are cancelled by the other side
+        void this.end.send({
+            s: wxInternalProtocol,
+            p: protocol,
+            m: mId,
+            f: wxFuncCancel,
+            d: null,
+        });
+    }
+
+    /** Call a callback on the other side, that was passed in as an argument */
+    public sendCallback(protocol: string, handle: number, args: unknown[]): WxPromise<unknown> {
+        return this.send(protocol, wxFuncCallback, [handle, ...args]);
+    }
+
+    /** Dispose a callback on the other side, that was passed in as an argument */
+    public sendCallbackDispose(protocol: string, handle: number) {
+        // if the end is closed, the callbacks are cleared by the other side
+        void this.end.send({
+            s: wxInternalProtocol,
+            p: protocol,
+            m: wxOneWayMessageId,
+            f: wxFuncCallbackDispose,
+            d: handle,
+        });
+    }
+
+    public sendOneWay(
+        protocol: string,
+        fId: number,
+        data: unknown[],
+        options?: WxSendOptions,
+    ): WxVoid {
+        // one-way calls don't complete, so the callbacks are always kept alive
+        const [args, handles] = this.registerCallbacks(data, options, true);
+        const res = this.end.send(
+            {
+                s: wxInternalProtocol,
+                p: protocol,
+                m: wxOneWayMessageId,
+                f: fId,
+                d: args,
+            },
+            options?.transfer && wxTransferList(options.transfer),
+        );
+        if (res.err) {
+            this.disposeCallbacks(handles);
+        }
+        return res;
+    }
+
+    public sendStream<TReturn>(
+        protocol: string,
+        fId: number,
+        data: unknown[],
+        options?: WxSendOptions,
+    ): WxStream<TReturn> {
+        const timeout = options?.timeout ?? this.timeout;
+        const signal = options?.signal;
+        return {
+            [Symbol.asyncIterator]: () => {
+                const reader: WxStreamReader<TReturn> = new WxStreamReader<TReturn>((onMessage) => {
+                    if (signal?.aborted) {
+                        return { err: { code: "Cancelled" } };
+                    }
+                    const mIdRes = this.nextMId();
+                    if (mIdRes.err) {
+                        return mIdRes;
+                    }
+                    const mId = mIdRes.val;
+                    const [args, handles] = this.registerCallbacks(data, options, false);
+                    this.pendingStreams.set(mId, onMessage);
+                    const res = this.end.send(
+                        {
+                            s: wxInternalProtocol,
+                            p: protocol,
+                            m: mId,
+                            f: fId,
+                            d: args,
+                        },
+                        options?.transfer && wxTransferList(options.transfer),
+                    );
+                    if (res.err) {
+                        this.pendingStreams.delete(mId);
+                        this.disposeCallbacks(handles);
+                        return res;
+                    }
+                    const onAbort = () => reader.cancel({ code: "Cancelled" });
+                    signal?.addEventListener("abort", onAbort, { once: true });
+                    return {
+                        val: {
+                            send: (f, d) => {
+                                // if the end is closed, the stream is ended by the bus
+                                void this.end.send({
+                                    s: wxInternalProtocol,
+                                    p: protocol,
+                                    m: mId,
+                                    f,
+                                    d,
+                                });
+                            },
+                            close: () => {
+                                this.pendingStreams.delete(mId);
+                                this.disposeCallbacks(handles);
+                                signal?.removeEventListener("abort", onAbort);
+                            },
+                        },
+                    };
+                }, timeout);
+                return reader;
+            },
+        };
+    }
+
+    /**
+     * Register the callbacks in the arguments, and return the arguments to send
+     * and the handles to dispose when the call completes
+     */
+    private registerCallbacks(
+        data: unknown[],
+        options: WxSendOptions | undefined,
+        keepAll: boolean,
+    ): [unknown[], number[]] {
+        if (!options?.callbacks) {
+            return [data, []];
+        }
+        const keepAlive = keepAll ? options.callbacks : options.keepAlive;
+        return this.callbacks.register(data, options.callbacks, keepAlive);
+    }
+
+    private disposeCallbacks(handles: number[]) {
+        for (const handle of handles) {
+            this.callbacks.dispose(handle);
+        }
+    }
+
     private nextMId(): WxResult<number> {
         let m = this.incrementMId();
         // handle message id collision
-        if (this.pendingMessages.has(m)) {
+        if (this.isMIdInUse(m)) {
             const initialMId = m;
-            while (this.pendingMessages.has(m)) {
+            while (this.isMIdInUse(m)) {
                 m = this.incrementMId();
                 if (m === initialMId) {
                     return {
@@ -432,6 +868,10 @@ class WxBusSender {
         return { val: m };
     }
 
+    private isMIdInUse(m: number) {
+        return this.pendingMessages.has(m) || this.pendingStreams.has(m);
+    }
+
     private incrementMId() {
         // make sure it's representable in 32-bit signed integer
         // for max compatibility with different runtimes
@@ -442,6 +882,37 @@ class WxBusSender {
     }
 }
 
+/** If the function ID is a response or a control message, which should not be replied to */
+const isNoReplyMessage = (f: number) =>
+    f === wxFuncReturn ||
+    f === wxFuncReturnError ||
+    f === wxFuncStreamChunk ||
+    f === wxFuncStreamEnd ||
+    f === wxFuncStreamError ||
+    f === wxFuncStreamPull ||
+    f === wxFuncStreamCancel ||
+    f === wxFuncCallbackDispose ||
+    f === wxFuncCancel;
+
+/** A call being handled on this side */
+interface ActiveCall {
+    /** If the other side cancelled the call */
+    cancelled: boolean;
+    /** Controller for the signal passed to the handler, created on demand */
+    controller?: AbortController;
+}
+
+/** Remove missing values from the transfer list, and remove duplicates */
+const wxTransferList = (transfer: unknown[]): unknown[] => {
+    const out: unknown[] = [];
+    for (const x of transfer) {
+        if (x !== undefined && x !== null && !out.includes(x)) {
+            out.push(x);
+        }
+    }
+    return out;
+};
+
 const shallowEqual = (a: string[], b: string[]) => {
     const aLen = a.length;
     if (aLen !== b.length) {
@@ -0,0 +1,99 @@
+import { describe, expect, test } from "vitest";
+
+import type { WxBusRecvHandler, WxProtocolBoundSender } from "./wx_bus.ts";
+import { type WxCallbackFn, wxDisposeCallback } from "./wx_callback.ts";
+import { wxLoopback } from "./wx_create.ts";
+import type { WxPromise } from "./wx_error.ts";
+
+/** Connect a sender to the handler through a loopback bus */
+const connect = async (recvHandler: WxBusRecvHandler) => {
+    const [passive, active] = wxLoopback({ timeout: 1000 });
+    const bindSend = (sender: WxProtocolBoundSender) => sender;
+    const [handlerSide, callerSide] = await Promise.all([
+        passive({ test: { protocol: "test", interfaces: ["Caller", "Api"], recvHandler, bindSend } }),
+        active({
+            test: {
+                protocol: "test",
+                interfaces: ["Api", "Caller"],
+                recvHandler: () => Promise.resolve({ err: { code: "UnknownFunction" } }),
+                bindSend,
+            },
+        }),
+    ]);
+    if (handlerSide.err || callerSide.err) {
+        throw new Error("failed to connect");
+    }
+    return callerSide.val.protocols.test;
+};
+
+describe("callbacks", () => {
+    test("round trip", async () => {
+        const sender = await connect(async (_, data, context) => {
+            const add = context.callback(data[1]) as (a: number, b: number) => WxPromise<number>;
+            const sum = await add(data[0] as number, 2);
+            if (sum.err) {
+                return sum;
+            }
+            return { val: sum.val * 10 };
+        });
+        const calls: number[][] = [];
+        const add = (a: number, b: number) => {
+            calls.push([a, b]);
+            return { val: a + b };
+        };
+        const result = await sender.send<number>(16, [1, add], { callbacks: [1] });
+        expect(result).toEqual({ val: 30 });
+        expect(calls).toEqual([[1, 2]]);
+    });
+
+    test("optional callback that is not passed", async () => {
+        const sender = await connect((_, data, context) =>
+            Promise.resolve({ val: context.callback(data[0]) === undefined }),
+        );
+        const result = await sender.send<boolean>(16, [undefined], { callbacks: [0] });
+        expect(result).toEqual({ val: true });
+    });
+
+    test("disposed when the call completes", async () => {
+        let stub: WxCallbackFn | undefined;
+        const sender = await connect((_, data, context) => {
+            stub = context.callback(data[0]);
+            return Promise.resolve({});
+        });
+        let called = 0;
+        await sender.sendVoid(16, [() => called++], { callbacks: [0] });
+        expect(await stub?.()).toEqual({ err: { code: "CallbackDisposed" } });
+        expect(called).toBe(0);
+    });
+
+    test("keepalive until disposed", async () => {
+        let stub: WxCallbackFn | undefined;
+        const sender = await connect((_, data, context) => {
+            stub = context.callback(data[0]);
+            return Promise.resolve({});
+        });
+        let called = 0;
+        const callback = () => {
+            called++;
+        };
+        await sender.sendVoid(16, [callback], { callbacks: [0], keepAlive: [0] });
+        expect(await stub?.()).toEqual({});
+        expect(called).toBe(1);
+
+        wxDisposeCallback(callback);
+        expect(await stub?.()).toEqual({ err: { code: "CallbackDisposed" } });
+        expect(called).toBe(1);
+    });
+
+    test("error thrown by the callback", async () => {
+        const sender = await connect(async (_, data, context) => {
+            const fail = context.callback(data[0]) as () => WxPromise<void>;
+            return fail();
+        });
+        const fail = () => {
+            throw new Error("boom");
+        };
+        const result = await sender.send<void>(16, [fail], { callbacks: [0] });
+        expect(result.err?.code).toBe("Catch");
+    });
+});
@@ -0,0 +1,148 @@
+import type { WxPromise } from "./wx_error.ts";
+
+/* eslint-disable @typescript-eslint/no-explicit-any */
+
+/** A function passed as an argument to the other side */
+export type WxCallbackFn = (...args: any[]) => any;
+
+/**
+ * Context passed to a {@link WxBusRecvHandler} by the bus, for creating
+ * the arguments that are not plain data
+ */
+export interface WxBusRecvContext {
+    /**
+     * Create a stub that calls the callback on the other side. The stub returns
+     * a `WxPromise` with the return value of the callback.
+     *
+     * Returns `undefined` if the argument is not a callback handle, for
+     * optional callbacks that are not passed. The return type is `any`
+     * so the stub can be passed as the callback type declared in the interface
+     */
+    callback: (handle: unknown) => any;
+
+    /**
+     * Get the signal that is aborted when the other side cancels the call,
+     * or when the connection is closed
+     */
+    signal: () => AbortSignal;
+}
+
+/** Functions to dispose callbacks registered with `@keepalive`, see {@link wxDisposeCallback} */
+const disposers = new WeakMap<WxCallbackFn, (() => void)[]>();
+
+/**
+ * Dispose a callback that is passed to the other side with `@keepalive`.
+ *
+ * On the calling side, this is the function that was passed in.
+ * On the handling side, this is the stub passed to the handler.
+ * After the callback is disposed, calling it from the other side
+ * will return a `CallbackDisposed` error.
+ *
+ * Callbacks without `@keepalive` are disposed automatically when the call completes.
+ */
+export const wxDisposeCallback = (fn: WxCallbackFn): void => {
+    const fns = disposers.get(fn);
+    if (!fns) {
+        return;
+    }
+    disposers.delete(fn);
+    for (const dispose of fns) {
+        dispose();
+    }
+};
+
+/**
+ * Callbacks registered on the calling side, which are called by the other side
+ *
+ * @ignore
+ */
+export class WxCallbackRegistry {
+    private callbacks: Map<number, WxCallbackFn> = new Map();
+    private nextHandle = 1;
+
+    /**
+     * Replace the callbacks at the indices in the arguments with handles.
+     *
+     * Returns the new arguments, and the handles that should be disposed when the call completes.
+     * The callbacks that are kept alive must be disposed with {@link wxDisposeCallback}
+     */
+    public register(
+        data: unknown[],
+        callbacks: number[],
+        keepAlive: number[] | undefined,
+    ): [unknown[], number[]] {
+        const out = [...data];
+        const handles: number[] = [];
+        for (const i of callbacks) {
+            const fn = out[i];
+            if (typeof fn !== "function") {
+                continue;
+            }
+            const handle = this.nextHandle++;
+            if (this.nextHandle >= 0x7fffffff) {
+                this.nextHandle = 1;
+            }
+            this.callbacks.set(handle, fn as WxCallbackFn);
+            out[i] = handle;
+            if (keepAlive?.includes(i)) {
+                addDisposer(fn as WxCallbackFn, () => this.dispose(handle));
+            } else {
+                handles.push(handle);
+            }
+        }
+        return [out, handles];
+    }
+
+    public get(handle: unknown): WxCallbackFn | undefined {
+        if (typeof handle !== "number") {
+            return undefined;
+        }
+        return this.callbacks.get(handle);
+    }
+
+    public dispose(handle: unknown) {
+        if (typeof handle === "number") {
+            this.callbacks.delete(handle);
+        }
+    }
+
+    public clear() {
+        this.callbacks.clear();
+    }
+}
+
+/**
+ * Create the stub on the handling side for a callback handle
+ *
+ * @ignore
+ */
+export const wxMakeCallbackStub = (
+    handle: unknown,
+    invoke: (handle: number, args: unknown[]) => WxPromise<unknown>,
+    dispose: (handle: number) => void,
+): WxCallbackFn | undefined => {
+    if (typeof handle !== "number") {
+        return undefined;
+    }
+    let disposed = false;
+    const stub = (...args: unknown[]): WxPromise<unknown> => {
+        if (disposed) {
+            return Promise.resolve({ err: { code: "CallbackDisposed" } });
+        }
+        return invoke(handle, args);
+    };
+    addDisposer(stub, () => {
+        disposed = true;
+        dispose(handle);
+    });
+    return stub;
+};
+
+const addDisposer = (fn: WxCallbackFn, dispose: () => void) => {
+    const fns = disposers.get(fn);
+    if (fns) {
+        fns.push(dispose);
+    } else {
+        disposers.set(fn, [dispose]);
+    }
+};
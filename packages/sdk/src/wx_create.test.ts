@@ -0,0 +1,114 @@
+import { describe, expect, test } from "vitest";
+
+import type { WxBusRecvHandler, WxProtocolBindConfig, WxProtocolBoundSender } from "./wx_bus.ts";
+import { wxLoopback } from "./wx_create.ts";
+import type { WxPromise } from "./wx_error.ts";
+
+interface Host {
+    log(message: string): WxPromise<void>;
+}
+
+interface Worker {
+    sub(a: number, b: number): WxPromise<number>;
+}
+
+// same as the generated sender classes, receivers and bind functions with `-l Host,Worker`
+
+class HostSender implements Host {
+    private sender: WxProtocolBoundSender;
+    constructor(sender: WxProtocolBoundSender) {
+        this.sender = sender;
+    }
+    public log(message: string): WxPromise<void> {
+        return this.sender.sendVoid(16 /* Host.log */, [message]);
+    }
+}
+
+class WorkerSender implements Worker {
+    private sender: WxProtocolBoundSender;
+    constructor(sender: WxProtocolBoundSender) {
+        this.sender = sender;
+    }
+    public sub(a: number, b: number): WxPromise<number> {
+        return this.sender.send<number>(17 /* Worker.sub */, [a, b]);
+    }
+}
+
+const hostRecver = (handler: Host): WxBusRecvHandler => {
+    return ((fId, args: unknown[]) => {
+        switch (fId) {
+            case 16 /* Host.log */: {
+                const [a0] = args;
+                return handler.log(a0 as string);
+            }
+        }
+        return Promise.resolve({ err: { code: "UnknownFunction" } });
+    }) as WxBusRecvHandler;
+};
+
+const workerRecver = (handler: Worker): WxBusRecvHandler => {
+    return ((fId, args: unknown[]) => {
+        switch (fId) {
+            case 17 /* Worker.sub */: {
+                const [a0, a1] = args;
+                return handler.sub(a0 as number, a1 as number);
+            }
+        }
+        return Promise.resolve({ err: { code: "UnknownFunction" } });
+    }) as WxBusRecvHandler;
+};
+
+const testHost = (handler: Worker): WxProtocolBindConfig<Host> => ({
+    protocol: "test",
+    interfaces: ["Host", "Worker"],
+    recvHandler: workerRecver(handler),
+    bindSend: (sender) => new HostSender(sender),
+});
+
+const testWorker = (handler: Host): WxProtocolBindConfig<Worker> => ({
+    protocol: "test",
+    interfaces: ["Worker", "Host"],
+    recvHandler: hostRecver(handler),
+    bindSend: (sender) => new WorkerSender(sender),
+});
+
+describe("wxLoopback", () => {
+    test("calls in both directions", async () => {
+        const logs: string[] = [];
+        const [creatorA, creatorB] = wxLoopback();
+        const [a, b] = await Promise.all([
+            creatorA({
+                api: testWorker({
+                    log: (message) => {
+                        logs.push(message);
+                        return Promise.resolve({});
+                    },
+                }),
+            }),
+            creatorB({ api: testHost({ sub: (a, b) => Promise.resolve({ val: a - b }) }) }),
+        ]);
+        if (a.err || b.err) {
+            throw new Error("failed to connect");
+        }
+        const worker = a.val.protocols.api;
+        const host = b.val.protocols.api;
+        // the arguments are in order
+        expect(await worker.sub(5, 3)).toEqual({ val: 2 });
+        expect(await host.log("hello")).toEqual({});
+        expect(logs).toEqual(["hello"]);
+
+        a.val.connection.close();
+        expect((await worker.sub(5, 3)).err?.code).toBe("Closed");
+    });
+
+    test("protocol mismatch", async () => {
+        const [creatorA, creatorB] = wxLoopback({ timeout: 100 });
+        const host = { log: () => Promise.resolve({}) };
+        const [a, b] = await Promise.all([
+            creatorA({ api: testWorker(host) }),
+            creatorB({ api: testWorker(host) }),
+        ]);
+        // the side that disagrees closes the connection
+        expect([a.err?.code, b.err?.code].sort()).toEqual(["Closed", "ProtocolDisagree"]);
+    });
+});
@@ -1,8 +1,14 @@
 /** @module wx_create */
 import { type WxBusCreator, wxCreateBus, type WxProtocolConfig } from "./wx_bus.ts";
-import { wxMakeWorkerEnd, wxMakeWorkerGlobalEnd } from "./wx_end.ts";
+import {
+    wxMakeChannel,
+    wxMakeWebSocketEnd,
+    wxMakeWorkerEnd,
+    wxMakeWorkerGlobalEnd,
+} from "./wx_end.ts";
 import { log } from "./wx_log.ts";
-import type { IFrameLike, WorkerLike } from "./wx_util.ts";
+import type { WxOnRecvFn } from "./wx_message.ts";
+import { type IFrameLike, type WebSocketLike, type WorkerLike, wxMakePromise } from "./wx_util.ts";
 import { type WxFrameLinkOptions, wxWindow, type WxWindowOpenOptions } from "./wx_window.ts";
 
 /**
@@ -132,3 +138,74 @@ export const wxWindowOwner = (
         );
     };
 };
+
+/**
+ * Create connection over a `WebSocket`, for example to a native helper process
+ * that serves the protocol using the `workex-runtime` Rust crate.
+ *
+ * Either a URL or an existing `WebSocket` can be passed in. Since messages
+ * are sent as JSON, all data in the protocol must be serializable to JSON.
+ */
+export const wxWebSocket = (
+    socket: string | WebSocketLike,
+    options?: WxWorkerCreateOptions,
+): WxBusCreator => {
+    return <TConfig extends WxProtocolConfig>(config: TConfig) => {
+        log.info("creating websocket connection");
+        return wxCreateBus(
+            true /* active side */,
+            (onRecv) => {
+                if (typeof socket === "string") {
+                    /* eslint-disable @typescript-eslint/no-explicit-any */
+                    const ws: WebSocketLike = new (globalThis as any).WebSocket(socket);
+                    /* eslint-enable @typescript-eslint/no-explicit-any */
+                    return wxMakeWebSocketEnd(ws, onRecv, options);
+                }
+                return wxMakeWebSocketEnd(socket, onRecv, options);
+            },
+            config,
+            options?.timeout,
+        );
+    };
+};
+
+/**
+ * Create a pair of connected creators in the same thread, without a Worker or window.
+ * This is mainly for testing the generated code end to end.
+ *
+ * Both creators must be called, one for each side. The connection is established
+ * after both are called. Messages are delivered asynchronously without cloning,
+ * so the transfer list is ignored.
+ */
+export const wxLoopback = (options?: WxWorkerCreateOptions): [WxBusCreator, WxBusCreator] => {
+    const { promise: onRecvA, resolve: resolveA } = wxMakePromise<WxOnRecvFn>();
+    const { promise: onRecvB, resolve: resolveB } = wxMakePromise<WxOnRecvFn>();
+    const channel = Promise.all([onRecvA, onRecvB]).then(([a, b]) => wxMakeChannel(a, b));
+    const creatorA = <TConfig extends WxProtocolConfig>(config: TConfig) => {
+        log.info("creating loopback connection (passive side)");
+        return wxCreateBus(
+            false /* passive side */,
+            async (onRecv) => {
+                resolveA(onRecv);
+                const [end] = await channel;
+                return { val: end };
+            },
+            config,
+            options?.timeout,
+        );
+    };
+    const creatorB = <TConfig extends WxProtocolConfig>(config: TConfig) => {
+        log.info("creating loopback connection (active side)");
+        return wxCreateBus(
+            true /* active side */,
+            async (onRecv) => {
+                resolveB(onRecv);
+                const [, end] = await channel;
+                return { val: end };
+            },
+            config,
+            options?.timeout,
+        );
+    };
+    return [creatorA, creatorB];
+};
@@ -1,4 +1,5 @@
 import { once } from "@pistonite/pure/sync";
+import { errstr } from "@pistonite/pure/result";
 
 import type { WxResult, WxVoid } from "./wx_error.ts";
 import {
@@ -10,7 +11,8 @@ import {
     type WxMessage,
     type WxOnRecvFn,
 } from "./wx_message.ts";
-import type { WorkerLike } from "./wx_util.ts";
+import type { WebSocketLike, WorkerLike } from "./wx_util.ts";
+import { log } from "./wx_log.ts";
 
 /**
  * Messaging primitive representing one end of an established messaging channel
@@ -24,8 +26,13 @@ import type { WorkerLike } from "./wx_util.ts";
  * first establish the handshake.
  */
 export interface WxEnd extends WxCloseController {
-    /** Send a message to the other end */
-    send: (message: WxMessage) => WxVoid;
+    /**
+     * Send a message to the other end
+     *
+     * Objects in the `transfer` list are transferred instead of cloned,
+     * if supported by the underlying messaging channel
+     */
+    send: (message: WxMessage, transfer?: unknown[]) => WxVoid;
 }
 
 export interface WxEndOptions {
@@ -90,11 +97,15 @@ export const wxMakeWorkerEnd = async (
     await start();
 
     const end: WxEnd = {
-        send: (message) => {
+        send: (message, transfer) => {
             if (isClosed()) {
                 return { err: { code: "Closed" } };
             }
-            worker.postMessage(message);
+            if (transfer?.length) {
+                worker.postMessage(message, transfer);
+            } else {
+                worker.postMessage(message);
+            }
             return {};
         },
         close,
@@ -166,11 +177,15 @@ export const wxMakeWorkerGlobalEnd = once({
 
         return {
             val: {
-                send: (message) => {
+                send: (message, transfer) => {
                     if (isClosed()) {
                         return { err: { code: "Closed" } };
                     }
-                    (globalThis as unknown as WorkerLike).postMessage(message);
+                    if (transfer?.length) {
+                        (globalThis as unknown as WorkerLike).postMessage(message, transfer);
+                    } else {
+                        (globalThis as unknown as WorkerLike).postMessage(message);
+                    }
                     return {};
                 },
                 close,
@@ -181,9 +196,117 @@ export const wxMakeWorkerGlobalEnd = once({
     },
 });
 
+/**
+ * Create a {@link WxEnd} for messaging over a `WebSocket`, for example
+ * to a native process using the `workex-runtime` Rust crate.
+ *
+ * Messages are sent as JSON text frames, so the data must be serializable to JSON.
+ * This side is the active side of the connection. The socket will be closed
+ * if the connection cannot be established, or when the `WxEnd` is closed.
+ *
+ * @param onRecv callback to register when receiving a message from the other end
+ */
+export const wxMakeWebSocketEnd = async (
+    socket: WebSocketLike,
+    onRecv: WxOnRecvFn,
+    options?: WxEndOptions,
+): Promise<WxResult<WxEnd>> => {
+    // wait for the socket to be open
+    if (socket.readyState === 0 /* CONNECTING */) {
+        const opened = await new Promise<boolean>((resolve) => {
+            socket.addEventListener("open", () => resolve(true));
+            socket.addEventListener("error", () => resolve(false));
+            socket.addEventListener("close", () => resolve(false));
+        });
+        if (!opened) {
+            return { err: { code: "Closed", message: "WebSocket failed to open" } };
+        }
+    } else if (socket.readyState !== 1 /* OPEN */) {
+        return { err: { code: "Closed", message: "WebSocket is not open" } };
+    }
+
+    const postMessage = (message: WxMessage) => {
+        socket.send(JSON.stringify(message));
+    };
+
+    const controller = wxMakeMessageController(
+        true,
+        options?.timeout,
+        onRecv,
+        (handler, signal) => {
+            socket.addEventListener(
+                "message",
+                (event: { data: unknown }) => {
+                    if (typeof event.data !== "string") {
+                        return;
+                    }
+                    let data: unknown;
+                    try {
+                        data = JSON.parse(event.data);
+                    } catch (e) {
+                        log.warn(`ignoring invalid message: ${errstr(e)}`);
+                        return;
+                    }
+                    handler({ data });
+                },
+                { signal },
+            );
+        },
+        postMessage,
+    );
+    if (controller.err) {
+        socket.close();
+        return controller;
+    }
+
+    const { start, close, isClosed, onClose } = controller.val;
+
+    // closing the socket from the other side closes the connection
+    socket.addEventListener("close", () => {
+        close();
+    });
+    // onclose, tell the other side and close the socket
+    void onClose(() => {
+        try {
+            postMessage({
+                s: wxInternalProtocol,
+                p: wxInternalProtocol,
+                m: 1,
+                f: wxFuncClose,
+                d: "close",
+            });
+        } catch {
+            // socket is already closed
+        }
+        socket.close();
+    });
+
+    const result = await start();
+    if (result.err) {
+        return result;
+    }
+
+    return {
+        val: {
+            // transfer is ignored, since messages are serialized to JSON
+            send: (message) => {
+                if (isClosed()) {
+                    return { err: { code: "Closed" } };
+                }
+                postMessage(message);
+                return {};
+            },
+            close,
+            onClose,
+            isClosed,
+        },
+    };
+};
+
 /**
  * Create a channel with 2 ends that can communicate with each other in the same context.
  * Calling `send` from one end will call the `onRecv` callback of the other end asynchronously.
+ * Messages are passed as-is without cloning, so the transfer list is ignored.
  * Calling `close` on either end will close the channel immediately (any message sent but not received
  * will also not be received).
  */
@@ -35,7 +35,11 @@ export type WxEc =
     /** The handler did not return anything */
     | "NoReturn"
     /** The stub handler in a one-direction protocol is being called unexpectedly */
-    | "UnexpectedStubCall";
+    | "UnexpectedStubCall"
+    /** Calling a callback passed in as an argument after it's disposed */
+    | "CallbackDisposed"
+    /** The call is cancelled by the signal passed to the call */
+    | "Cancelled";
 
 /** Workex Error object, containing an error code and optionally a message */
 export interface WxError {
@@ -60,3 +64,12 @@ export type WxPromise<T> = Promise<
     // eslint-disable-next-line @typescript-eslint/no-invalid-void-type
     (T extends void ? VoidOk : Ok<T>) | Err<WxError>
 >;
+
+/**
+ * Return type marker for one-way functions.
+ *
+ * The caller does not wait for the other side, and the other side never replies.
+ * Errors in the handler are logged on the receiving side.
+ */
+// eslint-disable-next-line @typescript-eslint/no-invalid-void-type
+export type WxOneWay = void;
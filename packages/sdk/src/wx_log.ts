@@ -4,3 +4,19 @@ import { logger } from "@pistonite/pure/log";
 export const log = logger("workex", {
     color: "#6f1903",
 });
+
+/** Names of the deprecated functions that were called, see {@link wxWarnDeprecatedCall} */
+const deprecatedCalled = new Set<string>();
+
+/**
+ * Log a warning the first time a deprecated function is called by the other side.
+ *
+ * This is called by the generated receivers when the CLI is run with `--log-deprecated`
+ */
+export const wxWarnDeprecatedCall = (name: string): void => {
+    if (deprecatedCalled.has(name)) {
+        return;
+    }
+    deprecatedCalled.add(name);
+    log.warn(`deprecated function ${name} is called by the other side`);
+};
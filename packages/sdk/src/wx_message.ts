@@ -57,6 +57,14 @@ export interface WxPayload {
  */
 export const wxInternalProtocol = "workex" as const;
 
+/**
+ * Message ID for one-way calls. The receiver never replies to these calls,
+ * so the sender does not keep track of them
+ *
+ * @ignore
+ */
+export const wxOneWayMessageId = 0 as const;
+
 /** Func ID for return value @ignore */
 export const wxFuncReturn = 0 as const;
 
@@ -102,6 +110,65 @@ export const wxFuncClose = 3 as const;
  */
 export const wxFuncProtocol = 4 as const;
 
+/**
+ * Func IDs used for streaming return values. The message ID is the same
+ * as the call that returned the stream. This is handled by the Bus layer
+ *
+ * Handling side sends:
+ * - chunk: d is the value of the chunk
+ * - end: the stream finished successfully
+ * - error: d is the error, and the stream is finished
+ *
+ * Calling side sends:
+ * - pull: d is the number of chunks consumed, that the handling side
+ *   can send more
+ * - cancel: the calling side stopped iterating the stream
+ *
+ * @ignore
+ */
+export const wxFuncStreamChunk = 5 as const;
+/** See {@link wxFuncStreamChunk} @ignore */
+export const wxFuncStreamEnd = 6 as const;
+/** See {@link wxFuncStreamChunk} @ignore */
+export const wxFuncStreamError = 7 as const;
+/** See {@link wxFuncStreamChunk} @ignore */
+export const wxFuncStreamPull = 8 as const;
+/** See {@link wxFuncStreamChunk} @ignore */
+export const wxFuncStreamCancel = 9 as const;
+
+/**
+ * Func ID used to call a callback passed in as an argument. This is handled by the Bus layer
+ *
+ * Handling side sends:
+ * - m: message id for the reply, which is a regular return (or return error)
+ * - d: the handle of the callback, followed by the arguments
+ *
+ * @ignore
+ */
+export const wxFuncCallback = 10 as const;
+
+/**
+ * Func ID used to dispose a callback passed in as an argument, from the handling side.
+ * This is handled by the Bus layer
+ *
+ * - m: unused
+ * - d: the handle of the callback
+ *
+ * @ignore
+ */
+export const wxFuncCallbackDispose = 11 as const;
+
+/**
+ * Func ID used to cancel a call, when the signal passed to the call is aborted.
+ * This is handled by the Bus layer
+ *
+ * - m: message id of the call to cancel
+ * - d: unused
+ *
+ * @ignore
+ */
+export const wxFuncCancel = 12 as const;
+
 /**
  * Message object with the `s` field set to "workex" to not be confused with messages
  * with other libraries
@@ -0,0 +1,84 @@
+import { describe, expect, test } from "vitest";
+
+import type { WxOneWay, WxPromise } from "./wx_error.ts";
+import { WxMock } from "./wx_mock.ts";
+import type { WxStream } from "./wx_stream.ts";
+
+interface Api {
+    add(a: number, b: number): WxPromise<number>;
+    log(message: string): WxPromise<void>;
+    notify(event: string): WxOneWay;
+    search(query: string): WxStream<string>;
+}
+
+/** Same as the mock generated by the CLI with `--mock` */
+class ApiMock extends WxMock<Api> implements Api {
+    public add(a: number, b: number): WxPromise<number> {
+        return this.wxCall("add", [a, b]);
+    }
+
+    public log(message: string): WxPromise<void> {
+        return this.wxCallVoid("log", [message]);
+    }
+
+    public notify(event: string): WxOneWay {
+        this.wxOneWay("notify", [event]);
+    }
+
+    public search(query: string): WxStream<string> {
+        return this.wxStream("search", [query]);
+    }
+}
+
+describe("WxMock", () => {
+    test("default results", async () => {
+        const mock = new ApiMock();
+        expect((await mock.add(1, 2)).err?.code).toBe("Fail");
+        expect(await mock.log("hello")).toEqual({});
+        const items = [];
+        for await (const item of mock.search("a")) {
+            items.push(item);
+        }
+        expect(items).toEqual([]);
+    });
+
+    test("record calls", async () => {
+        const mock = new ApiMock();
+        await mock.add(1, 2);
+        mock.notify("event");
+        await mock.add(3, 4);
+        expect(mock.wxCalls).toEqual([
+            { fn: "add", args: [1, 2] },
+            { fn: "notify", args: ["event"] },
+            { fn: "add", args: [3, 4] },
+        ]);
+        expect(mock.wxCallsTo("add")).toEqual([
+            [1, 2],
+            [3, 4],
+        ]);
+        mock.wxReset();
+        expect(mock.wxCalls).toEqual([]);
+    });
+
+    test("configured results", async () => {
+        const mock = new ApiMock()
+            .wxSetResult("add", { val: 3 })
+            .wxSetResultOnce("add", { err: { code: "First" } })
+            .wxSetResult("search", [{ val: "x" }, { err: { code: "End" } }]);
+        expect(await mock.add(1, 2)).toEqual({ err: { code: "First" } });
+        expect(await mock.add(1, 2)).toEqual({ val: 3 });
+        expect(await mock.add(1, 2)).toEqual({ val: 3 });
+        const items = [];
+        for await (const item of mock.search("a")) {
+            items.push(item);
+        }
+        expect(items).toEqual([{ val: "x" }, { err: { code: "End" } }]);
+    });
+
+    test("delay", async () => {
+        const mock = new ApiMock().wxSetResult("log", {}, 50);
+        const start = Date.now();
+        expect(await mock.log("hello")).toEqual({});
+        expect(Date.now() - start).toBeGreaterThanOrEqual(45);
+    });
+});
@@ -0,0 +1,145 @@
+import type { WxResult } from "./wx_error.ts";
+
+/* eslint-disable @typescript-eslint/no-explicit-any */
+
+/** Names of the functions in an interface */
+export type WxMockKey<T> = keyof T & string;
+
+/** Arguments of a function in an interface */
+export type WxMockArgs<F> = F extends (...args: infer A) => any ? A : never;
+
+/**
+ * Result to return from a mocked function.
+ *
+ * For functions returning `WxPromise`, this is the value the promise resolves to,
+ * such as `{ val: 42 }` or `{ err: { code: "Fail" } }`. For functions returning
+ * `WxStream`, this is the items in the stream.
+ */
+export type WxMockResult<F> = F extends (...args: any[]) => infer R
+    ? R extends Promise<infer P>
+        ? P
+        : R extends AsyncIterable<infer I>
+          ? I[]
+          : never
+    : never;
+
+/** A call recorded by a {@link WxMock} */
+export type WxMockCall<T> = {
+    [K in WxMockKey<T>]: { fn: K; args: WxMockArgs<T[K]> };
+}[WxMockKey<T>];
+
+interface MockEntry {
+    result: unknown;
+    delay: number;
+}
+
+/**
+ * Base class for the mock implementations generated by the CLI with `--mock`.
+ *
+ * The mock records every call and its arguments in `wxCalls`. The results
+ * are configured with `wxSetResult` and `wxSetResultOnce`. If no result is configured,
+ * functions returning `WxPromise<void>` succeed, streams are empty, and other functions
+ * return a `Fail` error.
+ *
+ * The members are prefixed with `wx` to not conflict with the functions in the interface.
+ */
+export class WxMock<T> {
+    /** All calls made to the mock, in order */
+    public wxCalls: WxMockCall<T>[] = [];
+
+    private wxResults: Map<string, MockEntry> = new Map();
+    private wxResultsOnce: Map<string, MockEntry[]> = new Map();
+
+    /**
+     * Set the result for all calls to the function. If `delay` is set,
+     * the result is returned after that many milliseconds
+     */
+    public wxSetResult<K extends WxMockKey<T>>(
+        fn: K,
+        result: WxMockResult<T[K]>,
+        delay = 0,
+    ): this {
+        this.wxResults.set(fn, { result, delay });
+        return this;
+    }
+
+    /**
+     * Set the result for the next call to the function. Multiple results
+     * can be queued, and are used before the one set with `wxSetResult`
+     */
+    public wxSetResultOnce<K extends WxMockKey<T>>(
+        fn: K,
+        result: WxMockResult<T[K]>,
+        delay = 0,
+    ): this {
+        const queue = this.wxResultsOnce.get(fn);
+        if (queue) {
+            queue.push({ result, delay });
+        } else {
+            this.wxResultsOnce.set(fn, [{ result, delay }]);
+        }
+        return this;
+    }
+
+    /** Get the arguments of each call to the function, in order */
+    public wxCallsTo<K extends WxMockKey<T>>(fn: K): WxMockArgs<T[K]>[] {
+        return this.wxCalls.filter((call) => call.fn === fn).map((call) => call.args);
+    }
+
+    /** Clear the recorded calls and the configured results */
+    public wxReset() {
+        this.wxCalls = [];
+        this.wxResults.clear();
+        this.wxResultsOnce.clear();
+    }
+
+    /** Record a call to a function returning `WxPromise<T>` */
+    protected async wxCall(fn: WxMockKey<T>, args: unknown[]): Promise<any> {
+        const entry = this.wxRecord(fn, args);
+        if (!entry) {
+            return { err: { code: "Fail", message: `no mock result for ${fn}` } };
+        }
+        await wait(entry.delay);
+        return entry.result;
+    }
+
+    /** Record a call to a function returning `WxPromise<void>` */
+    protected async wxCallVoid(fn: WxMockKey<T>, args: unknown[]): Promise<any> {
+        const entry = this.wxRecord(fn, args);
+        if (!entry) {
+            return {};
+        }
+        await wait(entry.delay);
+        return entry.result;
+    }
+
+    /** Record a call to a function returning `WxStream<T>` */
+    protected wxStream(fn: WxMockKey<T>, args: unknown[]): AsyncIterable<any> {
+        const entry = this.wxRecord(fn, args);
+        return (async function* () {
+            if (!entry) {
+                return;
+            }
+            await wait(entry.delay);
+            yield* entry.result as WxResult<unknown>[];
+        })();
+    }
+
+    /** Record a call to a one-way function returning `WxOneWay` */
+    protected wxOneWay(fn: WxMockKey<T>, args: unknown[]): void {
+        this.wxRecord(fn, args);
+    }
+
+    private wxRecord(fn: WxMockKey<T>, args: unknown[]): MockEntry | undefined {
+        this.wxCalls.push({ fn, args } as WxMockCall<T>);
+        const once = this.wxResultsOnce.get(fn)?.shift();
+        return once || this.wxResults.get(fn);
+    }
+}
+
+const wait = (delay: number): Promise<void> | undefined => {
+    if (delay <= 0) {
+        return undefined;
+    }
+    return new Promise((resolve) => setTimeout(resolve, delay));
+};
@@ -0,0 +1,94 @@
+import { describe, expect, test } from "vitest";
+
+import type { WxBusRecvHandler, WxProtocolBoundSender } from "./wx_bus.ts";
+import { wxLoopback } from "./wx_create.ts";
+import type { WxResult } from "./wx_error.ts";
+import { wxStreamWindow } from "./wx_stream.ts";
+
+/** Connect a sender to the handler through a loopback bus */
+const connect = async (recvHandler: WxBusRecvHandler) => {
+    const [passive, active] = wxLoopback({ timeout: 1000 });
+    const bindSend = (sender: WxProtocolBoundSender) => sender;
+    const [handlerSide, callerSide] = await Promise.all([
+        passive({ test: { protocol: "test", interfaces: ["Caller", "Api"], recvHandler, bindSend } }),
+        active({
+            test: {
+                protocol: "test",
+                interfaces: ["Api", "Caller"],
+                recvHandler: () => Promise.resolve({ err: { code: "UnknownFunction" } }),
+                bindSend,
+            },
+        }),
+    ]);
+    if (handlerSide.err || callerSide.err) {
+        throw new Error("failed to connect");
+    }
+    return callerSide.val.protocols.test;
+};
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+describe("WxStream", () => {
+    test("round trip", async () => {
+        async function* count(n: number): AsyncIterable<WxResult<number>> {
+            for (let i = 0; i < n; i++) {
+                yield { val: i };
+            }
+            yield { err: { code: "Done" } };
+        }
+        const sender = await connect((_, data) =>
+            Promise.resolve({ stream: count(data[0] as number) }),
+        );
+        const items = [];
+        for await (const item of sender.sendStream<number>(16, [3])) {
+            items.push(item);
+        }
+        expect(items).toEqual([{ val: 0 }, { val: 1 }, { val: 2 }, { err: { code: "Done" } }]);
+    });
+
+    test("cancel when the loop breaks", async () => {
+        let produced = 0;
+        let finished = false;
+        async function* forever(): AsyncIterable<WxResult<number>> {
+            try {
+                while (true) {
+                    yield { val: produced++ };
+                }
+            } finally {
+                finished = true;
+            }
+        }
+        const sender = await connect(() => Promise.resolve({ stream: forever() }));
+        for await (const item of sender.sendStream<number>(16, [])) {
+            if (item.val === 2) {
+                break;
+            }
+        }
+        await sleep(50);
+        expect(finished).toBe(true);
+        expect(produced).toBeLessThanOrEqual(wxStreamWindow + 3);
+    });
+
+    test("backpressure", async () => {
+        let produced = 0;
+        async function* many(): AsyncIterable<WxResult<number>> {
+            while (produced < 100) {
+                yield { val: produced++ };
+            }
+        }
+        const sender = await connect(() => Promise.resolve({ stream: many() }));
+        const iterator = sender.sendStream<number>(16, [])[Symbol.asyncIterator]();
+        expect(await iterator.next()).toEqual({ value: { val: 0 }, done: false });
+        // the other side stops after sending a full window without being consumed
+        await sleep(50);
+        expect(produced).toBe(wxStreamWindow);
+
+        // consuming half of the window lets the other side send more
+        for (let i = 1; i < wxStreamWindow / 2; i++) {
+            expect(await iterator.next()).toEqual({ value: { val: i }, done: false });
+        }
+        await sleep(50);
+        expect(produced).toBe(wxStreamWindow + wxStreamWindow / 2);
+        await iterator.return?.();
+    });
+});
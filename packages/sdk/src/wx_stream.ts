@@ -0,0 +1,244 @@
+import { errstr } from "@pistonite/pure/result";
+
+import type { WxError, WxResult, WxVoid } from "./wx_error.ts";
+import {
+    wxFuncReturnError,
+    wxFuncStreamCancel,
+    wxFuncStreamChunk,
+    wxFuncStreamEnd,
+    wxFuncStreamError,
+    wxFuncStreamPull,
+} from "./wx_message.ts";
+
+/**
+ * Return type for functions that return the result incrementally.
+ *
+ * On the calling side, the stream is iterated with `for await`. The call is sent
+ * when the iteration starts. If the call fails, the error is the last item in the stream.
+ * Breaking out of the loop early cancels the stream on the other side.
+ *
+ * On the handling side, the function is usually implemented as an async generator:
+ * ```typescript
+ * async *search(query: string): WxStream<string> {
+ *     for (const match of matches) {
+ *         yield { val: match };
+ *     }
+ * }
+ * ```
+ * Yielding an error ends the stream with that error.
+ */
+export type WxStream<T> = AsyncIterable<WxResult<T>>;
+
+/**
+ * Number of chunks the handling side can send before waiting
+ * for the calling side to consume them
+ *
+ * @ignore
+ */
+export const wxStreamWindow = 16;
+
+/**
+ * Handle for an open stream on the calling side, used by {@link WxStreamReader}
+ *
+ * @ignore
+ */
+export interface WxStreamHandle {
+    /** Send a control message (pull or cancel) to the other side */
+    send: (f: number, d?: unknown) => void;
+    /** Stop receiving messages for the stream */
+    close: () => void;
+}
+
+/**
+ * Function to send the call for a stream, and register the callback to receive
+ * messages for it
+ *
+ * @ignore
+ */
+export type WxStreamOpenFn = (
+    onMessage: (f: number, d: unknown) => void,
+) => WxResult<WxStreamHandle>;
+
+/**
+ * Iterator on the calling side that receives the chunks of a stream.
+ *
+ * @ignore
+ */
+export class WxStreamReader<T> implements AsyncIterator<WxResult<T>, undefined> {
+    private open: WxStreamOpenFn;
+    private timeout: number;
+
+    private handle: WxStreamHandle | undefined;
+    private state: "init" | "open" | "ended" = "init";
+    private queue: WxResult<T>[] = [];
+    private wake: (() => void) | undefined;
+    /** Number of chunks consumed but not pulled again */
+    private consumed = 0;
+
+    constructor(open: WxStreamOpenFn, timeout: number) {
+        this.open = open;
+        this.timeout = timeout;
+    }
+
+    public async next(): Promise<IteratorResult<WxResult<T>, undefined>> {
+        if (this.state === "init") {
+            const res = this.open((f, d) => this.onMessage(f, d));
+            if (res.err) {
+                this.state = "ended";
+                return { value: { err: res.err }, done: false };
+            }
+            this.handle = res.val;
+            this.state = "open";
+        }
+        if (!this.queue.length && this.state === "open") {
+            // the timeout is for waiting on each chunk, not the whole stream
+            const received = await new Promise<boolean>((resolve) => {
+                const timer = setTimeout(() => resolve(false), this.timeout);
+                this.wake = () => {
+                    clearTimeout(timer);
+                    resolve(true);
+                };
+            });
+            this.wake = undefined;
+            if (!received) {
+                this.cancel();
+                return { value: { err: { code: "Timeout" } }, done: false };
+            }
+        }
+        const item = this.queue.shift();
+        if (!item) {
+            return { value: undefined, done: true };
+        }
+        if (this.state === "open") {
+            // allow the other side to send more after half of the window is consumed
+            this.consumed++;
+            if (this.consumed >= wxStreamWindow / 2) {
+                this.handle?.send(wxFuncStreamPull, this.consumed);
+                this.consumed = 0;
+            }
+        }
+        return { value: item, done: false };
+    }
+
+    public async return(): Promise<IteratorResult<WxResult<T>, undefined>> {
+        this.cancel();
+        this.queue = [];
+        return { value: undefined, done: true };
+    }
+
+    /**
+     * Stop the stream and notify the other side. If `err` is provided,
+     * it's added as the last item of the stream
+     */
+    public cancel(err?: WxError) {
+        if (this.state === "open") {
+            this.handle?.send(wxFuncStreamCancel);
+            this.handle?.close();
+            if (err) {
+                this.queue.push({ err });
+            }
+        }
+        this.state = "ended";
+        this.wake?.();
+    }
+
+    private onMessage(f: number, d: unknown) {
+        if (this.state !== "open") {
+            return;
+        }
+        if (f === wxFuncStreamChunk) {
+            this.queue.push({ val: d as T });
+        } else {
+            if (f === wxFuncStreamError || f === wxFuncReturnError) {
+                this.queue.push({ err: d as WxError });
+            } else if (f !== wxFuncStreamEnd) {
+                this.queue.push({
+                    err: { code: "Fail", message: "the other side did not return a stream" },
+                });
+            }
+            this.state = "ended";
+            this.handle?.close();
+        }
+        this.wake?.();
+    }
+}
+
+/**
+ * Result returned by a {@link WxBusRecvHandler} for functions that return
+ * a {@link WxStream}
+ */
+export interface WxStreamResult {
+    stream: AsyncIterable<WxResult<unknown>>;
+    err?: undefined;
+}
+
+/**
+ * Iterate the stream returned by a handler, and send the chunks to the calling side.
+ *
+ * `setControl` is used to register the callback for control messages (pull or cancel)
+ * from the calling side, and `undefined` is passed to unregister it when the stream ends.
+ * The callback is also called with cancel when the bus is closed.
+ *
+ * @ignore
+ */
+export const wxPumpStream = async (
+    stream: AsyncIterable<WxResult<unknown>>,
+    send: (f: number, d: unknown) => WxVoid,
+    setControl: (control: ((f: number, d: unknown) => void) | undefined) => void,
+): Promise<WxVoid> => {
+    let credit = wxStreamWindow;
+    let cancelled = false;
+    let wake: (() => void) | undefined;
+    setControl((f, d) => {
+        if (f === wxFuncStreamCancel) {
+            cancelled = true;
+        } else if (f === wxFuncStreamPull && typeof d === "number" && d > 0) {
+            credit += d;
+        }
+        wake?.();
+    });
+
+    try {
+        const iterator = stream[Symbol.asyncIterator]();
+        while (true) {
+            // backpressure: wait for the calling side to consume the chunks
+            while (credit <= 0 && !cancelled) {
+                await new Promise<void>((resolve) => {
+                    wake = resolve;
+                });
+                wake = undefined;
+            }
+            if (cancelled) {
+                await iterator.return?.();
+                return {};
+            }
+            const next = await iterator.next();
+            if (cancelled) {
+                if (!next.done) {
+                    await iterator.return?.();
+                }
+                return {};
+            }
+            if (next.done) {
+                return send(wxFuncStreamEnd, undefined);
+            }
+            if (next.value.err) {
+                await iterator.return?.();
+                return send(wxFuncStreamError, next.value.err);
+            }
+            credit--;
+            const res = send(wxFuncStreamChunk, next.value.val);
+            if (res.err) {
+                await iterator.return?.();
+                return res;
+            }
+        }
+    } catch (e) {
+        return send(wxFuncStreamError, {
+            code: "Catch",
+            message: errstr(e),
+        } satisfies WxError);
+    } finally {
+        setControl(undefined);
+    }
+};
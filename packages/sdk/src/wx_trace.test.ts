@@ -0,0 +1,109 @@
+import { afterEach, describe, expect, test } from "vitest";
+
+import type { WxBusRecvHandler, WxProtocolBoundSender } from "./wx_bus.ts";
+import { wxLoopback } from "./wx_create.ts";
+import type { WxPromise } from "./wx_error.ts";
+import { type WxTraceEvent, wxSetTraceHook, wxTraceRecver, wxTraceSender } from "./wx_trace.ts";
+
+interface Api {
+    add(a: number, b: number): WxPromise<number>;
+}
+
+/** Connect a traced sender of Api to a traced receiver, like the code generated with `--trace` */
+const connect = async (recvHandler: WxBusRecvHandler) => {
+    const [passive, active] = wxLoopback({ timeout: 1000 });
+    const [handlerSide, callerSide] = await Promise.all([
+        passive({
+            test: {
+                protocol: "test",
+                interfaces: ["Caller", "Api"],
+                recvHandler: wxTraceRecver(recvHandler, "test", "Api", { add: 16 }),
+                bindSend: (sender: WxProtocolBoundSender) => sender,
+            },
+        }),
+        active({
+            test: {
+                protocol: "test",
+                interfaces: ["Api", "Caller"],
+                recvHandler: () => Promise.resolve({ err: { code: "UnknownFunction" } }),
+                bindSend: (sender: WxProtocolBoundSender): Api => {
+                    const impl = {
+                        add: (a: number, b: number) => sender.send<number>(16, [a, b]),
+                    };
+                    return wxTraceSender(impl, "test", "Api", { add: 16 });
+                },
+            },
+        }),
+    ]);
+    if (handlerSide.err || callerSide.err) {
+        throw new Error("failed to connect");
+    }
+    return callerSide.val.protocols.test;
+};
+
+describe("trace", () => {
+    afterEach(() => {
+        wxSetTraceHook(undefined);
+    });
+
+    test("send and recv events", async () => {
+        const events: WxTraceEvent[] = [];
+        wxSetTraceHook((event) => events.push(event));
+        const api = await connect((_, data) =>
+            Promise.resolve({ val: (data[0] as number) + (data[1] as number) }),
+        );
+        expect(await api.add(1, 2)).toEqual({ val: 3 });
+        // the receiving side finishes first
+        expect(events.map(({ duration: _, ...event }) => event)).toEqual([
+            {
+                side: "recv",
+                protocol: "test",
+                interface: "Api",
+                function: "add",
+                fId: 16,
+                args: [1, 2],
+                result: { val: 3 },
+                err: undefined,
+            },
+            {
+                side: "send",
+                protocol: "test",
+                interface: "Api",
+                function: "add",
+                fId: 16,
+                args: [1, 2],
+                result: { val: 3 },
+                err: undefined,
+            },
+        ]);
+        expect(events[1].duration).toBeGreaterThanOrEqual(0);
+    });
+
+    test("error code", async () => {
+        const events: WxTraceEvent[] = [];
+        wxSetTraceHook((event) => events.push(event));
+        const api = await connect(() => Promise.resolve({ err: { code: "Fail" } }));
+        expect(await api.add(1, 2)).toEqual({ err: { code: "Fail" } });
+        expect(events.map((event) => [event.side, event.err])).toEqual([
+            ["recv", "Fail"],
+            ["send", "Fail"],
+        ]);
+    });
+
+    test("no hook", async () => {
+        const events: WxTraceEvent[] = [];
+        wxSetTraceHook((event) => events.push(event));
+        wxSetTraceHook(undefined);
+        const api = await connect(() => Promise.resolve({ val: 0 }));
+        expect(await api.add(1, 2)).toEqual({ val: 0 });
+        expect(events).toEqual([]);
+    });
+
+    test("error in the hook does not fail the call", async () => {
+        wxSetTraceHook(() => {
+            throw new Error("boom");
+        });
+        const api = await connect(() => Promise.resolve({ val: 3 }));
+        expect(await api.add(1, 2)).toEqual({ val: 3 });
+    });
+});
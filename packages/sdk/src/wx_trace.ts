@@ -0,0 +1,157 @@
+import type { WxBusRecvHandler } from "./wx_bus.ts";
+import type { WxEc } from "./wx_error.ts";
+import { log } from "./wx_log.ts";
+
+/* eslint-disable @typescript-eslint/no-explicit-any */
+
+/** A traced call, passed to the hook set with {@link wxSetTraceHook} */
+export interface WxTraceEvent {
+    /**
+     * `"send"` if the call is made from this side, `"recv"` if the call
+     * is made from the other side and handled on this side
+     */
+    side: "send" | "recv";
+    /** The protocol identifier */
+    protocol: string;
+    /** The interface that the function belongs to */
+    interface: string;
+    /** Name of the function */
+    function: string;
+    /** The function ID used in the messages */
+    fId: number;
+    /**
+     * Arguments of the call. On the receiving side, callbacks are the handles
+     * sent by the other side, and the signal is not included
+     */
+    args: unknown[];
+    /**
+     * Time in milliseconds from the start of the call to the result.
+     * For one-way calls and streams, this only includes sending the call
+     * or creating the stream
+     */
+    duration: number;
+    /**
+     * The result of the call, such as `{ val: 42 }` or `{ err: { code: "Timeout" } }`.
+     * This is `undefined` for one-way calls and streams on the calling side
+     */
+    result?: unknown;
+    /** The error code, if the call returned or threw an error */
+    err?: WxEc;
+}
+
+/** Hook that receives the traced calls */
+export type WxTraceHook = (event: WxTraceEvent) => void;
+
+let traceHook: WxTraceHook | undefined;
+
+/**
+ * Set the hook that receives the calls traced by the generated code.
+ * Pass `undefined` to remove the hook.
+ *
+ * Only the code generated by the CLI with `--trace` is traced.
+ */
+export const wxSetTraceHook = (hook: WxTraceHook | undefined): void => {
+    traceHook = hook;
+};
+
+/**
+ * Wrap a generated sender so the calls are traced.
+ *
+ * `functions` maps the function names to the function IDs
+ *
+ * @ignore
+ */
+export const wxTraceSender = <T extends object>(
+    impl: T,
+    protocol: string,
+    iface: string,
+    functions: Record<string, number>,
+): T => {
+    const traced = Object.create(impl);
+    for (const name in functions) {
+        const fId = functions[name];
+        const fn = (impl as any)[name] as (...args: unknown[]) => unknown;
+        traced[name] = (...args: unknown[]) => {
+            const start = performance.now();
+            return trace(fn.apply(impl, args), (result, err) => ({
+                side: "send",
+                protocol,
+                interface: iface,
+                function: name,
+                fId,
+                args,
+                duration: performance.now() - start,
+                result,
+                err,
+            }));
+        };
+    }
+    return traced;
+};
+
+/**
+ * Wrap a generated receiver so the calls are traced.
+ *
+ * `functions` maps the function names to the function IDs
+ *
+ * @ignore
+ */
+export const wxTraceRecver = (
+    handler: WxBusRecvHandler,
+    protocol: string,
+    iface: string,
+    functions: Record<string, number>,
+): WxBusRecvHandler => {
+    const names = new Map<number, string>();
+    for (const name in functions) {
+        names.set(functions[name], name);
+    }
+    return ((fId, args, context) => {
+        const start = performance.now();
+        return trace(handler(fId, args, context), (result, err) => ({
+            side: "recv",
+            protocol,
+            interface: iface,
+            function: names.get(fId) ?? "",
+            fId,
+            args,
+            duration: performance.now() - start,
+            result,
+            err,
+        }));
+    }) as WxBusRecvHandler;
+};
+
+/** Call the hook with the event when the result is available, and return the result */
+const trace = <T>(
+    result: T,
+    makeEvent: (result: unknown, err: WxEc | undefined) => WxTraceEvent,
+): T => {
+    if (!traceHook) {
+        return result;
+    }
+    if (!(result instanceof Promise)) {
+        // one-way calls and streams
+        callHook(makeEvent(undefined, undefined));
+        return result;
+    }
+    return result.then(
+        (value) => {
+            callHook(makeEvent(value, value?.err?.code));
+            return value;
+        },
+        (e) => {
+            callHook(makeEvent(undefined, "Catch"));
+            throw e;
+        },
+    ) as T;
+};
+
+const callHook = (event: WxTraceEvent) => {
+    try {
+        traceHook?.(event);
+    } catch (e) {
+        log.error("error in trace hook");
+        log.error(e);
+    }
+};
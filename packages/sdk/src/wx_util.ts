@@ -33,7 +33,7 @@ export const wxWrapHandler = <T extends (...args: any[]) => any>(
  */
 export interface WorkerLike {
     /* eslint-disable @typescript-eslint/no-explicit-any */
-    postMessage: (message: any) => any;
+    postMessage: (message: any, transfer?: any[]) => any;
     addEventListener: (
         type: string,
         listener: (event: any) => any,
@@ -55,7 +55,7 @@ export interface IFrameLike {
  */
 export interface WindowLike {
     /* eslint-disable @typescript-eslint/no-explicit-any */
-    postMessage: (message: any, targetOrigin: string) => any;
+    postMessage: (message: any, targetOrigin: string, transfer?: any[]) => any;
     addEventListener: (
         type: string,
         listener: (event: any) => any,
@@ -71,3 +71,20 @@ export interface WindowLike {
     closed?: boolean;
     /* eslint-enable @typescript-eslint/no-explicit-any */
 }
+
+/**
+ * Things that looks like a `WebSocket`
+ * @ignore
+ */
+export interface WebSocketLike {
+    /* eslint-disable @typescript-eslint/no-explicit-any */
+    readyState: number;
+    send: (data: string) => any;
+    addEventListener: (
+        type: string,
+        listener: (event: any) => any,
+        options?: { signal?: any },
+    ) => any;
+    close: () => void;
+    /* eslint-enable @typescript-eslint/no-explicit-any */
+}
@@ -307,11 +307,15 @@ const createOwnerFnFor = (ownerWindow: WindowLike, origin: string): WxWindow["ow
             await start();
 
             const end: WxEnd = {
-                send: (message) => {
+                send: (message, transfer) => {
                     if (isClosed()) {
                         return { err: { code: "Closed" } };
                     }
-                    ownerWindow.postMessage(message, ownerOrigin);
+                    if (transfer?.length) {
+                        ownerWindow.postMessage(message, ownerOrigin, transfer);
+                    } else {
+                        ownerWindow.postMessage(message, ownerOrigin);
+                    }
                     return {};
                 },
                 close,
@@ -394,11 +398,15 @@ const linkToTargetWindowCrossOrigin = async (
     await start();
 
     const end: WxEnd = {
-        send: (message) => {
+        send: (message, transfer) => {
             if (isClosed()) {
                 return { err: { code: "Closed" } };
             }
-            targetWindow.postMessage(message, targetOrigin);
+            if (transfer?.length) {
+                targetWindow.postMessage(message, targetOrigin, transfer);
+            } else {
+                targetWindow.postMessage(message, targetOrigin);
+            }
             return {};
         },
         close,